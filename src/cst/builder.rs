@@ -0,0 +1,407 @@
+use crate::cst::cst::{GreenElement, GreenNode, GreenToken, SyntaxKind, SyntaxNode};
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{Kind, Span, Token};
+use crate::parser::ast::*;
+use crate::parser::parser::Parser;
+use std::rc::Rc;
+
+/// The lossless tree for a source file together with the AST it was shaped by.
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub module: Module,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Lexes and parses `source`, keeping every token and all trivia.
+pub fn parse_tree(source: &str) -> SyntaxTree {
+    let mut lex = Lexer::new(source.chars().collect());
+    lex.lex();
+    let mut result = Parser::new(lex.tokens.clone()).parse();
+    let root = build_tree(source, &lex.tokens, &result.module);
+    lex.diagnostics.append(&mut result.diagnostics);
+    SyntaxTree {
+        root,
+        module: result.module,
+        diagnostics: lex.diagnostics,
+    }
+}
+
+// Nesting comes from the AST spans: every node claims the tokens inside its
+// span that no child claims. Trivia before a node's first token and after its
+// last one belongs to the parent, so nodes start and end on real tokens.
+pub fn build_tree(source: &str, tokens: &[Token], module: &Module) -> SyntaxNode {
+    let chars: Vec<char> = source.chars().collect();
+    let mut builder = TreeBuilder {
+        tokens: leaves(&chars, tokens),
+        cursor: 0,
+    };
+    // the root is unbounded so it also keeps trailing trivia and anything the
+    // lexer could not place
+    let shape = Shape::new(SyntaxKind::SourceFile, Span::new(0, usize::MAX))
+        .with(module.items.iter().map(item).collect());
+    SyntaxNode::new_root(Rc::new(builder.node(&shape)))
+}
+
+// The token stream with the gaps between tokens filled in, so that the
+// concatenated text is exactly the source.
+fn leaves(chars: &[char], tokens: &[Token]) -> Vec<(Span, Rc<GreenToken>)> {
+    let mut leaves = Vec::new();
+    let mut pos = 0;
+    for tok in tokens {
+        if tok.span.is_empty() || tok.span.start < pos || tok.span.end > chars.len() {
+            continue;
+        }
+        gap(chars, pos, tok.span.start, &mut leaves);
+        let text: String = chars[tok.span.start..tok.span.end].iter().collect();
+        leaves.push((tok.span, Rc::new(GreenToken::new(tok.kind.clone(), text))));
+        pos = tok.span.end;
+    }
+    gap(chars, pos, chars.len(), &mut leaves);
+    leaves
+}
+
+// whitespace runs become trivia, anything the lexer skipped is kept as an error
+fn gap(chars: &[char], start: usize, end: usize, leaves: &mut Vec<(Span, Rc<GreenToken>)>) {
+    let mut run_start = start;
+    while run_start < end {
+        let is_space = chars[run_start].is_whitespace();
+        let mut run_end = run_start;
+        while run_end < end && chars[run_end].is_whitespace() == is_space {
+            run_end += 1;
+        }
+        let text: String = chars[run_start..run_end].iter().collect();
+        let kind = match is_space {
+            true => Kind::WhiteSpace,
+            false => Kind::Err(text.clone()),
+        };
+        leaves.push((
+            Span::new(run_start, run_end),
+            Rc::new(GreenToken::new(kind, text)),
+        ));
+        run_start = run_end;
+    }
+}
+
+struct TreeBuilder {
+    tokens: Vec<(Span, Rc<GreenToken>)>,
+    cursor: usize,
+}
+
+impl TreeBuilder {
+    fn node(&mut self, shape: &Shape) -> GreenNode {
+        let mut children = Vec::new();
+        for child in &shape.children {
+            self.take_before(child.span.start, &mut children);
+            children.push(GreenElement::Node(Rc::new(self.node(child))));
+        }
+        self.take_until(shape.span.end, &mut children);
+        GreenNode::new(shape.kind, children)
+    }
+
+    fn take_before(&mut self, start: usize, out: &mut Vec<GreenElement>) {
+        while let Some((span, token)) = self.tokens.get(self.cursor) {
+            if span.start >= start {
+                break;
+            }
+            out.push(GreenElement::Token(token.clone()));
+            self.cursor += 1;
+        }
+    }
+
+    fn take_until(&mut self, end: usize, out: &mut Vec<GreenElement>) {
+        while let Some((span, token)) = self.tokens.get(self.cursor) {
+            if span.end > end || (span.is_empty() && span.start >= end) {
+                break;
+            }
+            let is_trivia = matches!(token.kind(), Kind::WhiteSpace | Kind::Comment);
+            // trailing trivia is left for the parent
+            if is_trivia && end != usize::MAX && !self.real_token_before(end) {
+                break;
+            }
+            out.push(GreenElement::Token(token.clone()));
+            self.cursor += 1;
+        }
+    }
+
+    // whether a non-trivia token follows the cursor and still ends inside `end`
+    fn real_token_before(&self, end: usize) -> bool {
+        self.tokens[self.cursor..]
+            .iter()
+            .take_while(|(span, _)| span.end <= end)
+            .any(|(_, token)| !matches!(token.kind(), Kind::WhiteSpace | Kind::Comment))
+    }
+}
+
+// ----- shapes, the node structure recovered from the AST -----
+
+struct Shape {
+    kind: SyntaxKind,
+    span: Span,
+    children: Vec<Shape>,
+}
+
+impl Shape {
+    fn new(kind: SyntaxKind, span: Span) -> Shape {
+        Shape {
+            kind,
+            span,
+            children: Vec::new(),
+        }
+    }
+
+    fn with(mut self, mut children: Vec<Shape>) -> Shape {
+        children.sort_by_key(|child| child.span.start);
+        self.children = children;
+        self
+    }
+}
+
+fn item(item: &Item) -> Shape {
+    match &item.kind {
+        ItemKind::Function(decl) => function(SyntaxKind::Function, decl, item.span),
+        ItemKind::Struct(decl) => {
+            let fields = decl
+                .fields
+                .iter()
+                .map(|field| {
+                    let mut children = vec![type_expr(&field.ty)];
+                    children.extend(field.default.iter().map(expr));
+                    Shape::new(SyntaxKind::StructField, field.span).with(children)
+                })
+                .collect::<Vec<_>>();
+            let mut children = generics(&decl.generics);
+            children.extend(fields);
+            Shape::new(SyntaxKind::Struct, item.span).with(children)
+        }
+        ItemKind::Impl(block) => {
+            let methods = block
+                .methods
+                .iter()
+                .map(|method| function(SyntaxKind::Method, &method.function, method.span))
+                .collect();
+            Shape::new(SyntaxKind::Impl, item.span).with(methods)
+        }
+        ItemKind::TypeAlias(alias) => {
+            let mut children = generics(&alias.generics);
+            children.push(type_expr(&alias.ty));
+            Shape::new(SyntaxKind::TypeAlias, item.span).with(children)
+        }
+        ItemKind::Macro(decl) => {
+            Shape::new(SyntaxKind::Macro, item.span).with(vec![block(&decl.body)])
+        }
+        ItemKind::Use(_) => Shape::new(SyntaxKind::Use, item.span),
+    }
+}
+
+// methods are laid out like functions, with `static` as an extra leading token
+fn function(kind: SyntaxKind, decl: &FunctionDecl, span: Span) -> Shape {
+    let mut children = generics(&decl.generics);
+    if let Some(receiver) = &decl.receiver {
+        children.push(Shape::new(SyntaxKind::Receiver, receiver.span));
+    }
+    for param in &decl.params {
+        children.push(Shape::new(SyntaxKind::Param, param.span).with(vec![type_expr(&param.ty)]));
+    }
+    children.extend(decl.return_type.iter().map(type_expr));
+    children.push(block(&decl.body));
+    Shape::new(kind, span).with(children)
+}
+
+// the `<` and `>` around the parameters stay tokens of the item itself
+fn generics(params: &[GenericParam]) -> Vec<Shape> {
+    params
+        .iter()
+        .map(|param| {
+            Shape::new(SyntaxKind::GenericParam, param.span)
+                .with(param.bounds.iter().map(type_expr).collect())
+        })
+        .collect()
+}
+
+fn type_expr(ty: &TypeExpr) -> Shape {
+    match &ty.kind {
+        TypeKind::Primitive(_) => Shape::new(SyntaxKind::PrimitiveType, ty.span),
+        TypeKind::Named(_) => Shape::new(SyntaxKind::NamedType, ty.span),
+        TypeKind::Generic { args, .. } => {
+            Shape::new(SyntaxKind::GenericType, ty.span).with(args.iter().map(type_expr).collect())
+        }
+        TypeKind::Function { params, ret } => {
+            let mut children: Vec<Shape> = params
+                .iter()
+                .map(|param| {
+                    let span = match &param.name {
+                        Some(name) => name.span.to(param.ty.span),
+                        None => param.ty.span,
+                    };
+                    Shape::new(SyntaxKind::FunctionTypeParam, span).with(vec![type_expr(&param.ty)])
+                })
+                .collect();
+            children.push(type_expr(ret));
+            Shape::new(SyntaxKind::FunctionType, ty.span).with(children)
+        }
+        TypeKind::Nullable(inner) => {
+            Shape::new(SyntaxKind::NullableType, ty.span).with(vec![type_expr(inner)])
+        }
+        TypeKind::List(element) => {
+            Shape::new(SyntaxKind::ListType, ty.span).with(vec![type_expr(element)])
+        }
+        TypeKind::Map { key, value } => {
+            Shape::new(SyntaxKind::MapType, ty.span).with(vec![type_expr(key), type_expr(value)])
+        }
+        TypeKind::Tuple(elements) => Shape::new(SyntaxKind::TupleType, ty.span)
+            .with(elements.iter().map(type_expr).collect()),
+        TypeKind::Missing => Shape::new(SyntaxKind::MissingType, ty.span),
+    }
+}
+
+fn block(block: &Block) -> Shape {
+    let stmts = block
+        .stmts
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            StmtKind::Let(binding) => {
+                let mut children: Vec<Shape> = binding.ty.iter().map(type_expr).collect();
+                children.push(expr(&binding.value));
+                Shape::new(SyntaxKind::LetStmt, stmt.span).with(children)
+            }
+            StmtKind::Expr(value) => {
+                Shape::new(SyntaxKind::ExprStmt, stmt.span).with(vec![expr(value)])
+            }
+        })
+        .collect();
+    Shape::new(SyntaxKind::Block, block.span).with(stmts)
+}
+
+fn expr(e: &Expr) -> Shape {
+    let (kind, children) = match &e.kind {
+        ExprKind::Literal(_) => (SyntaxKind::LiteralExpr, Vec::new()),
+        ExprKind::Ident(_) => (SyntaxKind::NameRef, Vec::new()),
+        ExprKind::Unary { operand, .. } => (SyntaxKind::UnaryExpr, vec![expr(operand)]),
+        ExprKind::Binary { lhs, rhs, .. } => (SyntaxKind::BinaryExpr, vec![expr(lhs), expr(rhs)]),
+        ExprKind::Assign { target, value, .. } => {
+            (SyntaxKind::AssignExpr, vec![expr(target), expr(value)])
+        }
+        ExprKind::Call { callee, args } => {
+            let mut children = vec![expr(callee)];
+            children.extend(args.iter().map(expr));
+            (SyntaxKind::CallExpr, children)
+        }
+        ExprKind::Field { base, .. } => (SyntaxKind::FieldExpr, vec![expr(base)]),
+        ExprKind::MethodCall { receiver, args, .. } => {
+            let mut children = vec![expr(receiver)];
+            children.extend(args.iter().map(expr));
+            (SyntaxKind::MethodCallExpr, children)
+        }
+        ExprKind::StructLit { fields, .. } => {
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    Shape::new(SyntaxKind::FieldInit, field.span).with(vec![expr(&field.value)])
+                })
+                .collect();
+            (SyntaxKind::StructLit, fields)
+        }
+        ExprKind::List(elements) => (SyntaxKind::ListExpr, elements.iter().map(expr).collect()),
+        ExprKind::Map(entries) => {
+            let entries = entries
+                .iter()
+                .map(|entry| {
+                    Shape::new(SyntaxKind::MapEntry, entry.span)
+                        .with(vec![expr(&entry.key), expr(&entry.value)])
+                })
+                .collect();
+            (SyntaxKind::MapExpr, entries)
+        }
+        ExprKind::Tuple(elements) => (SyntaxKind::TupleExpr, elements.iter().map(expr).collect()),
+        ExprKind::Index { base, index } => (SyntaxKind::IndexExpr, vec![expr(base), expr(index)]),
+        ExprKind::Cast { expr: value, ty } => {
+            (SyntaxKind::CastExpr, vec![expr(value), type_expr(ty)])
+        }
+        ExprKind::Lambda(lambda) => {
+            let mut children: Vec<Shape> = lambda
+                .params
+                .iter()
+                .map(|param| {
+                    Shape::new(SyntaxKind::LambdaParam, param.span)
+                        .with(param.ty.iter().map(type_expr).collect())
+                })
+                .collect();
+            children.extend(lambda.return_type.iter().map(type_expr));
+            children.push(block(&lambda.body));
+            (SyntaxKind::LambdaExpr, children)
+        }
+        ExprKind::If(if_expr) => {
+            let mut children: Vec<Shape> = if_expr
+                .branches
+                .iter()
+                .map(|branch| {
+                    let span = branch.condition.span.to(branch.block.span);
+                    Shape::new(SyntaxKind::IfBranch, span)
+                        .with(vec![expr(&branch.condition), block(&branch.block)])
+                })
+                .collect();
+            children.extend(if_expr.else_block.iter().map(block));
+            (SyntaxKind::IfExpr, children)
+        }
+        ExprKind::Match(match_expr) => {
+            let mut children = vec![expr(&match_expr.scrutinee)];
+            children.extend(match_expr.arms.iter().map(|arm| {
+                let mut children = vec![pattern(&arm.pattern)];
+                children.extend(arm.guard.iter().map(expr));
+                children.push(expr(&arm.body));
+                Shape::new(SyntaxKind::MatchArm, arm.span).with(children)
+            }));
+            (SyntaxKind::MatchExpr, children)
+        }
+        ExprKind::While(while_expr) => (
+            SyntaxKind::WhileExpr,
+            vec![expr(&while_expr.condition), block(&while_expr.body)],
+        ),
+        ExprKind::For(for_expr) => (
+            SyntaxKind::ForExpr,
+            vec![
+                pattern(&for_expr.pattern),
+                expr(&for_expr.iterable),
+                block(&for_expr.body),
+            ],
+        ),
+        ExprKind::Break { value, .. } => (
+            SyntaxKind::BreakExpr,
+            value.iter().map(|v| expr(v)).collect(),
+        ),
+        ExprKind::Continue { .. } => (SyntaxKind::ContinueExpr, Vec::new()),
+        ExprKind::Block(inner) => (SyntaxKind::BlockExpr, vec![block(inner)]),
+        ExprKind::Return(value) => (
+            SyntaxKind::ReturnExpr,
+            value.iter().map(|v| expr(v)).collect(),
+        ),
+        ExprKind::Missing => (SyntaxKind::MissingExpr, Vec::new()),
+    };
+    Shape::new(kind, e.span).with(children)
+}
+
+fn pattern(p: &Pattern) -> Shape {
+    let (kind, children) = match &p.kind {
+        PatternKind::Wildcard => (SyntaxKind::WildcardPat, Vec::new()),
+        PatternKind::Binding(_) => (SyntaxKind::BindingPat, Vec::new()),
+        PatternKind::Literal(_) => (SyntaxKind::LiteralPat, Vec::new()),
+        PatternKind::Tuple(elements) => {
+            (SyntaxKind::TuplePat, elements.iter().map(pattern).collect())
+        }
+        PatternKind::Struct { fields, .. } => {
+            let fields = fields
+                .iter()
+                .map(|field| {
+                    Shape::new(SyntaxKind::FieldPat, field.span).with(vec![pattern(&field.pattern)])
+                })
+                .collect();
+            (SyntaxKind::StructPat, fields)
+        }
+        PatternKind::Or(alternatives) => (
+            SyntaxKind::OrPat,
+            alternatives.iter().map(pattern).collect(),
+        ),
+    };
+    Shape::new(kind, p.span).with(children)
+}
@@ -0,0 +1,446 @@
+use crate::lexer::token::{Kind, Span};
+use std::fmt;
+use std::rc::Rc;
+
+/// Kinds of interior nodes. Leaves keep the lexer's `Kind`, with whitespace
+/// recovered between tokens as `Kind::WhiteSpace`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum SyntaxKind {
+    SourceFile,
+    // items
+    Function,
+    Receiver,
+    GenericParam,
+    Param,
+    Struct,
+    StructField,
+    Impl,
+    Method,
+    TypeAlias,
+    Macro,
+    Use,
+    // types
+    PrimitiveType,
+    NamedType,
+    GenericType,
+    FunctionType,
+    FunctionTypeParam,
+    NullableType,
+    ListType,
+    MapType,
+    TupleType,
+    MissingType,
+    // statements
+    Block,
+    LetStmt,
+    ExprStmt,
+    // expressions
+    LiteralExpr,
+    NameRef,
+    UnaryExpr,
+    BinaryExpr,
+    AssignExpr,
+    CallExpr,
+    FieldExpr,
+    MethodCallExpr,
+    StructLit,
+    FieldInit,
+    ListExpr,
+    MapExpr,
+    MapEntry,
+    TupleExpr,
+    IndexExpr,
+    CastExpr,
+    LambdaExpr,
+    LambdaParam,
+    IfExpr,
+    IfBranch,
+    MatchExpr,
+    MatchArm,
+    WhileExpr,
+    ForExpr,
+    BreakExpr,
+    ContinueExpr,
+    BlockExpr,
+    ReturnExpr,
+    MissingExpr,
+    // patterns
+    WildcardPat,
+    BindingPat,
+    LiteralPat,
+    TuplePat,
+    StructPat,
+    FieldPat,
+    OrPat,
+}
+
+impl SyntaxKind {
+    pub fn is_type(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::PrimitiveType
+                | SyntaxKind::NamedType
+                | SyntaxKind::GenericType
+                | SyntaxKind::FunctionType
+                | SyntaxKind::NullableType
+                | SyntaxKind::ListType
+                | SyntaxKind::MapType
+                | SyntaxKind::TupleType
+                | SyntaxKind::MissingType
+        )
+    }
+
+    pub fn is_expr(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::LiteralExpr
+                | SyntaxKind::NameRef
+                | SyntaxKind::UnaryExpr
+                | SyntaxKind::BinaryExpr
+                | SyntaxKind::AssignExpr
+                | SyntaxKind::CallExpr
+                | SyntaxKind::FieldExpr
+                | SyntaxKind::MethodCallExpr
+                | SyntaxKind::StructLit
+                | SyntaxKind::ListExpr
+                | SyntaxKind::MapExpr
+                | SyntaxKind::TupleExpr
+                | SyntaxKind::IndexExpr
+                | SyntaxKind::CastExpr
+                | SyntaxKind::LambdaExpr
+                | SyntaxKind::IfExpr
+                | SyntaxKind::MatchExpr
+                | SyntaxKind::WhileExpr
+                | SyntaxKind::ForExpr
+                | SyntaxKind::BreakExpr
+                | SyntaxKind::ContinueExpr
+                | SyntaxKind::BlockExpr
+                | SyntaxKind::ReturnExpr
+                | SyntaxKind::MissingExpr
+        )
+    }
+
+    pub fn is_pattern(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::WildcardPat
+                | SyntaxKind::BindingPat
+                | SyntaxKind::LiteralPat
+                | SyntaxKind::TuplePat
+                | SyntaxKind::StructPat
+                | SyntaxKind::FieldPat
+                | SyntaxKind::OrPat
+        )
+    }
+}
+
+// ----- green tree -----
+
+/// Immutable, position independent node. Green nodes only know their width so
+/// identical subtrees can be shared.
+#[derive(PartialEq, Eq, Debug)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct GreenToken {
+    kind: Kind,
+    text: String,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> GreenNode {
+        let width = children.iter().map(|child| child.width()).sum();
+        GreenNode {
+            kind,
+            width,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// Length in chars, matching the offsets used by `Span`.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+impl GreenToken {
+    pub fn new(kind: Kind, text: impl Into<String>) -> GreenToken {
+        GreenToken {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn width(&self) -> usize {
+        self.text.chars().count()
+    }
+}
+
+impl GreenElement {
+    pub fn width(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.width(),
+            GreenElement::Token(token) => token.width(),
+        }
+    }
+}
+
+// ----- red tree -----
+
+/// A green node seen from a particular position in the tree. Red nodes are
+/// created on demand while walking and know their parent and absolute offset.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Rc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Rc::new(NodeData {
+            green,
+            parent: None,
+            offset: 0,
+        }))
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.0.offset, self.0.offset + self.0.green.width())
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    pub fn ancestors(&self) -> Vec<SyntaxNode> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent();
+        while let Some(node) = current {
+            current = node.parent();
+            ancestors.push(node);
+        }
+        ancestors
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        let mut elements = Vec::with_capacity(self.0.green.children().len());
+        for child in self.0.green.children() {
+            elements.push(match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    offset,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    offset,
+                }),
+            });
+            offset += child.width();
+        }
+        elements
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// Tokens directly below this node, trivia included.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Token(token) => Some(token),
+                SyntaxElement::Node(_) => None,
+            })
+            .collect()
+    }
+
+    /// Every token in the subtree in source order, trivia included.
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// This node and every node below it, in preorder.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = vec![self.clone()];
+        for child in self.children() {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+
+    pub fn child_token(&self, kind: &Kind) -> Option<SyntaxToken> {
+        self.tokens().into_iter().find(|token| token.kind() == kind)
+    }
+
+    pub fn first_token(&self) -> Option<SyntaxToken> {
+        self.descendant_tokens()
+            .into_iter()
+            .find(|token| !token.is_trivia())
+    }
+
+    pub fn last_token(&self) -> Option<SyntaxToken> {
+        self.descendant_tokens()
+            .into_iter()
+            .rev()
+            .find(|token| !token.is_trivia())
+    }
+
+    /// The exact source text covered by the node.
+    pub fn text(&self) -> String {
+        let mut text = String::with_capacity(self.0.green.width());
+        for token in self.descendant_tokens() {
+            text.push_str(token.text());
+        }
+        text
+    }
+
+    fn dump(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{:?}@{}",
+            "",
+            self.kind(),
+            self.span(),
+            indent = depth * 2
+        )?;
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => node.dump(f, depth + 1)?,
+                SyntaxElement::Token(token) => {
+                    writeln!(f, "{:indent$}{:?}", "", token, indent = (depth + 1) * 2)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &SyntaxNode) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.0.offset == other.0.offset
+    }
+}
+
+/// Prints the tree one element per line, `Kind@start..end`.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.dump(f, 0)
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> &Kind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.offset + self.green.width())
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    /// Whitespace and comments, which carry no meaning for the parser.
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind(), Kind::WhiteSpace | Kind::Comment)
+    }
+}
+
+impl PartialEq for SyntaxToken {
+    fn eq(&self, other: &SyntaxToken) -> bool {
+        Rc::ptr_eq(&self.green, &other.green) && self.offset == other.offset
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{} {:?}", self.kind(), self.span(), self.text())
+    }
+}
+
+impl SyntaxElement {
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+}
@@ -0,0 +1,400 @@
+use crate::cst::cst::{SyntaxKind, SyntaxNode, SyntaxToken};
+use crate::lexer::token::Kind;
+
+/// A typed view over a `SyntaxNode` of a particular kind. Accessors return
+/// `None` or an empty list when broken code left a piece out.
+pub trait AstNode: Sized {
+    fn cast(node: SyntaxNode) -> Option<Self>;
+    fn syntax(&self) -> &SyntaxNode;
+}
+
+macro_rules! ast_node {
+    ($name:ident, $kind:ident) => {
+        #[derive(Debug, Clone, PartialEq)]
+        pub struct $name(SyntaxNode);
+
+        impl AstNode for $name {
+            fn cast(node: SyntaxNode) -> Option<Self> {
+                match node.kind() {
+                    SyntaxKind::$kind => Some($name(node)),
+                    _ => None,
+                }
+            }
+
+            fn syntax(&self) -> &SyntaxNode {
+                &self.0
+            }
+        }
+    };
+}
+
+fn child<N: AstNode>(node: &SyntaxNode) -> Option<N> {
+    node.children().into_iter().find_map(N::cast)
+}
+
+fn children<N: AstNode>(node: &SyntaxNode) -> Vec<N> {
+    node.children().into_iter().filter_map(N::cast).collect()
+}
+
+fn name_token(node: &SyntaxNode) -> Option<SyntaxToken> {
+    node.child_token(&Kind::Identifier)
+}
+
+ast_node!(SourceFile, SourceFile);
+ast_node!(Function, Function);
+ast_node!(Param, Param);
+ast_node!(Struct, Struct);
+ast_node!(StructField, StructField);
+ast_node!(Impl, Impl);
+ast_node!(Method, Method);
+ast_node!(TypeAlias, TypeAlias);
+ast_node!(Macro, Macro);
+ast_node!(Use, Use);
+ast_node!(Block, Block);
+ast_node!(LetStmt, LetStmt);
+ast_node!(ExprStmt, ExprStmt);
+ast_node!(BinaryExpr, BinaryExpr);
+ast_node!(CallExpr, CallExpr);
+ast_node!(LambdaExpr, LambdaExpr);
+ast_node!(IfExpr, IfExpr);
+ast_node!(IfBranch, IfBranch);
+
+/// Any type node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeRef(SyntaxNode);
+
+impl AstNode for TypeRef {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        match node.kind().is_type() {
+            true => Some(TypeRef(node)),
+            false => None,
+        }
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+/// Any expression node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr(SyntaxNode);
+
+impl AstNode for Expr {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        match node.kind().is_expr() {
+            true => Some(Expr(node)),
+            false => None,
+        }
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Function(Function),
+    Struct(Struct),
+    Impl(Impl),
+    TypeAlias(TypeAlias),
+    Macro(Macro),
+    Use(Use),
+}
+
+impl AstNode for Item {
+    fn cast(node: SyntaxNode) -> Option<Self> {
+        let item = match node.kind() {
+            SyntaxKind::Function => Item::Function(Function(node)),
+            SyntaxKind::Struct => Item::Struct(Struct(node)),
+            SyntaxKind::Impl => Item::Impl(Impl(node)),
+            SyntaxKind::TypeAlias => Item::TypeAlias(TypeAlias(node)),
+            SyntaxKind::Macro => Item::Macro(Macro(node)),
+            SyntaxKind::Use => Item::Use(Use(node)),
+            _ => return None,
+        };
+        Some(item)
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Item::Function(node) => node.syntax(),
+            Item::Struct(node) => node.syntax(),
+            Item::Impl(node) => node.syntax(),
+            Item::TypeAlias(node) => node.syntax(),
+            Item::Macro(node) => node.syntax(),
+            Item::Use(node) => node.syntax(),
+        }
+    }
+}
+
+impl SourceFile {
+    pub fn items(&self) -> Vec<Item> {
+        children(&self.0)
+    }
+}
+
+impl Function {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        children(&self.0)
+    }
+
+    pub fn return_type(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl Param {
+    pub fn is_mutable(&self) -> bool {
+        self.0.child_token(&Kind::Mutable).is_some()
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+}
+
+impl Struct {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn fields(&self) -> Vec<StructField> {
+        children(&self.0)
+    }
+}
+
+impl StructField {
+    pub fn is_public(&self) -> bool {
+        self.0.child_token(&Kind::Public).is_some()
+    }
+
+    pub fn is_mutable(&self) -> bool {
+        self.0.child_token(&Kind::Mutable).is_some()
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+
+    pub fn default(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+}
+
+impl Impl {
+    pub fn target(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn methods(&self) -> Vec<Method> {
+        children(&self.0)
+    }
+}
+
+impl Method {
+    pub fn is_static(&self) -> bool {
+        self.0.child_token(&Kind::Static).is_some()
+    }
+
+    pub fn has_receiver(&self) -> bool {
+        self.0
+            .children()
+            .iter()
+            .any(|node| node.kind() == SyntaxKind::Receiver)
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn params(&self) -> Vec<Param> {
+        children(&self.0)
+    }
+
+    pub fn return_type(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl TypeAlias {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+}
+
+impl Macro {
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    /// The parameter names, the identifiers after the macro's own.
+    pub fn params(&self) -> Vec<SyntaxToken> {
+        self.0
+            .tokens()
+            .into_iter()
+            .filter(|tok| tok.kind() == &Kind::Identifier)
+            .skip(1)
+            .collect()
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl Use {
+    /// The module path, the identifiers before any `{`.
+    pub fn path(&self) -> Vec<SyntaxToken> {
+        self.split().0
+    }
+
+    /// The selectively imported names inside `{ ... }`.
+    pub fn names(&self) -> Vec<SyntaxToken> {
+        self.split().1
+    }
+
+    fn split(&self) -> (Vec<SyntaxToken>, Vec<SyntaxToken>) {
+        let mut path = Vec::new();
+        let mut names = Vec::new();
+        let mut in_braces = false;
+        for tok in self.0.tokens() {
+            match tok.kind() {
+                Kind::LeftCurly => in_braces = true,
+                Kind::Identifier if in_braces => names.push(tok),
+                Kind::Identifier => path.push(tok),
+                _ => {}
+            }
+        }
+        (path, names)
+    }
+}
+
+impl Block {
+    /// `LetStmt` and `ExprStmt` nodes in order.
+    pub fn statements(&self) -> Vec<SyntaxNode> {
+        self.0
+            .children()
+            .into_iter()
+            .filter(|node| matches!(node.kind(), SyntaxKind::LetStmt | SyntaxKind::ExprStmt))
+            .collect()
+    }
+
+    pub fn end_token(&self) -> Option<SyntaxToken> {
+        self.0.child_token(&Kind::End)
+    }
+}
+
+impl LetStmt {
+    pub fn is_mutable(&self) -> bool {
+        self.0.child_token(&Kind::Mutable).is_some()
+    }
+
+    pub fn name(&self) -> Option<SyntaxToken> {
+        name_token(&self.0)
+    }
+
+    pub fn ty(&self) -> Option<TypeRef> {
+        child(&self.0)
+    }
+
+    pub fn value(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+}
+
+impl ExprStmt {
+    pub fn expr(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+}
+
+impl BinaryExpr {
+    pub fn lhs(&self) -> Option<Expr> {
+        children(&self.0).into_iter().next()
+    }
+
+    pub fn rhs(&self) -> Option<Expr> {
+        children(&self.0).into_iter().nth(1)
+    }
+
+    pub fn op_token(&self) -> Option<SyntaxToken> {
+        self.0.tokens().into_iter().find(|token| !token.is_trivia())
+    }
+}
+
+impl CallExpr {
+    pub fn callee(&self) -> Option<Expr> {
+        children(&self.0).into_iter().next()
+    }
+
+    pub fn args(&self) -> Vec<Expr> {
+        children(&self.0).into_iter().skip(1).collect()
+    }
+}
+
+impl LambdaExpr {
+    pub fn param_names(&self) -> Vec<SyntaxToken> {
+        self.0
+            .children()
+            .iter()
+            .filter(|node| node.kind() == SyntaxKind::LambdaParam)
+            .filter_map(name_token)
+            .collect()
+    }
+
+    pub fn body(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl IfExpr {
+    pub fn branches(&self) -> Vec<IfBranch> {
+        children(&self.0)
+    }
+
+    pub fn else_block(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl IfBranch {
+    pub fn condition(&self) -> Option<Expr> {
+        child(&self.0)
+    }
+
+    pub fn block(&self) -> Option<Block> {
+        child(&self.0)
+    }
+}
+
+impl Expr {
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.kind()
+    }
+}
@@ -0,0 +1,111 @@
+use crate::lexer::token::Span;
+use std::fmt;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Related places, such as the declaration an error is about.
+    pub notes: Vec<Note>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Note {
+    pub message: String,
+    pub span: Span,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn warning(message: impl Into<String>, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: message.into(),
+            span,
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_note(mut self, message: impl Into<String>, span: Span) -> Diagnostic {
+        self.notes.push(Note {
+            message: message.into(),
+            span,
+        });
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Renders the diagnostic with a 1-based `line:column` resolved against `source`.
+    pub fn render(&self, source: &str) -> String {
+        let (line, column) = line_column(source, self.span.start);
+        let mut out = format!(
+            "{}: {}\n  --> {}:{}",
+            self.severity, self.message, line, column
+        );
+        for note in &self.notes {
+            let (line, column) = line_column(source, note.span.start);
+            out.push_str(&format!(
+                "\nnote: {}\n  --> {}:{}",
+                note.message, line, column
+            ));
+        }
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {} at {}", self.severity, self.message, self.span)
+    }
+}
+
+/// Converts a char offset into a 1-based line and column.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for c in source.chars().take(offset) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+/// `count` followed by `noun`, in the plural unless `count` is 1, as in
+/// "takes 2 arguments".
+pub fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        n => format!("{} {}s", n, noun),
+    }
+}
@@ -0,0 +1,235 @@
+use crate::cst::builder::parse_tree;
+use crate::cst::cst::{SyntaxKind, SyntaxToken};
+use crate::lexer::token::Kind;
+
+const INDENT: &str = "    ";
+
+/// Pretty-prints rot source in the canonical style: four space indentation per
+/// open `do` or bracket, single spaces around binary operators, `->` and `?:`,
+/// at most one blank line in a row and a single trailing newline. Comments and
+/// the original line breaks are kept. Sources that do not parse are returned
+/// unchanged, since the layout of broken code cannot be trusted.
+pub fn format_source(source: &str) -> String {
+    let tree = parse_tree(source);
+    if tree.diagnostics.iter().any(|d| d.is_error()) {
+        return source.to_string();
+    }
+    let lines = split_lines(tree.root.descendant_tokens());
+    let mut out = String::new();
+    let mut openers: Vec<usize> = Vec::new();
+    let mut previous: Option<&Line> = None;
+    for (number, line) in lines.iter().enumerate() {
+        if line.tokens.is_empty() {
+            continue;
+        }
+        if let Some(prev) = previous {
+            out.push('\n');
+            if line.breaks > 1 && !prev.opens_block() && !line.closes_block() {
+                out.push('\n');
+            }
+        }
+        let mut leading = 0;
+        while line
+            .tokens
+            .get(leading)
+            .is_some_and(|tok| is_closer(tok.kind()))
+        {
+            openers.pop();
+            leading += 1;
+        }
+        // `else` closes the branch above it and opens its own
+        let mut depth = match line.tokens.get(leading).map(|tok| tok.kind()) {
+            Some(Kind::Else) => distinct(&openers[..openers.len().saturating_sub(1)]),
+            _ => distinct(&openers),
+        };
+        if previous.is_some_and(|prev| is_continuation(prev, line)) {
+            depth += 1;
+        }
+        out.push_str(&INDENT.repeat(depth));
+        write_line(&mut out, line);
+
+        for (i, tok) in line.tokens.iter().enumerate().skip(leading) {
+            match tok.kind() {
+                Kind::Do | Kind::LeftParen | Kind::LeftBracket | Kind::LeftCurly => {
+                    openers.push(number)
+                }
+                // a plain `else` opens the final branch, `else if` waits for its `do`
+                Kind::Else => {
+                    openers.pop();
+                    if line.tokens.get(i + 1).map(|t| t.kind()) != Some(&Kind::If) {
+                        openers.push(number);
+                    }
+                }
+                kind if is_closer(kind) => {
+                    openers.pop();
+                }
+                _ => {}
+            }
+        }
+        previous = Some(line);
+    }
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+struct Line {
+    // line breaks between this line and the previous one
+    breaks: usize,
+    tokens: Vec<SyntaxToken>,
+}
+
+impl Line {
+    fn last_code(&self) -> Option<&SyntaxToken> {
+        self.tokens
+            .iter()
+            .rev()
+            .find(|tok| *tok.kind() != Kind::Comment)
+    }
+
+    fn opens_block(&self) -> bool {
+        self.last_code().is_some_and(|tok| *tok.kind() == Kind::Do)
+    }
+
+    fn closes_block(&self) -> bool {
+        matches!(self.tokens[0].kind(), Kind::End | Kind::Else)
+    }
+}
+
+fn split_lines(tokens: Vec<SyntaxToken>) -> Vec<Line> {
+    let mut lines = vec![Line {
+        breaks: 0,
+        tokens: Vec::new(),
+    }];
+    let mut breaks = 0;
+    for tok in tokens {
+        let newlines = match tok.kind() {
+            Kind::WhiteSpace => tok.text().matches('\n').count(),
+            // comments carry the line break that ends them
+            Kind::Comment => tok.text().ends_with('\n') as usize,
+            _ => 0,
+        };
+        if *tok.kind() != Kind::WhiteSpace {
+            if breaks > 0 {
+                lines.push(Line {
+                    breaks,
+                    tokens: Vec::new(),
+                });
+                breaks = 0;
+            }
+            lines.last_mut().unwrap().tokens.push(tok);
+        }
+        breaks += newlines;
+    }
+    lines
+}
+
+fn write_line(out: &mut String, line: &Line) {
+    for (i, tok) in line.tokens.iter().enumerate() {
+        if i > 0 && needs_space(&line.tokens[i - 1], tok) {
+            out.push(' ');
+        }
+        out.push_str(tok.text().trim_end());
+    }
+}
+
+// openers on the same line only indent the following lines once
+fn distinct(openers: &[usize]) -> usize {
+    let mut lines = openers.to_vec();
+    lines.dedup();
+    lines.len()
+}
+
+fn is_closer(kind: &Kind) -> bool {
+    matches!(
+        kind,
+        Kind::End | Kind::RightParen | Kind::RightBracket | Kind::RightCurly
+    )
+}
+
+fn is_unary(tok: &SyntaxToken) -> bool {
+    matches!(tok.kind(), Kind::Minus | Kind::Exclaim)
+        && matches!(
+            tok.parent().kind(),
+            SyntaxKind::UnaryExpr | SyntaxKind::LiteralPat
+        )
+}
+
+// `<` and `>` delimiting generic parameters or type arguments
+fn is_angle(tok: &SyntaxToken) -> bool {
+    matches!(tok.kind(), Kind::LessThan | Kind::GreaterThan)
+        && tok.parent().kind() != SyntaxKind::BinaryExpr
+}
+
+fn is_binary_operator(tok: &SyntaxToken) -> bool {
+    !is_unary(tok)
+        && !is_angle(tok)
+        && matches!(
+            tok.kind(),
+            Kind::Plus
+                | Kind::Minus
+                | Kind::Asterisk
+                | Kind::Slash
+                | Kind::IsEqual
+                | Kind::NotEqual
+                | Kind::LessThan
+                | Kind::GreaterThan
+                | Kind::LessThanOrEqual
+                | Kind::GreaterThanOrEqual
+                | Kind::LogicalAnd
+                | Kind::LogicalOr
+                | Kind::Equal
+                | Kind::PlusEqual
+                | Kind::MinusEqual
+                | Kind::AsteriskEqual
+                | Kind::SlashEqual
+                | Kind::Elvis
+                | Kind::PipeForward
+        )
+}
+
+// a line continuing the expression of the line above gets one extra level
+fn is_continuation(prev: &Line, line: &Line) -> bool {
+    let first = &line.tokens[0];
+    let ends_with_operator = prev.last_code().is_some_and(is_binary_operator);
+    let starts_with_operator =
+        matches!(first.kind(), Kind::Dot | Kind::SafeDot) || is_binary_operator(first);
+    ends_with_operator || starts_with_operator
+}
+
+fn needs_space(prev: &SyntaxToken, next: &SyntaxToken) -> bool {
+    match next.kind() {
+        Kind::Comma | Kind::Semicolon | Kind::Colon | Kind::Dot | Kind::SafeDot => return false,
+        Kind::RightParen | Kind::RightBracket | Kind::RightCurly => return false,
+        Kind::Comment => return true,
+        // the `?` of a nullable type
+        Kind::Question if next.parent().kind() == SyntaxKind::NullableType => return false,
+        _ if is_angle(next) => return false,
+        _ => {}
+    }
+    match prev.kind() {
+        Kind::LeftParen | Kind::LeftBracket | Kind::LeftCurly | Kind::Dot | Kind::SafeDot => {
+            return false
+        }
+        _ => {}
+    }
+    if is_angle(prev) && *prev.kind() == Kind::LessThan {
+        return false;
+    }
+    if is_unary(prev) {
+        // `- -x` must not turn into a single `--` token
+        return matches!(next.kind(), Kind::Minus | Kind::Exclaim);
+    }
+    let follows_value = matches!(
+        prev.kind(),
+        Kind::Identifier | Kind::RightParen | Kind::RightBracket
+    ) || is_angle(prev);
+
+    match next.kind() {
+        // calls, struct literals and indexing attach to what precedes them
+        Kind::LeftParen | Kind::LeftBracket => !follows_value,
+        Kind::LeftCurly => *prev.kind() != Kind::Identifier,
+        _ => true,
+    }
+}
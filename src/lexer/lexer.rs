@@ -1,8 +1,9 @@
+use crate::diagnostics::diagnostic::Diagnostic;
 use crate::lexer::lexer::LexerState::*;
 use crate::lexer::token::{
     build_complex_dictionary, build_simple_dictionary, find_kind, ComplexDict, Kind, SimpleDict,
 };
-use crate::lexer::token::{is_special_char, Token};
+use crate::lexer::token::{is_special_char, Span, Token};
 use core::fmt;
 
 #[derive(PartialEq, Clone)]
@@ -11,8 +12,6 @@ enum LexerState {
     NumericEval,
     StringEval,
     CommentEval,
-    MultiLnStringEval,
-    MultiLnCommentEval,
     CharEval,
     MaybeRegexEval,
     RegexEval,
@@ -25,15 +24,13 @@ enum LexerState {
 impl fmt::Display for LexerState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Error(msg) = self {
-            return write!(f, "{}", format!("LexerState::Error({})", msg));
+            return write!(f, "LexerState::Error({})", msg);
         }
 
         let val = match self {
             Start => "Start",
             StringEval => "StringEval",
-            MultiLnStringEval => "MultiLnStringEval",
             CommentEval => "CommentEval",
-            MultiLnCommentEval => "MultiLnCommentEval",
             CharEval => "CharEval",
             RegexEval => "RegexEval",
             KeywordEval => "KeywordEval",
@@ -49,6 +46,8 @@ impl fmt::Display for LexerState {
 pub struct Lexer {
     state: LexerState,
     pub tokens: Vec<Token>,
+    /// Input that could not be lexed.
+    pub diagnostics: Vec<Diagnostic>,
     input: Vec<char>,
     index: usize,
     line_number: usize,
@@ -56,6 +55,8 @@ pub struct Lexer {
     complex_dict: ComplexDict,
     simple_dict: SimpleDict,
     buffer: String,
+    // char offset of the first char in `buffer`
+    buffer_start: usize,
     last: Option<char>,
     curr: Option<char>,
 }
@@ -102,6 +103,7 @@ impl Lexer {
         Lexer {
             state: Start,
             tokens: Vec::new(),
+            diagnostics: Vec::new(),
             input: buff,
             index: 0,
             line_number: 0,
@@ -109,68 +111,73 @@ impl Lexer {
             complex_dict: build_complex_dictionary(),
             simple_dict: build_simple_dictionary(),
             buffer: "".to_owned(),
+            buffer_start: 0,
             last: None::<char>,
             curr: None::<char>,
         }
     }
 
     fn peek(&self) -> Option<char> {
-        return if self.index < self.input.len() {
+        if self.index < self.input.len() {
             Some(self.input[self.index])
         } else {
             None
-        };
+        }
     }
 
     fn is_escaped(&self) -> bool {
         match self.last {
-            Some(c) => {
-                if c == '\\' {
-                    true
-                } else {
-                    false
-                }
-            }
+            Some(c) => c == '\\',
             None => false,
         }
     }
 
     fn get(&mut self) -> Option<char> {
-        return if self.index < self.input.len() {
+        if self.index < self.input.len() {
             let ret = self.input[self.index];
 
             self.inc();
             self.last = self.curr;
             self.curr = Some(ret);
-            match self.last {
-                Some(c) => {
-                    if c == '\n' {
-                        self.line_number += 1;
-                        self.line_position = 0;
-                    }
+            if let Some(c) = self.last {
+                if c == '\n' {
+                    self.line_number += 1;
+                    self.line_position = 0;
                 }
-                None => (),
             }
             self.line_position += 1;
             Some(ret)
         } else {
             None
-        };
+        }
     }
 
     fn inc(&mut self) {
         self.index += 1;
     }
 
+    // appends `c`, the char last returned by `get`
+    fn push(&mut self, c: char) {
+        if self.buffer.is_empty() {
+            self.buffer_start = self.index - 1;
+        }
+        self.buffer.push(c);
+    }
+
+    // the buffered chars, or the char last read when nothing is buffered
+    fn buffer_span(&self) -> Span {
+        match self.buffer.chars().count() {
+            0 => Span::new(self.index.saturating_sub(1), self.index),
+            len => Span::new(self.buffer_start, self.buffer_start + len),
+        }
+    }
+
     pub fn lex(&mut self) {
         while self.state != End {
             let new_state = self.handle_state();
             if let Error(msg) = new_state {
-                println!(
-                    "Encountered an error while parsing: {}\nlexer : {}",
-                    Error(msg),
-                    self
-                );
+                let span = self.buffer_span();
+                self.diagnostics.push(Diagnostic::error(msg, span));
                 break;
             }
 
@@ -189,24 +196,19 @@ impl Lexer {
 
     fn handle_state(&mut self) -> LexerState {
         let snapshot_state: LexerState = self.state.clone();
-        return match snapshot_state {
+        match snapshot_state {
             Start => self.handle_start_state(),
             KeywordEval => self.handle_keyword_eval(),
             CommentEval => self.handle_comment_eval(),
             StringEval => self.handle_string_eval(),
-            MultiLnStringEval => self.handle_multilnstring_eval(),
-            MultiLnCommentEval => self.handle_multilncomment_eval(),
             CharEval => self.handle_char_eval(),
             MaybeRegexEval => self.handle_maybe_regex(),
             RegexEval => self.handle_regex_eval(),
             NumericEval => self.handle_numeric_eval(),
             SpecialEval => self.handle_special_eval(),
-            End => End,
-            Error(msg) => {
-                println!("Lexer in error state: {}", Error(msg));
-                End
-            }
-        };
+            // reported by `lex` on entering it
+            End | Error(_) => End,
+        }
     }
 
     fn handle_buffer(&mut self) {
@@ -215,16 +217,22 @@ impl Lexer {
             self.simple_dict.clone(),
             self.buffer.clone(),
         ) {
-            let t_token = Token::new(
+            let mut t_token = Token::new(
                 t_kind,
                 self.buffer.clone(),
                 self.line_number,
                 self.line_position - self.buffer.len(),
             );
+            let end = self.buffer_start + self.buffer.chars().count();
+            t_token.span = Span::new(self.buffer_start, end);
             // dbg!(format!("token added: {}", t_token.clone()));
             self.tokens.push(t_token);
         } else {
-            println!("was not able to derive kind from buffer: {}", self.buffer);
+            let span = self.buffer_span();
+            self.diagnostics.push(Diagnostic::error(
+                format!("unrecognized token `{}`", self.buffer),
+                span,
+            ));
         }
     }
 
@@ -237,7 +245,7 @@ impl Lexer {
     }
 
     fn flush_buffer_no_append(&mut self) {
-        let t_pos = self.line_position.clone();
+        let t_pos = self.line_position;
         self.line_position -= 1;
         self.flush_buffer();
         self.line_position = t_pos;
@@ -248,13 +256,13 @@ impl Lexer {
             self.flush_buffer();
             Start
         } else if x.is_alphabetic() || x == '_' {
-            self.buffer.push(x);
+            self.push(x);
             KeywordEval
         } else if x.is_numeric() {
-            self.buffer.push(x);
+            self.push(x);
             NumericEval
         } else if is_special_char(x) {
-            self.buffer.push(x);
+            self.push(x);
             SpecialEval
         } else {
             Error("could not determine case".to_string())
@@ -264,32 +272,32 @@ impl Lexer {
     fn handle_start_state_simple_case(&mut self, x: char) -> LexerState {
         match x {
             'r' => {
-                self.buffer.push(x);
+                self.push(x);
                 MaybeRegexEval
             }
             '"' => {
-                self.buffer.push(x);
+                self.push(x);
                 StringEval
             }
             '#' => {
-                self.buffer.push(x);
+                self.push(x);
                 CommentEval
             }
             '\'' => {
-                self.buffer.push(x);
+                self.push(x);
                 CharEval
             }
-            _ => self.handle_general_complex_case(x.clone()),
+            _ => self.handle_general_complex_case(x),
         }
     }
 
     fn handle_start_state(&mut self) -> LexerState {
         let check = self.get();
 
-        return match check {
-            Some(c) => self.handle_start_state_simple_case(c.clone()),
+        match check {
+            Some(c) => self.handle_start_state_simple_case(c),
             None => End,
-        };
+        }
     }
 
     fn handle_comment_eval(&mut self) -> LexerState {
@@ -297,11 +305,11 @@ impl Lexer {
         match check {
             Some(c) => {
                 if c == '\n' {
-                    self.buffer.push(c);
+                    self.push(c);
                     self.flush_buffer();
                     Start
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     CommentEval
                 }
             }
@@ -322,7 +330,7 @@ impl Lexer {
                 } else if c == '\n' {
                     Error("found newline in possible string".to_string())
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     StringEval
                 }
             }
@@ -333,30 +341,22 @@ impl Lexer {
         }
     }
 
-    fn handle_multilnstring_eval(&mut self) -> LexerState {
-        LexerState::Error("multilnstring state TODO".to_string())
-    }
-
-    fn handle_multilncomment_eval(&mut self) -> LexerState {
-        LexerState::Error("multilncomment state TODO".to_string())
-    }
-
     fn handle_keyword_eval(&mut self) -> LexerState {
         let check = self.get();
         match check {
             Some(c) => {
                 if c.is_alphanumeric() || c == '_' {
-                    self.buffer.push(c);
+                    self.push(c);
                     KeywordEval
                 } else if is_special_char(c) {
                     self.flush_buffer();
-                    self.buffer.push(c);
+                    self.push(c);
                     SpecialEval
                 } else if c.is_whitespace() {
                     self.flush_buffer_no_append();
                     Start
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     Error(format!("issue lexing {}", self.buffer))
                 }
             }
@@ -375,10 +375,15 @@ impl Lexer {
                     self.flush_buffer_no_append();
                     Start
                 } else if c == '"' {
-                    self.buffer.push(c);
+                    self.push(c);
                     RegexEval
+                } else if is_special_char(c) {
+                    // just the identifier `r`, as in `r: i32` or `f(r)`
+                    self.flush_buffer();
+                    self.push(c);
+                    SpecialEval
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     KeywordEval
                 }
             }
@@ -396,10 +401,10 @@ impl Lexer {
         non_escaped_state: LexerState,
     ) -> LexerState {
         if self.is_escaped() {
-            self.buffer.push(x);
+            self.push(x);
             escaped_state
         } else {
-            self.buffer.push(x);
+            self.push(x);
             self.flush_buffer();
             non_escaped_state
         }
@@ -415,7 +420,7 @@ impl Lexer {
                 } else if c == '\n' {
                     Error("regex eval has seen a new line in regex expression".to_string())
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     RegexEval
                 }
             }
@@ -436,7 +441,7 @@ impl Lexer {
                 } else if c == '\n' {
                     Error("found newline in possible char".to_string())
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     CharEval
                 }
             }
@@ -453,13 +458,17 @@ impl Lexer {
         match check {
             Some(c) => {
                 if c.is_numeric() || c == '.' {
-                    self.buffer.push(c);
+                    self.push(c);
                     NumericEval
+                } else if is_special_char(c) {
+                    self.flush_buffer();
+                    self.push(c);
+                    SpecialEval
                 } else if c.is_whitespace() {
                     self.flush_buffer_no_append();
                     Start
                 } else {
-                    self.buffer.push(c);
+                    self.push(c);
                     Error(format!("issue lexing {}", self.buffer))
                 }
             }
@@ -474,7 +483,7 @@ impl Lexer {
         //we are here since there is a special character in the buffer.
         // if current char is last in input, flush and go back to start
         let mut next_char = self.clone().peek();
-        if next_char == None {
+        if next_char.is_none() {
             self.flush_buffer();
             return Start;
         }
@@ -496,8 +505,8 @@ impl Lexer {
             |s: String| -> Option<Kind> { find_kind(cdict.clone(), sdict.clone(), s).clone() };
 
         let generate_special_token =
-            |t_kind: Option<Kind>, payload: String, ln: usize, pos: usize| -> Token {
-                match t_kind {
+            |t_kind: Option<Kind>, payload: String, ln: usize, pos: usize, span: Span| -> Token {
+                let mut token = match t_kind {
                     None => {
                         Token::new(
                             Kind::Err(format!("could not determine kind of {}", payload)),
@@ -508,14 +517,16 @@ impl Lexer {
                         )
                     }
                     Some(kind) => Token::new(kind, payload.clone(), ln, pos),
-                }
+                };
+                token.span = span;
+                token
             };
 
         let mut temp_buff_state = self.buffer.clone();
         let current_kind = find_kind_h(temp_buff_state.clone());
 
         next_char = self.get();
-        let ln = self.line_number.clone();
+        let ln = self.line_number;
         let pos = self.line_position - temp_buff_state.len();
         match next_char {
             Some(c) => {
@@ -533,6 +544,7 @@ impl Lexer {
                             temp_buff_state.clone(),
                             ln,
                             pos,
+                            Span::new(self.buffer_start, self.index),
                         );
                         self.tokens.push(tok);
                         self.buffer = "".to_string();
@@ -540,12 +552,24 @@ impl Lexer {
                     None => {
                         // treat separately
                         //dbg!(format!("flushing buffer: <{}>", self.buffer.clone()));
-                        let mut tok =
-                            generate_special_token(current_kind, self.buffer.clone(), ln, pos - 1);
-                        self.tokens.push(tok.clone());
-                        //dbg!(format!("flushing buffer: <{}>", c.to_string()));
-                        tok = generate_special_token(next_kind, c.to_string(), ln, pos);
-                        self.tokens.push(tok.clone());
+                        let tok = generate_special_token(
+                            current_kind,
+                            self.buffer.clone(),
+                            ln,
+                            pos - 1,
+                            Span::new(self.buffer_start, self.index - 1),
+                        );
+                        self.tokens.push(tok);
+                        // the second char may still pair up with the one after it,
+                        // as the `->` in `)->`
+                        if next_kind.is_some() {
+                            self.buffer.clear();
+                            self.push(c);
+                            return SpecialEval;
+                        }
+                        let span = Span::new(self.index - 1, self.index);
+                        let tok = generate_special_token(next_kind, c.to_string(), ln, pos, span);
+                        self.tokens.push(tok);
                         self.buffer = "".to_string();
                     }
                 }
@@ -12,8 +12,10 @@ pub enum Kind {
     DefStruct,
     Def,
     DefImpl,
+    DefMacro,
     Static,
     Type,
+    Use,
     Return,
     Public, // expose?
     Mutable,
@@ -23,6 +25,10 @@ pub enum Kind {
     If,
     For,
     While,
+    In,
+    Break,
+    Continue,
+    As,
 
     // types
     I8,
@@ -81,10 +87,15 @@ pub enum Kind {
     NotEqual,
     LogicalAnd,
     LogicalOr,
-    Increment,
-    Decrement,
+    PlusEqual,
+    MinusEqual,
+    AsteriskEqual,
+    SlashEqual,
     Elvis,
     Arrow,
+    FatArrow,
+    PipeForward,
+    SafeDot,
 
     // literals
     IntLiteral,
@@ -139,6 +150,9 @@ impl fmt::Display for Kind {
             Kind::Static => write!(f, "Static"),
             Kind::For => write!(f, "For"),
             Kind::While => write!(f, "While"),
+            Kind::In => write!(f, "In"),
+            Kind::Break => write!(f, "Break"),
+            Kind::Continue => write!(f, "Continue"),
             Kind::Fn => write!(f, "Fn"),
             Kind::Public => write!(f, "Public"),
             Kind::Match => write!(f, "Match"),
@@ -147,6 +161,7 @@ impl fmt::Display for Kind {
             Kind::Double => write!(f, "Double"),
             Kind::DoubleLiteral => write!(f, "DoubleLiteral"),
             Kind::DefImpl => write!(f, "DefImpl"),
+            Kind::DefMacro => write!(f, "DefMacro"),
             Kind::U8 => write!(f, "U8"),
             Kind::U16 => write!(f, "U16"),
             Kind::U32 => write!(f, "U32"),
@@ -171,22 +186,65 @@ impl fmt::Display for Kind {
             Kind::NotEqual => write!(f, "NotEqual"),
             Kind::LogicalAnd => write!(f, "LogicalAnd"),
             Kind::LogicalOr => write!(f, "LogicalOr"),
-            Kind::Increment => write!(f, "Increment"),
-            Kind::Decrement => write!(f, "Decrement"),
+            Kind::PlusEqual => write!(f, "PlusEqual"),
+            Kind::MinusEqual => write!(f, "MinusEqual"),
+            Kind::AsteriskEqual => write!(f, "AsteriskEqual"),
+            Kind::SlashEqual => write!(f, "SlashEqual"),
             Kind::LessThanOrEqual => write!(f, "LessThanOrEqual"),
             Kind::GreaterThanOrEqual => write!(f, "GreaterThanOrEqual"),
             Kind::Type => write!(f, "Type"),
+            Kind::Use => write!(f, "Use"),
+            Kind::As => write!(f, "As"),
             Kind::Arrow => write!(f, "Arrow"),
-            Kind::Err(msg) => write!(f, "{}", format!("Kind::Error({})", msg)),
+            Kind::FatArrow => write!(f, "FatArrow"),
+            Kind::PipeForward => write!(f, "PipeForward"),
+            Kind::SafeDot => write!(f, "SafeDot"),
+            Kind::Err(msg) => write!(f, "Kind::Error({})", msg),
             _ => write!(f, "UNKNOWN CASE, NEED TO ADD PRINT HANDLE"),
         }
     }
 }
 
+/// Half open range of char offsets into the lexed input.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    /// Smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct Token {
     pub kind: Kind,
     pub lexeme: String,
+    pub span: Span,
     line_number: usize,
     line_position: usize,
 }
@@ -197,10 +255,15 @@ impl Token {
         Token {
             kind: kind_t,
             lexeme: text.parse().unwrap(),
+            span: Span::default(),
             line_number: line_num,
             line_position: line_pos,
         }
     }
+
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
 }
 
 impl fmt::Display for Token {
@@ -220,26 +283,32 @@ pub type SimpleDict = HashMap<String, Kind>;
 pub type ComplexDict = (HashMap<usize, Kind>, RegexSet);
 
 pub fn build_complex_dictionary() -> ComplexDict {
-    let mut links: Vec<(String, Kind)> = Vec::new();
-    links.push((r"^[ \t\r\f]+$".to_string(), Kind::WhiteSpace));
-    links.push((r"^#.*\n$".to_string(), Kind::Comment));
-    links.push((r#"^""".*"""$\r\n"#.to_string(), Kind::MultiLnStringLiteral));
-    links.push((r"^[0-9]+$".to_string(), Kind::IntLiteral));
-    links.push((r#"^".*"$"#.to_string(), Kind::StringLiteral));
-    links.push((r#"^'.*'$"#.to_string(), Kind::CharLiteral));
-    links.push((r#"^r".*"$"#.to_string(), Kind::RegexLiteral));
-    links.push((r"^[0-9]+(\.[0-9]+)?$".to_string(), Kind::DoubleLiteral));
-    links.push((r"^\?:$".to_string(), Kind::Elvis));
-    links.push((r"^\|\|$".to_string(), Kind::LogicalOr));
-    links.push((r"^&&$".to_string(), Kind::LogicalAnd));
-    links.push((r"^==$".to_string(), Kind::IsEqual));
-    links.push((r"^!=$".to_string(), Kind::NotEqual));
-    links.push((r"^-=$".to_string(), Kind::Increment));
-    links.push((r"^\+=$".to_string(), Kind::Decrement));
-    links.push((r"^<=$".to_string(), Kind::LessThanOrEqual));
-    links.push((r"^\+=$".to_string(), Kind::GreaterThanOrEqual));
-    links.push((r"^\->$".to_string(), Kind::Arrow));
-    links.push((r"[a-zA-Z_][a-zA-Z0-9_]*".to_string(), Kind::Identifier));
+    let links: Vec<(&str, Kind)> = vec![
+        (r"^[ \t\r\f]+$", Kind::WhiteSpace),
+        (r"^#.*\n?$", Kind::Comment),
+        (r#"^""".*"""$\r\n"#, Kind::MultiLnStringLiteral),
+        (r"^[0-9]+$", Kind::IntLiteral),
+        (r#"^".*"$"#, Kind::StringLiteral),
+        (r#"^'.*'$"#, Kind::CharLiteral),
+        (r#"^r".*"$"#, Kind::RegexLiteral),
+        (r"^[0-9]+(\.[0-9]+)?$", Kind::DoubleLiteral),
+        (r"^\?:$", Kind::Elvis),
+        (r"^\|\|$", Kind::LogicalOr),
+        (r"^&&$", Kind::LogicalAnd),
+        (r"^==$", Kind::IsEqual),
+        (r"^!=$", Kind::NotEqual),
+        (r"^\+=$", Kind::PlusEqual),
+        (r"^-=$", Kind::MinusEqual),
+        (r"^\*=$", Kind::AsteriskEqual),
+        (r"^/=$", Kind::SlashEqual),
+        (r"^<=$", Kind::LessThanOrEqual),
+        (r"^>=$", Kind::GreaterThanOrEqual),
+        (r"^\->$", Kind::Arrow),
+        (r"^=>$", Kind::FatArrow),
+        (r"^\|>$", Kind::PipeForward),
+        (r"^\?\.$", Kind::SafeDot),
+        (r"^[a-zA-Z_][a-zA-Z0-9_]*$", Kind::Identifier),
+    ];
 
     let mut dict: HashMap<usize, Kind> = HashMap::new();
     let mut patterns: Vec<String> = Vec::new();
@@ -291,11 +360,15 @@ pub fn build_simple_dictionary() -> SimpleDict {
     ret.insert("def".to_string(), Kind::Def);
     ret.insert("defstruct".to_string(), Kind::DefStruct);
     ret.insert("defimpl".to_string(), Kind::DefImpl);
+    ret.insert("defmacro".to_string(), Kind::DefMacro);
     ret.insert("do".to_string(), Kind::Do);
     ret.insert("fn".to_string(), Kind::Fn);
     ret.insert("end".to_string(), Kind::End);
     ret.insert("while".to_string(), Kind::While);
     ret.insert("for".to_string(), Kind::For);
+    ret.insert("in".to_string(), Kind::In);
+    ret.insert("break".to_string(), Kind::Break);
+    ret.insert("continue".to_string(), Kind::Continue);
     ret.insert("static".to_string(), Kind::Static);
     ret.insert("public".to_string(), Kind::Public);
     ret.insert("match".to_string(), Kind::Match);
@@ -304,6 +377,8 @@ pub fn build_simple_dictionary() -> SimpleDict {
     ret.insert("if".to_string(), Kind::If);
     ret.insert("else".to_string(), Kind::Else);
     ret.insert("type".to_string(), Kind::Type);
+    ret.insert("use".to_string(), Kind::Use);
+    ret.insert("as".to_string(), Kind::As);
 
     // types / values
     ret.insert("null".to_string(), Kind::Null);
@@ -327,14 +402,11 @@ pub fn build_simple_dictionary() -> SimpleDict {
     ret.insert("usize".to_string(), Kind::USize);
     ret.insert("isize".to_string(), Kind::ISize);
 
-    return ret;
+    ret
 }
 
 fn simple_eval_kind(dict: SimpleDict, input: String) -> Option<Kind> {
-    return match dict.get(&input) {
-        None => None,
-        Some(kind_ptr) => Some(kind_ptr.clone()),
-    };
+    dict.get(&input).cloned()
 }
 
 fn complex_eval_kind(dict: ComplexDict, input: String) -> Option<Kind> {
@@ -345,10 +417,7 @@ fn complex_eval_kind(dict: ComplexDict, input: String) -> Option<Kind> {
     } else {
         let first = matches[0];
         let kind_opt = translator.get(&first);
-        match kind_opt {
-            Some(kind) => Some(kind.clone()),
-            None => None,
-        }
+        kind_opt.cloned()
     }
 }
 
@@ -360,14 +429,14 @@ pub fn find_kind(
     let ret = None;
 
     let mut check = simple_eval_kind(simple_dictionary, input.clone());
-    if check != None {
+    if check.is_some() {
         return check;
     }
 
     check = complex_eval_kind(complex_dict, input.clone());
-    if check != None {
+    if check.is_some() {
         return check;
     }
 
-    return ret;
+    ret
 }
@@ -1,5 +1,9 @@
 extern crate core;
 
+pub mod cst;
+pub mod diagnostics;
+pub mod formatter;
 pub mod lexer;
-mod parser;
-mod semantic_analyzer;
+pub mod modules;
+pub mod parser;
+pub mod semantic_analyzer;
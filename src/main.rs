@@ -1,8 +1,13 @@
 extern crate core;
 
-use std::{env, fs};
-
-mod lexer;
+use rot::formatter::formatter::format_source;
+use rot::lexer;
+use rot::modules::loader::load_program;
+use rot::parser::parser::parse_source;
+use rot::parser::serialize::{to_json, to_sexpr};
+use rot::semantic_analyzer::semantic_analyzer::analyze;
+use std::path::Path;
+use std::{env, fs, process};
 
 fn main() {
     let args: Vec<String> = env::args().collect();
@@ -13,12 +18,155 @@ fn main() {
         }
         Some(path) => path,
     };
+    if file_path == "fmt" {
+        process::exit(fmt(&args[2..]));
+    }
+    if file_path == "check" {
+        process::exit(check(&args[2..]));
+    }
+    if file_path == "parse" {
+        process::exit(parse(&args[2..]));
+    }
 
-    let content = fs::read(file_path).expect(&format!("could not read {}", file_path));
+    let content = fs::read(file_path).unwrap_or_else(|_| panic!("could not read {}", file_path));
     let sample: Vec<char> = content.iter().map(|x| *x as char).collect();
     let mut lex = lexer::lexer::Lexer::new(sample);
     lex.lex();
+    lex.diagnostics.iter().for_each(|d| eprintln!("{}", d));
 
     println!("Printing tokens");
     lex.tokens.iter().for_each(|tok| println!("{}", tok));
 }
+
+// `rot fmt [--check] <files>`, rewrites files in place or with `--check` only
+// reports the ones that would change. Returns the exit code.
+fn fmt(args: &[String]) -> i32 {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--check").collect();
+    if files.is_empty() {
+        println!("usage: rot fmt [--check] <files>");
+        return 2;
+    }
+    let mut status = 0;
+    for path in files {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("could not read {}: {}", path, err);
+                status = 1;
+                continue;
+            }
+        };
+        let errors: Vec<_> = parse_source(&source)
+            .diagnostics
+            .into_iter()
+            .filter(|d| d.is_error())
+            .collect();
+        if !errors.is_empty() {
+            errors
+                .iter()
+                .for_each(|d| eprintln!("{}: {}", path, d.render(&source)));
+            status = 1;
+            continue;
+        }
+        let formatted = format_source(&source);
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("would reformat {}", path);
+            status = 1;
+        } else if let Err(err) = fs::write(path, formatted) {
+            eprintln!("could not write {}: {}", path, err);
+            status = 1;
+        }
+    }
+    status
+}
+
+// `rot parse [--format json|sexpr] <file>`, dumps the AST to stdout. Errors are
+// rendered to stderr and make the exit code 1, the JSON dump carries them too.
+fn parse(args: &[String]) -> i32 {
+    let usage = "usage: rot parse [--format json|sexpr] <file>";
+    let mut format = "json";
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some(value @ ("json" | "sexpr")) => format = value,
+                _ => {
+                    println!("{}", usage);
+                    return 2;
+                }
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                println!("{}", usage);
+                return 2;
+            }
+        }
+    }
+    let path = match path {
+        Some(path) => path,
+        None => {
+            println!("{}", usage);
+            return 2;
+        }
+    };
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(err) => {
+            eprintln!("could not read {}: {}", path, err);
+            return 1;
+        }
+    };
+    let result = parse_source(&source);
+    result
+        .diagnostics
+        .iter()
+        .for_each(|d| eprintln!("{}: {}", path, d.render(&source)));
+    match format {
+        "sexpr" => print!("{}", to_sexpr(&result.module)),
+        _ => println!("{}", to_json(&result.module, &result.diagnostics)),
+    }
+    if result.has_errors() {
+        1
+    } else {
+        0
+    }
+}
+
+// `rot check <file>`, loads the file and every module it imports, analyzes them
+// and reports the problems found in any of them. Returns the exit code.
+fn check(args: &[String]) -> i32 {
+    let path = match args {
+        [path] => path,
+        _ => {
+            println!("usage: rot check <file>");
+            return 2;
+        }
+    };
+    let program = match load_program(Path::new(path)) {
+        Ok(program) => program,
+        Err(err) => {
+            eprintln!("could not read {}: {}", path, err);
+            return 1;
+        }
+    };
+    let mut failed = program.has_errors();
+    for module in &program.modules {
+        let analysis = analyze(&module.module);
+        failed |= analysis.iter().any(|d| d.is_error());
+        module
+            .diagnostics
+            .iter()
+            .chain(&analysis)
+            .for_each(|d| eprintln!("{}", program.source_map.render(module.file, d)));
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
@@ -0,0 +1,179 @@
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::token::Span;
+use crate::modules::source_map::{FileId, SourceMap};
+use crate::parser::ast::*;
+use crate::parser::expand::expand_macros;
+use crate::parser::parser::parse_source;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+pub const EXTENSION: &str = "rot";
+
+/// A parsed and macro expanded file of the program along with the parse,
+/// expansion and import errors in it.
+#[derive(Debug)]
+pub struct LoadedModule {
+    pub name: String,
+    pub file: FileId,
+    pub module: Module,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The entry module and everything it imports, transitively.
+#[derive(Debug)]
+pub struct Program {
+    pub source_map: SourceMap,
+    /// Dependencies come before the modules that import them, the entry module
+    /// is last.
+    pub modules: Vec<LoadedModule>,
+}
+
+impl Program {
+    pub fn module(&self, name: &str) -> Option<&LoadedModule> {
+        self.modules.iter().find(|module| module.name == name)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.modules
+            .iter()
+            .any(|module| module.diagnostics.iter().any(|d| d.is_error()))
+    }
+}
+
+/// Module `a.b.c` lives in `a/b/c.rot` below the directory of the entry file.
+pub fn module_path(root: &Path, name: &str) -> PathBuf {
+    let mut path = root.to_path_buf();
+    path.extend(name.split('.'));
+    path.set_extension(EXTENSION);
+    path
+}
+
+/// Loads `entry` and every module it imports from disk. The entry module is
+/// named after its file stem and its directory is the root for imports.
+pub fn load_program(entry: &Path) -> io::Result<Program> {
+    let root = entry.parent().unwrap_or(Path::new(""));
+    let name = entry
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    load_program_with(root, &name, |path| fs::read_to_string(path))
+}
+
+/// Like `load_program` with files read through `read`, which receives the
+/// path given by `module_path`. Fails only when the entry module cannot be read.
+pub fn load_program_with(
+    root: &Path,
+    entry: &str,
+    read: impl FnMut(&Path) -> io::Result<String>,
+) -> io::Result<Program> {
+    let mut loader = Loader {
+        root: root.to_path_buf(),
+        read,
+        source_map: SourceMap::new(),
+        modules: Vec::new(),
+        loaded: HashMap::new(),
+        stack: Vec::new(),
+    };
+    let path = module_path(root, entry);
+    let source = (loader.read)(&path)?;
+    loader.load(entry.to_string(), path, source);
+    Ok(Program {
+        source_map: loader.source_map,
+        modules: loader.modules,
+    })
+}
+
+struct Loader<R> {
+    root: PathBuf,
+    read: R,
+    source_map: SourceMap,
+    modules: Vec<LoadedModule>,
+    // index into `modules` of every finished module
+    loaded: HashMap<String, usize>,
+    // modules whose imports are being loaded, a `use` of one of them is a cycle
+    stack: Vec<String>,
+}
+
+impl<R: FnMut(&Path) -> io::Result<String>> Loader<R> {
+    fn load(&mut self, name: String, path: PathBuf, source: String) -> usize {
+        let result = parse_source(&source);
+        let file = self.source_map.add(path, source);
+        let (module, expansion_errors) = expand_macros(result.module);
+        let mut diagnostics = result.diagnostics;
+        diagnostics.extend(expansion_errors);
+        self.stack.push(name.clone());
+        for item in &module.items {
+            if let ItemKind::Use(decl) = &item.kind {
+                self.import(decl, item.span, &mut diagnostics);
+            }
+        }
+        self.stack.pop();
+        self.modules.push(LoadedModule {
+            name: name.clone(),
+            file,
+            module,
+            diagnostics,
+        });
+        self.loaded.insert(name, self.modules.len() - 1);
+        self.modules.len() - 1
+    }
+
+    fn import(&mut self, decl: &UseDecl, span: Span, diagnostics: &mut Vec<Diagnostic>) {
+        let target = decl.module_name();
+        if let Some(position) = self.stack.iter().position(|name| *name == target) {
+            let mut cycle = self.stack[position..].to_vec();
+            cycle.push(target);
+            diagnostics.push(Diagnostic::error(
+                format!("import cycle: {}", cycle.join(" -> ")),
+                span,
+            ));
+            return;
+        }
+        let index = match self.loaded.get(&target) {
+            Some(index) => *index,
+            None => {
+                let path = module_path(&self.root, &target);
+                match (self.read)(&path) {
+                    Ok(source) => self.load(target.clone(), path, source),
+                    Err(_) => {
+                        let relative = module_path(Path::new(""), &target);
+                        diagnostics.push(Diagnostic::error(
+                            format!(
+                                "cannot find module `{}`, looked for `{}`",
+                                target,
+                                relative.display()
+                            ),
+                            span,
+                        ));
+                        return;
+                    }
+                }
+            }
+        };
+        let module = &self.modules[index].module;
+        for name in decl.names.iter().flatten() {
+            let defined = module
+                .items
+                .iter()
+                .any(|item| item_name(item).is_some_and(|n| n.name == name.name));
+            if !defined {
+                diagnostics.push(Diagnostic::error(
+                    format!("module `{}` has no item `{}`", target, name.name),
+                    name.span,
+                ));
+            }
+        }
+    }
+}
+
+/// The name an item is imported by, impls, imports and macros are not
+/// importable.
+pub fn item_name(item: &Item) -> Option<&Ident> {
+    match &item.kind {
+        ItemKind::Function(decl) => Some(&decl.name),
+        ItemKind::Struct(decl) => Some(&decl.name),
+        ItemKind::TypeAlias(alias) => Some(&alias.name),
+        ItemKind::Impl(_) | ItemKind::Macro(_) | ItemKind::Use(_) => None,
+    }
+}
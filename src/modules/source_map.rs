@@ -0,0 +1,51 @@
+use crate::diagnostics::diagnostic::Diagnostic;
+use std::path::{Path, PathBuf};
+
+/// Identifies a file added to a `SourceMap`.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct FileId(pub u32);
+
+#[derive(Debug)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub source: String,
+}
+
+/// Every file of a program, so spans that are only meaningful within one file
+/// can be rendered against the right source.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, path: PathBuf, source: String) -> FileId {
+        self.files.push(SourceFile { path, source });
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0 as usize]
+    }
+
+    pub fn path(&self, id: FileId) -> &Path {
+        &self.file(id).path
+    }
+
+    pub fn source(&self, id: FileId) -> &str {
+        &self.file(id).source
+    }
+
+    /// `path: error: message` followed by the `line:column` in that file.
+    pub fn render(&self, id: FileId, diagnostic: &Diagnostic) -> String {
+        format!(
+            "{}: {}",
+            self.path(id).display(),
+            diagnostic.render(self.source(id))
+        )
+    }
+}
@@ -0,0 +1,569 @@
+use crate::lexer::token::Span;
+use std::fmt;
+
+/// Identifies an expression node so later passes can attach information to it.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
+pub struct NodeId(pub u32);
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Ident {
+    /// The name as written, without the `#N` macro expansion appends to the
+    /// names of a macro body, see `expand_macros`.
+    pub fn source_name(&self) -> &str {
+        self.name.split('#').next().unwrap_or(&self.name)
+    }
+
+    /// The name of a module level definition a macro body refers to, which
+    /// expansion marks as `name#0`.
+    pub fn module_level_name(&self) -> Option<&str> {
+        self.name.strip_suffix("#0")
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Module {
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Item {
+    pub kind: ItemKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ItemKind {
+    Function(FunctionDecl),
+    Struct(StructDecl),
+    Impl(ImplBlock),
+    TypeAlias(TypeAlias),
+    Use(UseDecl),
+    Macro(MacroDecl),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct FunctionDecl {
+    pub name: Ident,
+    pub generics: Vec<GenericParam>,
+    pub receiver: Option<Receiver>,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeExpr>,
+    pub body: Block,
+}
+
+/// The explicit `self` taken by instance methods.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Receiver {
+    pub span: Span,
+}
+
+/// `T: Show + Eq` in `def show<T: Show + Eq>(x: T)`, the bounds may be empty.
+#[derive(PartialEq, Debug, Clone)]
+pub struct GenericParam {
+    pub name: Ident,
+    pub bounds: Vec<TypeExpr>,
+    pub span: Span,
+}
+
+/// `x: i32`, or `mut x: i32` for a parameter the body may assign to.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Param {
+    pub mutable: bool,
+    pub name: Ident,
+    pub ty: TypeExpr,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Visibility {
+    Private,
+    Public,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct StructDecl {
+    pub name: Ident,
+    pub generics: Vec<GenericParam>,
+    pub fields: Vec<StructField>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct StructField {
+    pub visibility: Visibility,
+    /// Declared `mut`, only then can the field be assigned after construction.
+    pub mutable: bool,
+    pub name: Ident,
+    pub ty: TypeExpr,
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+/// `defimpl Point do ... end`
+#[derive(PartialEq, Debug, Clone)]
+pub struct ImplBlock {
+    pub target: Ident,
+    pub methods: Vec<Method>,
+}
+
+/// A `def` inside a `defimpl`. Static methods have no receiver and are called
+/// through the type, `Point.origin()`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Method {
+    pub is_static: bool,
+    pub function: FunctionDecl,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct TypeAlias {
+    pub name: Ident,
+    pub generics: Vec<GenericParam>,
+    pub ty: TypeExpr,
+}
+
+/// `defmacro twice(e) do e; e end`. A call to the macro is replaced by its body
+/// with the arguments put in place of the parameters, see `expand`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MacroDecl {
+    pub name: Ident,
+    pub params: Vec<Ident>,
+    pub body: Block,
+}
+
+/// `use geometry.shapes` imports a module, `use geometry.shapes.{Circle, area}`
+/// imports the listed items of it.
+#[derive(PartialEq, Debug, Clone)]
+pub struct UseDecl {
+    pub path: Vec<Ident>,
+    pub names: Option<Vec<Ident>>,
+}
+
+impl UseDecl {
+    /// The dotted module path, `geometry.shapes`.
+    pub fn module_name(&self) -> String {
+        self.path
+            .iter()
+            .map(|ident| ident.name.as_str())
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct TypeExpr {
+    pub kind: TypeKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum TypeKind {
+    Primitive(PrimitiveType),
+    Named(Ident),
+    /// `Pair<i32, T>`, a named type applied to type arguments.
+    Generic {
+        name: Ident,
+        args: Vec<TypeExpr>,
+    },
+    Function {
+        params: Vec<FunctionTypeParam>,
+        ret: Box<TypeExpr>,
+    },
+    /// `T?`, a `T` or `null`.
+    Nullable(Box<TypeExpr>),
+    /// `[T]`
+    List(Box<TypeExpr>),
+    /// `{K => V}`
+    Map {
+        key: Box<TypeExpr>,
+        value: Box<TypeExpr>,
+    },
+    /// `(A, B)`, `()` is the unit type.
+    Tuple(Vec<TypeExpr>),
+    /// Stands in for a type the parser could not read.
+    Missing,
+}
+
+/// A parameter of a function type, `(x: usize) -> string` keeps the optional name.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FunctionTypeParam {
+    pub name: Option<Ident>,
+    pub ty: TypeExpr,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum PrimitiveType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    I128,
+    U128,
+    ISize,
+    USize,
+    Float,
+    Double,
+    Bool,
+    Char,
+    String,
+}
+
+impl PrimitiveType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrimitiveType::I8 => "i8",
+            PrimitiveType::U8 => "u8",
+            PrimitiveType::I16 => "i16",
+            PrimitiveType::U16 => "u16",
+            PrimitiveType::I32 => "i32",
+            PrimitiveType::U32 => "u32",
+            PrimitiveType::I64 => "i64",
+            PrimitiveType::U64 => "u64",
+            PrimitiveType::I128 => "i128",
+            PrimitiveType::U128 => "u128",
+            PrimitiveType::ISize => "isize",
+            PrimitiveType::USize => "usize",
+            PrimitiveType::Float => "float",
+            PrimitiveType::Double => "double",
+            PrimitiveType::Bool => "boolean",
+            PrimitiveType::Char => "char",
+            PrimitiveType::String => "string",
+        }
+    }
+}
+
+impl fmt::Display for PrimitiveType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum StmtKind {
+    Let(LetStmt),
+    Expr(Expr),
+}
+
+/// `x: u8 = 1`, or `mut x: u8 = 1` for a binding that can be assigned again.
+/// The type is only optional with `mut`, `mut x = 1`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LetStmt {
+    pub mutable: bool,
+    pub name: Ident,
+    pub ty: Option<TypeExpr>,
+    pub value: Expr,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Expr {
+    pub id: NodeId,
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ExprKind {
+    Literal(Literal),
+    Ident(Ident),
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// `a = b`, or `a += b` with the operator in `op`. The target of a
+    /// compound assignment is evaluated once, so it is not `a = a + b` when
+    /// `a` is `xs[f()]`.
+    Assign {
+        target: Box<Expr>,
+        op: Option<BinaryOp>,
+        value: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    /// `a.b`, or `a?.b` when `safe`, which yields `null` for a `null` base.
+    Field {
+        base: Box<Expr>,
+        name: Ident,
+        safe: bool,
+    },
+    MethodCall {
+        receiver: Box<Expr>,
+        method: Ident,
+        args: Vec<Expr>,
+        safe: bool,
+    },
+    StructLit {
+        name: Ident,
+        fields: Vec<FieldInit>,
+    },
+    /// `[1, 2, 3]`
+    List(Vec<Expr>),
+    /// `{"a" => 1}`
+    Map(Vec<MapEntry>),
+    /// `(a, b)`, a single element tuple needs a trailing comma, `(a,)`.
+    Tuple(Vec<Expr>),
+    /// `xs[i]`
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+    /// `x as u8`, an explicit numeric conversion.
+    Cast {
+        expr: Box<Expr>,
+        ty: TypeExpr,
+    },
+    Lambda(Lambda),
+    If(IfExpr),
+    Match(MatchExpr),
+    While(WhileExpr),
+    For(ForExpr),
+    Break {
+        label: Option<Ident>,
+        value: Option<Box<Expr>>,
+    },
+    Continue {
+        label: Option<Ident>,
+    },
+    Block(Block),
+    Return(Option<Box<Expr>>),
+    /// Stands in for an expression the parser could not read.
+    Missing,
+}
+
+/// `[label:] while cond do ... end`
+#[derive(PartialEq, Debug, Clone)]
+pub struct WhileExpr {
+    pub label: Option<Ident>,
+    pub condition: Box<Expr>,
+    pub body: Block,
+}
+
+/// `[label:] for pattern in iterable do ... end`
+#[derive(PartialEq, Debug, Clone)]
+pub struct ForExpr {
+    pub label: Option<Ident>,
+    pub pattern: Pattern,
+    pub iterable: Box<Expr>,
+    pub body: Block,
+}
+
+/// `if a do ... else if b do ... else ... end`, the value is the last expression
+/// of whichever branch runs.
+#[derive(PartialEq, Debug, Clone)]
+pub struct IfExpr {
+    pub branches: Vec<IfBranch>,
+    pub else_block: Option<Block>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct IfBranch {
+    pub condition: Expr,
+    pub block: Block,
+}
+
+/// `fn x, y: i32 -> i32 do x + y end`
+#[derive(PartialEq, Debug, Clone)]
+pub struct Lambda {
+    pub params: Vec<LambdaParam>,
+    pub return_type: Option<TypeExpr>,
+    pub body: Block,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct LambdaParam {
+    pub name: Ident,
+    pub ty: Option<TypeExpr>,
+    pub span: Span,
+}
+
+/// `match value do pattern -> expr ... end`
+#[derive(PartialEq, Debug, Clone)]
+pub struct MatchExpr {
+    pub scrutinee: Box<Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum PatternKind {
+    /// `_`
+    Wildcard,
+    Binding(Ident),
+    /// Literals, including `null`.
+    Literal(Literal),
+    Tuple(Vec<Pattern>),
+    Struct {
+        name: Ident,
+        fields: Vec<FieldPattern>,
+    },
+    /// `a | b`
+    Or(Vec<Pattern>),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct FieldPattern {
+    pub name: Ident,
+    pub pattern: Pattern,
+    pub span: Span,
+}
+
+impl Pattern {
+    /// Names bound by the pattern, in source order. Every alternative of an
+    /// or-pattern binds the same names, so only the first is consulted.
+    pub fn bindings(&self) -> Vec<&Ident> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings<'a>(&'a self, names: &mut Vec<&'a Ident>) {
+        match &self.kind {
+            PatternKind::Wildcard | PatternKind::Literal(_) => {}
+            PatternKind::Binding(ident) => names.push(ident),
+            PatternKind::Tuple(elements) => {
+                elements.iter().for_each(|p| p.collect_bindings(names));
+            }
+            PatternKind::Struct { fields, .. } => {
+                fields
+                    .iter()
+                    .for_each(|f| f.pattern.collect_bindings(names));
+            }
+            PatternKind::Or(alternatives) => {
+                if let Some(first) = alternatives.first() {
+                    first.collect_bindings(names);
+                }
+            }
+        }
+    }
+}
+
+/// `x: 1` inside a struct literal.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FieldInit {
+    pub name: Ident,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// `key => value` inside a map literal.
+#[derive(PartialEq, Debug, Clone)]
+pub struct MapEntry {
+    pub key: Expr,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// Literal payloads keep their source text so range checks can happen later.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Literal {
+    Int(String),
+    Float(String),
+    Str(String),
+    Char(String),
+    Regex(String),
+    Bool(bool),
+    Null,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "!",
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    NotEq,
+    Lt,
+    Gt,
+    LtEq,
+    GtEq,
+    And,
+    Or,
+    /// `a ?: b`, `a` unless it is `null`.
+    Elvis,
+    /// `x |> f(a)`, the right side is a call, method call or function name
+    /// and `desugar` turns the whole into `f(x, a)`.
+    Pipe,
+}
+
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::Elvis => "?:",
+            BinaryOp::Pipe => "|>",
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::parser::ast::*;
+use crate::parser::fold::{self, Folder};
+use crate::parser::visit::{self, Visitor};
+
+/// Lowers the sugar the parser keeps for tools: pipelines become calls,
+/// `x |> f(a)` is `f(x, a)`, `x |> f` is `f(x)` and `x |> s.push()` is
+/// `s.push(x)`. The call takes the id and span of the pipeline, so the piped
+/// value comes before the callee in the source.
+///
+/// Compound assignments stay, `xs[f()] += 1` evaluates `f()` once, which
+/// `xs[f()] = xs[f()] + 1` would not.
+///
+/// Everything else keeps its id.
+pub fn desugar(module: Module) -> Module {
+    Desugarer.fold_module(module)
+}
+
+/// Hands out expression ids above every id in a module, for passes that add
+/// expressions to it.
+pub(crate) struct Ids(u32);
+
+impl Ids {
+    pub(crate) fn after(module: &Module) -> Ids {
+        let mut max = MaxId(None);
+        max.visit_module(module);
+        Ids(max.0.map_or(0, |id| id + 1))
+    }
+
+    pub(crate) fn fresh(&mut self) -> NodeId {
+        let id = NodeId(self.0);
+        self.0 += 1;
+        id
+    }
+
+    // a copy of `expr` whose nodes all have new ids
+    pub(crate) fn renumber(&mut self, expr: Expr) -> Expr {
+        let mut renumber = Renumber(self);
+        renumber.fold_expr(expr)
+    }
+}
+
+struct MaxId(Option<u32>);
+
+impl Visitor for MaxId {
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.0 = self.0.max(Some(expr.id.0));
+        visit::walk_expr(self, expr);
+    }
+}
+
+struct Renumber<'a>(&'a mut Ids);
+
+impl Folder for Renumber<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = fold::fold_expr(self, expr);
+        Expr {
+            id: self.0.fresh(),
+            ..expr
+        }
+    }
+}
+
+struct Desugarer;
+
+impl Folder for Desugarer {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = fold::fold_expr(self, expr);
+        match expr.kind {
+            ExprKind::Binary {
+                op: BinaryOp::Pipe,
+                lhs,
+                rhs,
+            } => {
+                let kind = match rhs.kind {
+                    ExprKind::Call { callee, mut args } => {
+                        args.insert(0, *lhs);
+                        ExprKind::Call { callee, args }
+                    }
+                    ExprKind::MethodCall {
+                        receiver,
+                        method,
+                        mut args,
+                        safe,
+                    } => {
+                        args.insert(0, *lhs);
+                        ExprKind::MethodCall {
+                            receiver,
+                            method,
+                            args,
+                            safe,
+                        }
+                    }
+                    // the parser only accepts calls and names after `|>`
+                    _ => ExprKind::Call {
+                        callee: rhs,
+                        args: vec![*lhs],
+                    },
+                };
+                Expr { kind, ..expr }
+            }
+            kind => Expr { kind, ..expr },
+        }
+    }
+}
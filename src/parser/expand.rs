@@ -0,0 +1,366 @@
+use crate::diagnostics::diagnostic::{plural, Diagnostic};
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use crate::parser::desugar::Ids;
+use crate::parser::fold::{self, Folder};
+use crate::parser::visit::{self, Visitor};
+use crate::parser::visit_mut::{self, VisitorMut};
+use std::collections::{HashMap, HashSet};
+
+/// How deep expansions may nest before a macro is taken to expand forever.
+pub const RECURSION_LIMIT: usize = 64;
+
+/// Replaces every call of a `defmacro` with the macro's body and removes the
+/// macro items, before any semantic analysis sees the module.
+///
+/// - the arguments are put in place of the parameters as written, unevaluated,
+///   so a parameter used twice evaluates its argument twice.
+/// - expansion is hygienic. Bindings the body introduces are renamed to
+///   `name#N`, which no source can spell, so they neither capture nor shadow
+///   names at the call site. The other names of the body, besides the
+///   parameters, become `name#0` and refer to what the module defines even
+///   where the call site has a local of that name.
+/// - everything taken from the body gets the span of the call, the arguments
+///   keep their own. Names keep their span in the macro, so that two bindings
+///   of one name in the body stay apart. Every inserted expression gets a
+///   fresh id and the expansion, a block expression, takes the id of the call.
+pub fn expand_macros(module: Module) -> (Module, Vec<Diagnostic>) {
+    let mut macros = HashMap::new();
+    let mut items = Vec::new();
+    for item in module.items.iter().cloned() {
+        match item.kind {
+            ItemKind::Macro(decl) => {
+                macros.insert(decl.name.name.clone(), decl);
+            }
+            _ => items.push(item),
+        }
+    }
+    let mut expander = Expander {
+        ids: Ids::after(&module),
+        macros,
+        expansions: 0,
+        depth: 0,
+        diagnostics: Vec::new(),
+    };
+    let module = Module {
+        items: items
+            .into_iter()
+            .map(|item| expander.fold_item(item))
+            .collect(),
+        span: module.span,
+    };
+    (module, expander.diagnostics)
+}
+
+struct Expander {
+    ids: Ids,
+    macros: HashMap<String, MacroDecl>,
+    // numbers the hygienic renames, one per expansion
+    expansions: usize,
+    depth: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Expander {
+    fn expand(&mut self, call: &Expr, decl: &MacroDecl, args: Vec<Expr>) -> Expr {
+        let name = &decl.name.name;
+        let missing = Expr {
+            id: call.id,
+            span: call.span,
+            kind: ExprKind::Missing,
+        };
+        if args.len() != decl.params.len() {
+            let message = format!(
+                "macro `{}` takes {} but {} {} given",
+                name,
+                plural(decl.params.len(), "argument"),
+                args.len(),
+                if args.len() == 1 { "was" } else { "were" }
+            );
+            self.diagnostics.push(Diagnostic::error(message, call.span));
+            return missing;
+        }
+        if self.depth == RECURSION_LIMIT {
+            self.diagnostics.push(Diagnostic::error(
+                format!("recursion limit reached while expanding `{}`", name),
+                call.span,
+            ));
+            return missing;
+        }
+
+        self.expansions += 1;
+        let params: HashSet<&str> = decl.params.iter().map(|p| p.name.as_str()).collect();
+        let mut introduced = Introduced {
+            params: &params,
+            names: HashSet::new(),
+        };
+        introduced.visit_block(&decl.body);
+        let mut rename = Rename {
+            params: &params,
+            names: &introduced.names,
+            suffix: format!("#{}", self.expansions),
+        };
+        let mut body = decl.body.clone();
+        MoveTo(call.span).visit_block_mut(&mut body);
+        let body = rename.fold_block(body);
+
+        let args = decl
+            .params
+            .iter()
+            .map(|param| param.name.clone())
+            .zip(args)
+            .collect();
+        let mut substitute = Substitute {
+            ids: &mut self.ids,
+            args,
+        };
+        let body = substitute.fold_block(body);
+        let expansion = Expr {
+            id: call.id,
+            span: call.span,
+            kind: ExprKind::Block(body),
+        };
+
+        // the expansion may call macros itself
+        self.depth += 1;
+        let expansion = self.fold_expr(expansion);
+        self.depth -= 1;
+        expansion
+    }
+}
+
+impl Folder for Expander {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = fold::fold_expr(self, expr);
+        let decl = match &expr.kind {
+            ExprKind::Call { callee, .. } => match &callee.kind {
+                // a macro body calls other macros by their module level name
+                ExprKind::Ident(ident) => {
+                    let name = ident.module_level_name().unwrap_or(&ident.name);
+                    self.macros.get(name).cloned()
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        match (decl, expr.kind) {
+            (Some(decl), ExprKind::Call { args, .. }) => {
+                let call = Expr {
+                    kind: ExprKind::Missing,
+                    ..expr
+                };
+                self.expand(&call, &decl, args)
+            }
+            (_, kind) => Expr { kind, ..expr },
+        }
+    }
+}
+
+// names a macro body binds, other than its parameters
+struct Introduced<'a> {
+    params: &'a HashSet<&'a str>,
+    names: HashSet<String>,
+}
+
+impl Introduced<'_> {
+    fn bind(&mut self, ident: &Ident) {
+        if !self.params.contains(ident.name.as_str()) {
+            self.names.insert(ident.name.clone());
+        }
+    }
+}
+
+impl Visitor for Introduced<'_> {
+    fn visit_let(&mut self, binding: &LetStmt) {
+        self.bind(&binding.name);
+        visit::walk_let(self, binding);
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        if let PatternKind::Binding(ident) = &pattern.kind {
+            self.bind(ident);
+        }
+        visit::walk_pattern(self, pattern);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            // the locals of the call site are out of the body's reach, so a
+            // name it assigns is one it binds
+            ExprKind::Assign { target, .. } => {
+                if let ExprKind::Ident(ident) = &target.kind {
+                    self.bind(ident);
+                }
+            }
+            ExprKind::Lambda(lambda) => {
+                for param in &lambda.params {
+                    self.bind(&param.name);
+                }
+            }
+            _ => {}
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+// renames the binding and use sites of `names` and marks the other names
+// used as module level ones, leaving parameters, fields, methods and types
+// alone
+struct Rename<'a> {
+    params: &'a HashSet<&'a str>,
+    names: &'a HashSet<String>,
+    suffix: String,
+}
+
+impl Rename<'_> {
+    fn rename(&self, ident: Ident) -> Ident {
+        match self.names.contains(&ident.name) {
+            true => Ident {
+                name: format!("{}{}", ident.name, self.suffix),
+                ..ident
+            },
+            false => ident,
+        }
+    }
+
+    fn rename_use(&self, ident: Ident) -> Ident {
+        if self.names.contains(&ident.name) || self.params.contains(ident.name.as_str()) {
+            return self.rename(ident);
+        }
+        Ident {
+            name: format!("{}#0", ident.name),
+            ..ident
+        }
+    }
+}
+
+impl Folder for Rename<'_> {
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        match stmt.kind {
+            StmtKind::Let(binding) => Stmt {
+                kind: StmtKind::Let(LetStmt {
+                    name: self.rename(binding.name),
+                    ty: binding.ty,
+                    value: self.fold_expr(binding.value),
+                    mutable: binding.mutable,
+                }),
+                span: stmt.span,
+            },
+            StmtKind::Expr(expr) => Stmt {
+                kind: StmtKind::Expr(self.fold_expr(expr)),
+                span: stmt.span,
+            },
+        }
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        match expr.kind {
+            ExprKind::Ident(ident) => Expr {
+                kind: ExprKind::Ident(self.rename_use(ident)),
+                ..expr
+            },
+            ExprKind::Lambda(lambda) => {
+                let params = lambda
+                    .params
+                    .into_iter()
+                    .map(|param| LambdaParam {
+                        name: self.rename(param.name),
+                        ..param
+                    })
+                    .collect();
+                let lambda = Lambda { params, ..lambda };
+                Expr {
+                    kind: ExprKind::Lambda(fold::fold_lambda(self, lambda)),
+                    ..expr
+                }
+            }
+            kind => fold::fold_expr(self, Expr { kind, ..expr }),
+        }
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        match pattern.kind {
+            PatternKind::Binding(ident) => Pattern {
+                kind: PatternKind::Binding(self.rename(ident)),
+                span: pattern.span,
+            },
+            kind => fold::fold_pattern(self, Pattern { kind, ..pattern }),
+        }
+    }
+}
+
+// moves every node of a macro body to the call site
+struct MoveTo(Span);
+
+impl VisitorMut for MoveTo {
+    fn visit_type_mut(&mut self, ty: &mut TypeExpr) {
+        ty.span = self.0;
+        visit_mut::walk_type_mut(self, ty);
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        block.span = self.0;
+        visit_mut::walk_block_mut(self, block);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        stmt.span = self.0;
+        visit_mut::walk_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        expr.span = self.0;
+        visit_mut::walk_expr_mut(self, expr);
+    }
+
+    fn visit_field_init_mut(&mut self, field: &mut FieldInit) {
+        field.span = self.0;
+        visit_mut::walk_field_init_mut(self, field);
+    }
+
+    fn visit_map_entry_mut(&mut self, entry: &mut MapEntry) {
+        entry.span = self.0;
+        visit_mut::walk_map_entry_mut(self, entry);
+    }
+
+    fn visit_lambda_param_mut(&mut self, param: &mut LambdaParam) {
+        param.span = self.0;
+        visit_mut::walk_lambda_param_mut(self, param);
+    }
+
+    fn visit_match_arm_mut(&mut self, arm: &mut MatchArm) {
+        arm.span = self.0;
+        visit_mut::walk_match_arm_mut(self, arm);
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        pattern.span = self.0;
+        if let PatternKind::Struct { fields, .. } = &mut pattern.kind {
+            for field in fields {
+                field.span = self.0;
+            }
+        }
+        visit_mut::walk_pattern_mut(self, pattern);
+    }
+}
+
+// puts the arguments in place of the parameters and gives everything else a
+// fresh id
+struct Substitute<'a> {
+    ids: &'a mut Ids,
+    args: HashMap<String, Expr>,
+}
+
+impl Folder for Substitute<'_> {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        if let ExprKind::Ident(ident) = &expr.kind {
+            if let Some(arg) = self.args.get(&ident.name) {
+                return self.ids.renumber(arg.clone());
+            }
+        }
+        Expr {
+            id: self.ids.fresh(),
+            ..fold::fold_expr(self, expr)
+        }
+    }
+}
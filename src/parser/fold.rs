@@ -0,0 +1,487 @@
+use crate::parser::ast::*;
+
+/// Rebuilds the AST by value, for passes that replace nodes wholesale such as
+/// desugarings. Every method defaults to the free function of the same name,
+/// which folds the children and keeps the node, its id and its span.
+pub trait Folder: Sized {
+    fn fold_module(&mut self, module: Module) -> Module {
+        fold_module(self, module)
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        fold_item(self, item)
+    }
+
+    fn fold_function(&mut self, decl: FunctionDecl) -> FunctionDecl {
+        fold_function(self, decl)
+    }
+
+    fn fold_generic_param(&mut self, param: GenericParam) -> GenericParam {
+        fold_generic_param(self, param)
+    }
+
+    fn fold_param(&mut self, param: Param) -> Param {
+        fold_param(self, param)
+    }
+
+    fn fold_struct(&mut self, decl: StructDecl) -> StructDecl {
+        fold_struct(self, decl)
+    }
+
+    fn fold_struct_field(&mut self, field: StructField) -> StructField {
+        fold_struct_field(self, field)
+    }
+
+    fn fold_impl(&mut self, block: ImplBlock) -> ImplBlock {
+        fold_impl(self, block)
+    }
+
+    fn fold_method(&mut self, method: Method) -> Method {
+        fold_method(self, method)
+    }
+
+    fn fold_type_alias(&mut self, alias: TypeAlias) -> TypeAlias {
+        fold_type_alias(self, alias)
+    }
+
+    fn fold_macro(&mut self, decl: MacroDecl) -> MacroDecl {
+        fold_macro(self, decl)
+    }
+
+    fn fold_use(&mut self, decl: UseDecl) -> UseDecl {
+        fold_use(self, decl)
+    }
+
+    fn fold_type(&mut self, ty: TypeExpr) -> TypeExpr {
+        fold_type(self, ty)
+    }
+
+    fn fold_block(&mut self, block: Block) -> Block {
+        fold_block(self, block)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_lambda(&mut self, lambda: Lambda) -> Lambda {
+        fold_lambda(self, lambda)
+    }
+
+    fn fold_match_arm(&mut self, arm: MatchArm) -> MatchArm {
+        fold_match_arm(self, arm)
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        fold_pattern(self, pattern)
+    }
+
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        ident
+    }
+}
+
+pub fn fold_module<F: Folder>(folder: &mut F, module: Module) -> Module {
+    Module {
+        items: module
+            .items
+            .into_iter()
+            .map(|item| folder.fold_item(item))
+            .collect(),
+        span: module.span,
+    }
+}
+
+pub fn fold_item<F: Folder>(folder: &mut F, item: Item) -> Item {
+    let kind = match item.kind {
+        ItemKind::Function(decl) => ItemKind::Function(folder.fold_function(decl)),
+        ItemKind::Struct(decl) => ItemKind::Struct(folder.fold_struct(decl)),
+        ItemKind::Impl(block) => ItemKind::Impl(folder.fold_impl(block)),
+        ItemKind::TypeAlias(alias) => ItemKind::TypeAlias(folder.fold_type_alias(alias)),
+        ItemKind::Macro(decl) => ItemKind::Macro(folder.fold_macro(decl)),
+        ItemKind::Use(decl) => ItemKind::Use(folder.fold_use(decl)),
+    };
+    Item {
+        kind,
+        span: item.span,
+    }
+}
+
+pub fn fold_function<F: Folder>(folder: &mut F, decl: FunctionDecl) -> FunctionDecl {
+    FunctionDecl {
+        name: folder.fold_ident(decl.name),
+        generics: fold_generics(folder, decl.generics),
+        receiver: decl.receiver,
+        params: decl
+            .params
+            .into_iter()
+            .map(|param| folder.fold_param(param))
+            .collect(),
+        return_type: decl.return_type.map(|ty| folder.fold_type(ty)),
+        body: folder.fold_block(decl.body),
+    }
+}
+
+pub fn fold_generic_param<F: Folder>(folder: &mut F, param: GenericParam) -> GenericParam {
+    GenericParam {
+        name: folder.fold_ident(param.name),
+        bounds: param
+            .bounds
+            .into_iter()
+            .map(|bound| folder.fold_type(bound))
+            .collect(),
+        span: param.span,
+    }
+}
+
+fn fold_generics<F: Folder>(folder: &mut F, generics: Vec<GenericParam>) -> Vec<GenericParam> {
+    generics
+        .into_iter()
+        .map(|param| folder.fold_generic_param(param))
+        .collect()
+}
+
+pub fn fold_param<F: Folder>(folder: &mut F, param: Param) -> Param {
+    Param {
+        mutable: param.mutable,
+        name: folder.fold_ident(param.name),
+        ty: folder.fold_type(param.ty),
+        span: param.span,
+    }
+}
+
+pub fn fold_struct<F: Folder>(folder: &mut F, decl: StructDecl) -> StructDecl {
+    StructDecl {
+        name: folder.fold_ident(decl.name),
+        generics: fold_generics(folder, decl.generics),
+        fields: decl
+            .fields
+            .into_iter()
+            .map(|field| folder.fold_struct_field(field))
+            .collect(),
+    }
+}
+
+pub fn fold_struct_field<F: Folder>(folder: &mut F, field: StructField) -> StructField {
+    StructField {
+        visibility: field.visibility,
+        mutable: field.mutable,
+        name: folder.fold_ident(field.name),
+        ty: folder.fold_type(field.ty),
+        default: field.default.map(|default| folder.fold_expr(default)),
+        span: field.span,
+    }
+}
+
+pub fn fold_impl<F: Folder>(folder: &mut F, block: ImplBlock) -> ImplBlock {
+    ImplBlock {
+        target: folder.fold_ident(block.target),
+        methods: block
+            .methods
+            .into_iter()
+            .map(|method| folder.fold_method(method))
+            .collect(),
+    }
+}
+
+pub fn fold_method<F: Folder>(folder: &mut F, method: Method) -> Method {
+    Method {
+        is_static: method.is_static,
+        function: folder.fold_function(method.function),
+        span: method.span,
+    }
+}
+
+pub fn fold_type_alias<F: Folder>(folder: &mut F, alias: TypeAlias) -> TypeAlias {
+    TypeAlias {
+        name: folder.fold_ident(alias.name),
+        generics: fold_generics(folder, alias.generics),
+        ty: folder.fold_type(alias.ty),
+    }
+}
+
+pub fn fold_macro<F: Folder>(folder: &mut F, decl: MacroDecl) -> MacroDecl {
+    MacroDecl {
+        name: folder.fold_ident(decl.name),
+        params: decl
+            .params
+            .into_iter()
+            .map(|param| folder.fold_ident(param))
+            .collect(),
+        body: folder.fold_block(decl.body),
+    }
+}
+
+pub fn fold_use<F: Folder>(folder: &mut F, decl: UseDecl) -> UseDecl {
+    UseDecl {
+        path: decl
+            .path
+            .into_iter()
+            .map(|segment| folder.fold_ident(segment))
+            .collect(),
+        names: decl.names.map(|names| {
+            names
+                .into_iter()
+                .map(|name| folder.fold_ident(name))
+                .collect()
+        }),
+    }
+}
+
+pub fn fold_type<F: Folder>(folder: &mut F, ty: TypeExpr) -> TypeExpr {
+    let kind = match ty.kind {
+        TypeKind::Named(name) => TypeKind::Named(folder.fold_ident(name)),
+        TypeKind::Generic { name, args } => TypeKind::Generic {
+            name: folder.fold_ident(name),
+            args: args.into_iter().map(|arg| folder.fold_type(arg)).collect(),
+        },
+        TypeKind::Function { params, ret } => TypeKind::Function {
+            params: params
+                .into_iter()
+                .map(|param| FunctionTypeParam {
+                    name: param.name.map(|name| folder.fold_ident(name)),
+                    ty: folder.fold_type(param.ty),
+                })
+                .collect(),
+            ret: Box::new(folder.fold_type(*ret)),
+        },
+        TypeKind::Nullable(inner) => TypeKind::Nullable(Box::new(folder.fold_type(*inner))),
+        TypeKind::List(element) => TypeKind::List(Box::new(folder.fold_type(*element))),
+        TypeKind::Map { key, value } => TypeKind::Map {
+            key: Box::new(folder.fold_type(*key)),
+            value: Box::new(folder.fold_type(*value)),
+        },
+        TypeKind::Tuple(elements) => TypeKind::Tuple(
+            elements
+                .into_iter()
+                .map(|element| folder.fold_type(element))
+                .collect(),
+        ),
+        kind @ (TypeKind::Primitive(_) | TypeKind::Missing) => kind,
+    };
+    TypeExpr {
+        kind,
+        span: ty.span,
+    }
+}
+
+pub fn fold_block<F: Folder>(folder: &mut F, block: Block) -> Block {
+    Block {
+        stmts: block
+            .stmts
+            .into_iter()
+            .map(|stmt| folder.fold_stmt(stmt))
+            .collect(),
+        span: block.span,
+    }
+}
+
+pub fn fold_stmt<F: Folder>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let kind = match stmt.kind {
+        StmtKind::Let(binding) => StmtKind::Let(LetStmt {
+            mutable: binding.mutable,
+            name: folder.fold_ident(binding.name),
+            ty: binding.ty.map(|ty| folder.fold_type(ty)),
+            value: folder.fold_expr(binding.value),
+        }),
+        StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(expr)),
+    };
+    Stmt {
+        kind,
+        span: stmt.span,
+    }
+}
+
+fn fold_boxed<F: Folder>(folder: &mut F, expr: Expr) -> Box<Expr> {
+    Box::new(folder.fold_expr(expr))
+}
+
+fn fold_exprs<F: Folder>(folder: &mut F, exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs
+        .into_iter()
+        .map(|expr| folder.fold_expr(expr))
+        .collect()
+}
+
+pub fn fold_expr<F: Folder>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.kind {
+        ExprKind::Ident(ident) => ExprKind::Ident(folder.fold_ident(ident)),
+        ExprKind::Unary { op, operand } => ExprKind::Unary {
+            op,
+            operand: fold_boxed(folder, *operand),
+        },
+        ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+            op,
+            lhs: fold_boxed(folder, *lhs),
+            rhs: fold_boxed(folder, *rhs),
+        },
+        ExprKind::Assign { target, op, value } => {
+            let value = fold_boxed(folder, *value);
+            ExprKind::Assign {
+                target: fold_boxed(folder, *target),
+                op,
+                value,
+            }
+        }
+        ExprKind::Call { callee, args } => ExprKind::Call {
+            callee: fold_boxed(folder, *callee),
+            args: fold_exprs(folder, args),
+        },
+        ExprKind::Field { base, name, safe } => ExprKind::Field {
+            base: fold_boxed(folder, *base),
+            name: folder.fold_ident(name),
+            safe,
+        },
+        ExprKind::MethodCall {
+            receiver,
+            method,
+            args,
+            safe,
+        } => ExprKind::MethodCall {
+            receiver: fold_boxed(folder, *receiver),
+            method: folder.fold_ident(method),
+            args: fold_exprs(folder, args),
+            safe,
+        },
+        ExprKind::StructLit { name, fields } => ExprKind::StructLit {
+            name: folder.fold_ident(name),
+            fields: fields
+                .into_iter()
+                .map(|field| FieldInit {
+                    name: folder.fold_ident(field.name),
+                    value: folder.fold_expr(field.value),
+                    span: field.span,
+                })
+                .collect(),
+        },
+        ExprKind::List(elements) => ExprKind::List(fold_exprs(folder, elements)),
+        ExprKind::Map(entries) => ExprKind::Map(
+            entries
+                .into_iter()
+                .map(|entry| MapEntry {
+                    key: folder.fold_expr(entry.key),
+                    value: folder.fold_expr(entry.value),
+                    span: entry.span,
+                })
+                .collect(),
+        ),
+        ExprKind::Tuple(elements) => ExprKind::Tuple(fold_exprs(folder, elements)),
+        ExprKind::Index { base, index } => ExprKind::Index {
+            base: fold_boxed(folder, *base),
+            index: fold_boxed(folder, *index),
+        },
+        ExprKind::Cast { expr, ty } => ExprKind::Cast {
+            expr: fold_boxed(folder, *expr),
+            ty: folder.fold_type(ty),
+        },
+        ExprKind::Lambda(lambda) => ExprKind::Lambda(folder.fold_lambda(lambda)),
+        ExprKind::If(if_expr) => ExprKind::If(IfExpr {
+            branches: if_expr
+                .branches
+                .into_iter()
+                .map(|branch| IfBranch {
+                    condition: folder.fold_expr(branch.condition),
+                    block: folder.fold_block(branch.block),
+                })
+                .collect(),
+            else_block: if_expr.else_block.map(|block| folder.fold_block(block)),
+        }),
+        ExprKind::Match(match_expr) => ExprKind::Match(MatchExpr {
+            scrutinee: fold_boxed(folder, *match_expr.scrutinee),
+            arms: match_expr
+                .arms
+                .into_iter()
+                .map(|arm| folder.fold_match_arm(arm))
+                .collect(),
+        }),
+        ExprKind::While(while_expr) => ExprKind::While(WhileExpr {
+            label: while_expr.label,
+            condition: fold_boxed(folder, *while_expr.condition),
+            body: folder.fold_block(while_expr.body),
+        }),
+        ExprKind::For(for_expr) => {
+            let iterable = fold_boxed(folder, *for_expr.iterable);
+            ExprKind::For(ForExpr {
+                label: for_expr.label,
+                pattern: folder.fold_pattern(for_expr.pattern),
+                iterable,
+                body: folder.fold_block(for_expr.body),
+            })
+        }
+        ExprKind::Break { label, value } => ExprKind::Break {
+            label,
+            value: value.map(|value| fold_boxed(folder, *value)),
+        },
+        ExprKind::Block(block) => ExprKind::Block(folder.fold_block(block)),
+        ExprKind::Return(value) => ExprKind::Return(value.map(|value| fold_boxed(folder, *value))),
+        kind @ (ExprKind::Literal(_) | ExprKind::Continue { .. } | ExprKind::Missing) => kind,
+    };
+    Expr {
+        id: expr.id,
+        kind,
+        span: expr.span,
+    }
+}
+
+pub fn fold_lambda<F: Folder>(folder: &mut F, lambda: Lambda) -> Lambda {
+    Lambda {
+        params: lambda
+            .params
+            .into_iter()
+            .map(|param| LambdaParam {
+                name: folder.fold_ident(param.name),
+                ty: param.ty.map(|ty| folder.fold_type(ty)),
+                span: param.span,
+            })
+            .collect(),
+        return_type: lambda.return_type.map(|ty| folder.fold_type(ty)),
+        body: folder.fold_block(lambda.body),
+    }
+}
+
+pub fn fold_match_arm<F: Folder>(folder: &mut F, arm: MatchArm) -> MatchArm {
+    MatchArm {
+        pattern: folder.fold_pattern(arm.pattern),
+        guard: arm.guard.map(|guard| folder.fold_expr(guard)),
+        body: folder.fold_expr(arm.body),
+        span: arm.span,
+    }
+}
+
+pub fn fold_pattern<F: Folder>(folder: &mut F, pattern: Pattern) -> Pattern {
+    let kind = match pattern.kind {
+        PatternKind::Binding(ident) => PatternKind::Binding(folder.fold_ident(ident)),
+        PatternKind::Tuple(elements) => PatternKind::Tuple(
+            elements
+                .into_iter()
+                .map(|element| folder.fold_pattern(element))
+                .collect(),
+        ),
+        PatternKind::Struct { name, fields } => PatternKind::Struct {
+            name: folder.fold_ident(name),
+            fields: fields
+                .into_iter()
+                .map(|field| FieldPattern {
+                    name: field.name,
+                    pattern: folder.fold_pattern(field.pattern),
+                    span: field.span,
+                })
+                .collect(),
+        },
+        PatternKind::Or(alternatives) => PatternKind::Or(
+            alternatives
+                .into_iter()
+                .map(|alternative| folder.fold_pattern(alternative))
+                .collect(),
+        ),
+        kind @ (PatternKind::Wildcard | PatternKind::Literal(_)) => kind,
+    };
+    Pattern {
+        kind,
+        span: pattern.span,
+    }
+}
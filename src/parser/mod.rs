@@ -1 +1,9 @@
-mod parser;
\ No newline at end of file
+pub mod ast;
+pub mod desugar;
+pub mod expand;
+pub mod fold;
+#[allow(clippy::module_inception)]
+pub mod parser;
+pub mod serialize;
+pub mod visit;
+pub mod visit_mut;
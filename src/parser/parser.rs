@@ -1,17 +1,1592 @@
-use crate::lexer::token::Token;
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::lexer::Lexer;
+use crate::lexer::token::{Kind, Span, Token};
+use crate::parser::ast::*;
 
+pub type PResult<T> = Result<T, Diagnostic>;
+
+/// A best-effort module together with everything that went wrong while parsing
+/// it. Malformed pieces are replaced by `Missing` nodes so the tree stays usable.
+#[derive(Debug)]
+pub struct ParseResult {
+    pub module: Module,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseResult {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.is_error())
+    }
+
+    /// The module if it parsed cleanly, otherwise every diagnostic.
+    pub fn into_result(self) -> Result<Module, Vec<Diagnostic>> {
+        if self.has_errors() {
+            Err(self.diagnostics)
+        } else {
+            Ok(self.module)
+        }
+    }
+}
+
+// Statements end at a `;` or a line break. Binary operators (other than `-`) may
+// continue an expression on the following line, while calls and struct literals
+// need their opening delimiter on the same line as what precedes it.
+//
+// Errors are recorded rather than returned from `parse`. A failed item skips to
+// the next `def`, `defstruct`, `defimpl`, `defmacro`, `type` or `use`, a failed
+// statement skips to the next terminator or `end`, and a missing `end` is
+// reported and assumed.
 pub struct Parser {
-    input: Vec<Token>
+    input: Vec<Token>,
+    index: usize,
+    next_id: u32,
+    // labels of the loops being parsed, `break outer` only names a label when
+    // an enclosing loop declares it
+    loop_labels: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl Parser {
     pub fn new(lexemes: Vec<Token>) -> Parser {
         Parser {
             input: lexemes
+                .into_iter()
+                .filter(|tok| tok.kind != Kind::Comment)
+                .collect(),
+            index: 0,
+            next_id: 0,
+            loop_labels: Vec::new(),
+            diagnostics: Vec::new(),
         }
     }
 
-    pub fn parse() {
-        
+    pub fn parse(&mut self) -> ParseResult {
+        let mut items = Vec::new();
+        while !self.at_eof() {
+            let start = self.index;
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_item(start);
+                }
+            }
+        }
+        ParseResult {
+            module: Module {
+                items,
+                span: Span::new(0, self.eof_span().end),
+            },
+            diagnostics: std::mem::take(&mut self.diagnostics),
+        }
     }
-}
\ No newline at end of file
+
+    // ----- token helpers -----
+
+    fn peek(&self) -> Option<&Token> {
+        self.input.get(self.index)
+    }
+
+    fn peek_kind(&self) -> Option<&Kind> {
+        self.peek().map(|tok| &tok.kind)
+    }
+
+    fn peek_nth_kind(&self, n: usize) -> Option<&Kind> {
+        self.input.get(self.index + n).map(|tok| &tok.kind)
+    }
+
+    fn at_eof(&self) -> bool {
+        self.index >= self.input.len()
+    }
+
+    fn check(&self, kind: &Kind) -> bool {
+        self.peek_kind() == Some(kind)
+    }
+
+    fn advance(&mut self) -> Token {
+        let tok = self.input[self.index].clone();
+        self.index += 1;
+        tok
+    }
+
+    fn eat(&mut self, kind: &Kind) -> Option<Token> {
+        if self.check(kind) {
+            Some(self.advance())
+        } else {
+            None
+        }
+    }
+
+    fn expect(&mut self, kind: Kind, what: &str) -> PResult<Token> {
+        match self.eat(&kind) {
+            Some(tok) => Ok(tok),
+            None => Err(self.unexpected(what)),
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> PResult<Ident> {
+        let tok = self.expect(Kind::Identifier, what)?;
+        Ok(Ident {
+            name: tok.lexeme,
+            span: tok.span,
+        })
+    }
+
+    fn eof_span(&self) -> Span {
+        match self.input.last() {
+            Some(tok) => Span::new(tok.span.end, tok.span.end),
+            None => Span::default(),
+        }
+    }
+
+    fn current_span(&self) -> Span {
+        match self.peek() {
+            Some(tok) => tok.span,
+            None => self.eof_span(),
+        }
+    }
+
+    fn prev_span(&self) -> Span {
+        match self.index.checked_sub(1).and_then(|i| self.input.get(i)) {
+            Some(tok) => tok.span,
+            None => Span::default(),
+        }
+    }
+
+    /// True when the current token starts a later line than the one consumed before it.
+    fn on_new_line(&self) -> bool {
+        match (self.index.checked_sub(1), self.peek()) {
+            (Some(prev), Some(tok)) => tok.line_number() > self.input[prev].line_number(),
+            _ => false,
+        }
+    }
+
+    fn describe_current(&self) -> String {
+        match self.peek() {
+            Some(tok) => format!("`{}`", tok.lexeme.trim()),
+            None => "end of file".to_string(),
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> Diagnostic {
+        Diagnostic::error(
+            format!("expected {}, found {}", expected, self.describe_current()),
+            self.current_span(),
+        )
+    }
+
+    fn mk_expr(&mut self, kind: ExprKind, span: Span) -> Expr {
+        let id = NodeId(self.next_id);
+        self.next_id += 1;
+        Expr { id, kind, span }
+    }
+
+    /// Records a missing `end` instead of failing, so an unterminated block still
+    /// produces a node. The returned span is empty when the `end` was assumed.
+    fn expect_end(&mut self) -> Span {
+        match self.eat(&Kind::End) {
+            Some(tok) => tok.span,
+            None => {
+                let diagnostic = self.unexpected("`end`");
+                self.diagnostics.push(diagnostic);
+                let end = self.prev_span().end;
+                Span::new(end, end)
+            }
+        }
+    }
+
+    fn expect_terminator(&mut self) -> PResult<()> {
+        if self.eat(&Kind::Semicolon).is_some() {
+            while self.eat(&Kind::Semicolon).is_some() {}
+            return Ok(());
+        }
+        if self.at_eof() || self.check(&Kind::End) || self.on_new_line() {
+            return Ok(());
+        }
+        Err(self.unexpected("`;` or a new line"))
+    }
+
+    // ----- recovery -----
+
+    fn at_item_start(&self) -> bool {
+        matches!(
+            self.peek_kind(),
+            Some(
+                Kind::Def
+                    | Kind::DefStruct
+                    | Kind::DefImpl
+                    | Kind::DefMacro
+                    | Kind::Type
+                    | Kind::Use
+                    | Kind::Static
+            )
+        )
+    }
+
+    fn synchronize_item(&mut self, start: usize) {
+        if self.index == start && !self.at_eof() {
+            self.advance();
+        }
+        while !self.at_eof()
+            && !matches!(
+                self.peek_kind(),
+                Some(
+                    Kind::Def
+                        | Kind::DefStruct
+                        | Kind::DefImpl
+                        | Kind::DefMacro
+                        | Kind::Type
+                        | Kind::Use
+                )
+            )
+        {
+            self.advance();
+        }
+    }
+
+    // skips the rest of a broken statement, stepping over nested `do ... end`
+    // blocks, and stops before `end`, a closer or the start of an item
+    fn synchronize_stmt(&mut self, closers: &[Kind]) {
+        let start = self.index;
+        let mut depth = 0usize;
+        while let Some(kind) = self.peek_kind() {
+            if self.at_item_start() {
+                break;
+            }
+            if depth == 0 {
+                if *kind == Kind::End || closers.contains(kind) {
+                    break;
+                }
+                if self.index > start && self.on_new_line() {
+                    break;
+                }
+                if *kind == Kind::Semicolon {
+                    self.advance();
+                    break;
+                }
+            }
+            match kind {
+                Kind::Do => depth += 1,
+                Kind::End => depth -= 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    // ----- items -----
+
+    fn parse_item(&mut self) -> PResult<Item> {
+        match self.peek_kind() {
+            Some(Kind::Def) => self.parse_function(),
+            Some(Kind::DefStruct) => self.parse_struct(),
+            Some(Kind::DefImpl) => self.parse_impl(),
+            Some(Kind::DefMacro) => self.parse_macro(),
+            Some(Kind::Type) => self.parse_type_alias(),
+            Some(Kind::Use) => self.parse_use(),
+            _ => Err(self.unexpected("`def`, `defstruct`, `defimpl`, `defmacro`, `type` or `use`")),
+        }
+    }
+
+    fn parse_function(&mut self) -> PResult<Item> {
+        let (decl, span) = self.parse_function_decl(false)?;
+        Ok(Item {
+            kind: ItemKind::Function(decl),
+            span,
+        })
+    }
+
+    fn parse_function_decl(&mut self, allow_receiver: bool) -> PResult<(FunctionDecl, Span)> {
+        let start = self.expect(Kind::Def, "`def`")?.span;
+        let name = self.expect_ident("a function name")?;
+        let generics = self.parse_generic_params()?;
+        self.expect(Kind::LeftParen, "`(`")?;
+        let receiver = self.parse_receiver()?;
+        if let Some(receiver) = &receiver {
+            if !allow_receiver {
+                return Err(Diagnostic::error(
+                    "a `self` receiver is only allowed on methods inside `defimpl`",
+                    receiver.span,
+                ));
+            }
+            if self.eat(&Kind::Comma).is_none() && !self.check(&Kind::RightParen) {
+                return Err(self.unexpected("`,` or `)`"));
+            }
+        }
+        let mut params = Vec::new();
+        while !self.check(&Kind::RightParen) {
+            params.push(self.parse_param()?);
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(Kind::RightParen, "`)`")?;
+        let return_type = match self.eat(&Kind::Arrow) {
+            Some(_) => Some(self.parse_type()?),
+            None => None,
+        };
+        let body = self.parse_do_block()?;
+        let span = start.to(body.span);
+        Ok((
+            FunctionDecl {
+                name,
+                generics,
+                receiver,
+                params,
+                return_type,
+                body,
+            },
+            span,
+        ))
+    }
+
+    fn parse_receiver(&mut self) -> PResult<Option<Receiver>> {
+        let is_receiver = matches!(self.peek(), Some(tok) if tok.kind == Kind::Identifier && tok.lexeme == "self")
+            && self.peek_nth_kind(1) != Some(&Kind::Colon);
+        if !is_receiver {
+            return Ok(None);
+        }
+        let span = self.advance().span;
+        Ok(Some(Receiver { span }))
+    }
+
+    // `<T, U: Show + Eq>` after the name of a def, defstruct or type alias
+    fn parse_generic_params(&mut self) -> PResult<Vec<GenericParam>> {
+        let mut generics = Vec::new();
+        if self.eat(&Kind::LessThan).is_none() {
+            return Ok(generics);
+        }
+        loop {
+            let name = self.expect_ident("a type parameter")?;
+            let mut bounds = Vec::new();
+            if self.eat(&Kind::Colon).is_some() {
+                bounds.push(self.parse_type()?);
+                while self.eat(&Kind::Plus).is_some() {
+                    bounds.push(self.parse_type()?);
+                }
+            }
+            let span = name.span.to(self.prev_span());
+            generics.push(GenericParam { name, bounds, span });
+            if self.eat(&Kind::Comma).is_none() || self.check(&Kind::GreaterThan) {
+                break;
+            }
+        }
+        self.expect(Kind::GreaterThan, "`>`")?;
+        Ok(generics)
+    }
+
+    fn parse_param(&mut self) -> PResult<Param> {
+        let mutable = self.eat(&Kind::Mutable);
+        let name = self.expect_ident("a parameter name")?;
+        self.expect(Kind::Colon, "`:`")?;
+        let ty = self.parse_type()?;
+        let start = mutable.as_ref().map_or(name.span, |tok| tok.span);
+        Ok(Param {
+            mutable: mutable.is_some(),
+            span: start.to(ty.span),
+            name,
+            ty,
+        })
+    }
+
+    fn parse_struct(&mut self) -> PResult<Item> {
+        let start = self.expect(Kind::DefStruct, "`defstruct`")?.span;
+        let name = self.expect_ident("a struct name")?;
+        let generics = self.parse_generic_params()?;
+        self.expect(Kind::Do, "`do`")?;
+        let mut fields = Vec::new();
+        while !self.check(&Kind::End) && !self.at_eof() && !self.at_item_start() {
+            let field = self
+                .parse_struct_field()
+                .and_then(|field| self.expect_terminator().map(|_| field));
+            match field {
+                Ok(field) => fields.push(field),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_stmt(&[Kind::End]);
+                }
+            }
+        }
+        let end = self.expect_end();
+        Ok(Item {
+            kind: ItemKind::Struct(StructDecl {
+                name,
+                generics,
+                fields,
+            }),
+            span: start.to(end),
+        })
+    }
+
+    fn parse_struct_field(&mut self) -> PResult<StructField> {
+        let start = self.current_span();
+        let visibility = match self.eat(&Kind::Public) {
+            Some(_) => Visibility::Public,
+            None => Visibility::Private,
+        };
+        let mutable = self.eat(&Kind::Mutable).is_some();
+        let name = self.expect_ident("a field name")?;
+        self.expect(Kind::Colon, "`:`")?;
+        let ty = self.parse_type()?;
+        let default = match self.eat(&Kind::Equal) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+        let span = start.to(self.prev_span());
+        Ok(StructField {
+            visibility,
+            mutable,
+            name,
+            ty,
+            default,
+            span,
+        })
+    }
+
+    // `defmacro name(a, b) do ... end`, the parameters are untyped fragments
+    fn parse_macro(&mut self) -> PResult<Item> {
+        let start = self.expect(Kind::DefMacro, "`defmacro`")?.span;
+        let name = self.expect_ident("a macro name")?;
+        self.expect(Kind::LeftParen, "`(`")?;
+        let mut params = Vec::new();
+        while !self.check(&Kind::RightParen) {
+            params.push(self.expect_ident("a macro parameter")?);
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(Kind::RightParen, "`)`")?;
+        let body = self.parse_do_block()?;
+        let span = start.to(body.span);
+        Ok(Item {
+            kind: ItemKind::Macro(MacroDecl { name, params, body }),
+            span,
+        })
+    }
+
+    fn parse_impl(&mut self) -> PResult<Item> {
+        let start = self.expect(Kind::DefImpl, "`defimpl`")?.span;
+        let target = self.expect_ident("a type name")?;
+        self.expect(Kind::Do, "`do`")?;
+        let mut methods = Vec::new();
+        while !self.check(&Kind::End) && !self.at_eof() {
+            if self.eat(&Kind::Semicolon).is_some() {
+                continue;
+            }
+            if matches!(
+                self.peek_kind(),
+                Some(Kind::DefStruct | Kind::DefImpl | Kind::DefMacro | Kind::Type | Kind::Use)
+            ) {
+                break;
+            }
+            let start = self.index;
+            match self.parse_method() {
+                Ok(method) => methods.push(method),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_method(start);
+                }
+            }
+        }
+        let end = self.expect_end();
+        Ok(Item {
+            kind: ItemKind::Impl(ImplBlock { target, methods }),
+            span: start.to(end),
+        })
+    }
+
+    // skips to the next `def`, `static` or the `end` closing the `defimpl`
+    fn synchronize_method(&mut self, start: usize) {
+        if self.index == start && !self.at_eof() {
+            self.advance();
+        }
+        let mut depth = 0usize;
+        while let Some(kind) = self.peek_kind() {
+            if self.at_item_start() || (depth == 0 && *kind == Kind::End) {
+                break;
+            }
+            match kind {
+                Kind::Do => depth += 1,
+                Kind::End => depth -= 1,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    fn parse_method(&mut self) -> PResult<Method> {
+        let start = self.current_span();
+        let is_static = self.eat(&Kind::Static).is_some();
+        if !self.check(&Kind::Def) {
+            return Err(self.unexpected("`def` or `static def`"));
+        }
+        let (function, span) = self.parse_function_decl(true)?;
+        match (&function.receiver, is_static) {
+            (Some(receiver), true) => {
+                return Err(Diagnostic::error(
+                    "static functions cannot take a `self` receiver",
+                    receiver.span,
+                ))
+            }
+            (None, false) => {
+                return Err(Diagnostic::error(
+                    format!(
+                        "method `{}` needs a `self` receiver, use `static def` for associated functions",
+                        function.name.name
+                    ),
+                    function.name.span,
+                ))
+            }
+            _ => {}
+        }
+        Ok(Method {
+            is_static,
+            function,
+            span: start.to(span),
+        })
+    }
+
+    fn parse_type_alias(&mut self) -> PResult<Item> {
+        let start = self.expect(Kind::Type, "`type`")?.span;
+        let name = self.expect_ident("a type name")?;
+        let generics = self.parse_generic_params()?;
+        self.expect(Kind::Equal, "`=`")?;
+        let ty = self.parse_type()?;
+        let span = start.to(ty.span);
+        Ok(Item {
+            kind: ItemKind::TypeAlias(TypeAlias { name, generics, ty }),
+            span,
+        })
+    }
+
+    // `use a.b.c` or `use a.b.{x, y}`
+    fn parse_use(&mut self) -> PResult<Item> {
+        let start = self.expect(Kind::Use, "`use`")?.span;
+        let mut path = vec![self.expect_ident("a module name")?];
+        let mut names = None;
+        while self.eat(&Kind::Dot).is_some() {
+            if self.eat(&Kind::LeftCurly).is_some() {
+                let mut list = Vec::new();
+                while !self.check(&Kind::RightCurly) {
+                    list.push(self.expect_ident("an item name")?);
+                    if self.eat(&Kind::Comma).is_none() {
+                        break;
+                    }
+                }
+                self.expect(Kind::RightCurly, "`}`")?;
+                names = Some(list);
+                break;
+            }
+            path.push(self.expect_ident("a module name")?);
+        }
+        let span = start.to(self.prev_span());
+        self.expect_terminator()?;
+        Ok(Item {
+            kind: ItemKind::Use(UseDecl { path, names }),
+            span,
+        })
+    }
+
+    // ----- types -----
+
+    // a trailing `?` makes any type nullable, `(i32) -> i32?` returns an `i32?`
+    // while `((i32) -> i32)?` is a nullable function
+    fn parse_type(&mut self) -> PResult<TypeExpr> {
+        let ty = self.parse_type_atom()?;
+        match self.eat(&Kind::Question) {
+            Some(question) => Ok(TypeExpr {
+                span: ty.span.to(question.span),
+                kind: TypeKind::Nullable(Box::new(ty)),
+            }),
+            None => Ok(ty),
+        }
+    }
+
+    // `<` right after a type name always opens its type arguments. Types only
+    // appear after `:`, `->`, in type arguments or as bounds, never as operands,
+    // and in an expression `<` is always the comparison, so `f(a < b, c > d)`
+    // passes two booleans.
+    fn parse_type_atom(&mut self) -> PResult<TypeExpr> {
+        if let Some(prim) = self.peek_kind().and_then(primitive_type) {
+            let span = self.advance().span;
+            return Ok(TypeExpr {
+                kind: TypeKind::Primitive(prim),
+                span,
+            });
+        }
+        match self.peek_kind() {
+            Some(Kind::Identifier) => {
+                let name = self.expect_ident("a type")?;
+                if self.eat(&Kind::LessThan).is_none() {
+                    let span = name.span;
+                    return Ok(TypeExpr {
+                        kind: TypeKind::Named(name),
+                        span,
+                    });
+                }
+                let mut args = Vec::new();
+                loop {
+                    args.push(self.parse_type()?);
+                    if self.eat(&Kind::Comma).is_none() || self.check(&Kind::GreaterThan) {
+                        break;
+                    }
+                }
+                let end = self.expect(Kind::GreaterThan, "`>`")?.span;
+                let span = name.span.to(end);
+                Ok(TypeExpr {
+                    kind: TypeKind::Generic { name, args },
+                    span,
+                })
+            }
+            Some(Kind::LeftParen) => self.parse_function_type(),
+            Some(Kind::LeftBracket) => {
+                let start = self.advance().span;
+                let element = self.parse_type()?;
+                let end = self.expect(Kind::RightBracket, "`]`")?.span;
+                Ok(TypeExpr {
+                    kind: TypeKind::List(Box::new(element)),
+                    span: start.to(end),
+                })
+            }
+            Some(Kind::LeftCurly) => {
+                let start = self.advance().span;
+                let key = self.parse_type()?;
+                self.expect(Kind::FatArrow, "`=>`")?;
+                let value = self.parse_type()?;
+                let end = self.expect(Kind::RightCurly, "`}`")?.span;
+                Ok(TypeExpr {
+                    kind: TypeKind::Map {
+                        key: Box::new(key),
+                        value: Box::new(value),
+                    },
+                    span: start.to(end),
+                })
+            }
+            None
+            | Some(
+                Kind::Equal
+                | Kind::Comma
+                | Kind::RightParen
+                | Kind::RightCurly
+                | Kind::Do
+                | Kind::End
+                | Kind::Semicolon
+                | Kind::Arrow
+                | Kind::FatArrow
+                | Kind::RightBracket
+                | Kind::GreaterThan,
+            ) => {
+                let diagnostic = self.unexpected("a type");
+                self.diagnostics.push(diagnostic);
+                let start = self.current_span().start;
+                Ok(TypeExpr {
+                    kind: TypeKind::Missing,
+                    span: Span::new(start, start),
+                })
+            }
+            _ => Err(self.unexpected("a type")),
+        }
+    }
+
+    // `(x: usize, string) -> T`, without the arrow `(A, B)` is a tuple type and
+    // a lone `(T)` just a parenthesized type
+    fn parse_function_type(&mut self) -> PResult<TypeExpr> {
+        let start = self.expect(Kind::LeftParen, "`(`")?.span;
+        let mut params = Vec::new();
+        let mut trailing_comma = false;
+        while !self.check(&Kind::RightParen) {
+            let name =
+                if self.check(&Kind::Identifier) && self.peek_nth_kind(1) == Some(&Kind::Colon) {
+                    let name = self.expect_ident("a parameter name")?;
+                    self.advance();
+                    Some(name)
+                } else {
+                    None
+                };
+            let ty = self.parse_type()?;
+            params.push(FunctionTypeParam { name, ty });
+            trailing_comma = self.eat(&Kind::Comma).is_some();
+            if !trailing_comma {
+                break;
+            }
+        }
+        self.expect(Kind::RightParen, "`)`")?;
+        if self.eat(&Kind::Arrow).is_some() {
+            let ret = self.parse_type()?;
+            let span = start.to(ret.span);
+            return Ok(TypeExpr {
+                kind: TypeKind::Function {
+                    params,
+                    ret: Box::new(ret),
+                },
+                span,
+            });
+        }
+        if params.iter().any(|param| param.name.is_some()) {
+            return Err(self.unexpected("`->`"));
+        }
+        let span = start.to(self.prev_span());
+        if params.len() == 1 && !trailing_comma {
+            let mut inner = params.remove(0).ty;
+            inner.span = span;
+            return Ok(inner);
+        }
+        Ok(TypeExpr {
+            kind: TypeKind::Tuple(params.into_iter().map(|param| param.ty).collect()),
+            span,
+        })
+    }
+
+    // ----- statements -----
+
+    fn parse_do_block(&mut self) -> PResult<Block> {
+        let start = self.expect(Kind::Do, "`do`")?.span;
+        let stmts = self.parse_stmts_until(&[Kind::End]);
+        let end = self.expect_end();
+        Ok(Block {
+            stmts,
+            span: start.to(end),
+        })
+    }
+
+    fn parse_stmts_until(&mut self, closers: &[Kind]) -> Vec<Stmt> {
+        let mut stmts = Vec::new();
+        while let Some(kind) = self.peek_kind() {
+            if closers.contains(kind) || self.at_item_start() {
+                break;
+            }
+            if self.eat(&Kind::Semicolon).is_some() {
+                continue;
+            }
+            let start = self.current_span();
+            let reported = self.diagnostics.len();
+            let index = self.index;
+            match self.parse_stmt() {
+                // a stray closer starts no expression and is already reported,
+                // skip it and the rest of its line
+                Ok(_) if self.index == index => {
+                    self.advance();
+                    self.synchronize_stmt(closers);
+                }
+                Ok(stmt) => {
+                    stmts.push(stmt);
+                    if self.peek_kind().is_some_and(|kind| closers.contains(kind)) {
+                        continue;
+                    }
+                    if let Err(diagnostic) = self.expect_terminator() {
+                        // a missing node already explains what went wrong here
+                        if self.diagnostics.len() == reported {
+                            self.diagnostics.push(diagnostic);
+                        }
+                        self.synchronize_stmt(closers);
+                    }
+                }
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_stmt(closers);
+                    let span = Span::new(start.start, self.prev_span().end.max(start.start));
+                    let missing = self.mk_expr(ExprKind::Missing, span);
+                    stmts.push(Stmt {
+                        kind: StmtKind::Expr(missing),
+                        span,
+                    });
+                }
+            }
+        }
+        stmts
+    }
+
+    fn parse_stmt(&mut self) -> PResult<Stmt> {
+        if self.check(&Kind::Mutable) {
+            return self.parse_let();
+        }
+        if self.check(&Kind::Identifier)
+            && self.peek_nth_kind(1) == Some(&Kind::Colon)
+            && !self.at_labeled_loop()
+        {
+            return self.parse_let();
+        }
+        let expr = self.parse_expr()?;
+        Ok(Stmt {
+            span: expr.span,
+            kind: StmtKind::Expr(expr),
+        })
+    }
+
+    // `x: T = v`, `mut x: T = v` or `mut x = v`
+    fn parse_let(&mut self) -> PResult<Stmt> {
+        let mutable = self.eat(&Kind::Mutable);
+        let name = self.expect_ident("a binding name")?;
+        let ty = match mutable {
+            Some(_) if !self.check(&Kind::Colon) => None,
+            _ => {
+                self.expect(Kind::Colon, "`:`")?;
+                Some(self.parse_type()?)
+            }
+        };
+        self.expect(Kind::Equal, "`=`")?;
+        let value = self.parse_expr()?;
+        let span = mutable
+            .as_ref()
+            .map_or(name.span, |tok| tok.span)
+            .to(value.span);
+        Ok(Stmt {
+            kind: StmtKind::Let(LetStmt {
+                mutable: mutable.is_some(),
+                name,
+                ty,
+                value,
+            }),
+            span,
+        })
+    }
+
+    // ----- expressions -----
+
+    pub fn parse_expr(&mut self) -> PResult<Expr> {
+        let target = self.parse_binary(0)?;
+        let op = match self.peek_kind() {
+            Some(Kind::Equal) => None,
+            Some(Kind::PlusEqual) => Some(BinaryOp::Add),
+            Some(Kind::MinusEqual) => Some(BinaryOp::Sub),
+            Some(Kind::AsteriskEqual) => Some(BinaryOp::Mul),
+            Some(Kind::SlashEqual) => Some(BinaryOp::Div),
+            _ => return Ok(target),
+        };
+        self.advance();
+        if !is_place(&target) {
+            return Err(Diagnostic::error(
+                "invalid left-hand side of assignment",
+                target.span,
+            ));
+        }
+        let value = self.parse_expr()?;
+        let span = target.span.to(value.span);
+        Ok(self.mk_expr(
+            ExprKind::Assign {
+                target: Box::new(target),
+                op,
+                value: Box::new(value),
+            },
+            span,
+        ))
+    }
+
+    fn peek_binary_op(&self) -> Option<(BinaryOp, u8, u8)> {
+        let op = match self.peek_kind()? {
+            Kind::LogicalOr => BinaryOp::Or,
+            Kind::LogicalAnd => BinaryOp::And,
+            Kind::IsEqual => BinaryOp::Eq,
+            Kind::NotEqual => BinaryOp::NotEq,
+            Kind::LessThan => BinaryOp::Lt,
+            Kind::GreaterThan => BinaryOp::Gt,
+            Kind::LessThanOrEqual => BinaryOp::LtEq,
+            Kind::GreaterThanOrEqual => BinaryOp::GtEq,
+            Kind::Plus => BinaryOp::Add,
+            Kind::Minus => BinaryOp::Sub,
+            Kind::Asterisk => BinaryOp::Mul,
+            Kind::Slash => BinaryOp::Div,
+            Kind::Elvis => BinaryOp::Elvis,
+            Kind::PipeForward => BinaryOp::Pipe,
+            _ => return None,
+        };
+        // a `-` opening a line is a negation starting the next statement
+        if op == BinaryOp::Sub && self.on_new_line() {
+            return None;
+        }
+        let (left, right) = match op {
+            BinaryOp::Or => (1, 2),
+            BinaryOp::And => (3, 4),
+            BinaryOp::Eq | BinaryOp::NotEq => (5, 6),
+            BinaryOp::Lt | BinaryOp::Gt | BinaryOp::LtEq | BinaryOp::GtEq => (7, 8),
+            // `a + b |> f() == c` is `f(a + b) == c`
+            BinaryOp::Pipe => (9, 10),
+            // right associative, `a ?: b ?: c` tries `a`, then `b`, then `c`
+            BinaryOp::Elvis => (12, 11),
+            BinaryOp::Add | BinaryOp::Sub => (13, 14),
+            BinaryOp::Mul | BinaryOp::Div => (15, 16),
+        };
+        Some((op, left, right))
+    }
+
+    fn parse_binary(&mut self, min_bp: u8) -> PResult<Expr> {
+        let mut lhs = self.parse_cast()?;
+        while let Some((op, left, right)) = self.peek_binary_op() {
+            if left < min_bp {
+                break;
+            }
+            self.advance();
+            let rhs = self.parse_binary(right)?;
+            if op == BinaryOp::Pipe && !is_pipe_stage(&rhs) {
+                return Err(Diagnostic::error(
+                    "the right side of `|>` must be a call or a function name",
+                    rhs.span,
+                ));
+            }
+            let span = lhs.span.to(rhs.span);
+            lhs = self.mk_expr(
+                ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+                span,
+            );
+        }
+        Ok(lhs)
+    }
+
+    // `as` binds tighter than any binary operator, `-x as u8` converts `-x`
+    fn parse_cast(&mut self) -> PResult<Expr> {
+        let mut expr = self.parse_unary()?;
+        while self.check(&Kind::As) && !self.on_new_line() {
+            self.advance();
+            let ty = self.parse_type()?;
+            let span = expr.span.to(ty.span);
+            expr = self.mk_expr(
+                ExprKind::Cast {
+                    expr: Box::new(expr),
+                    ty,
+                },
+                span,
+            );
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> PResult<Expr> {
+        let op = match self.peek_kind() {
+            Some(Kind::Minus) => UnaryOp::Neg,
+            Some(Kind::Exclaim) => UnaryOp::Not,
+            _ => return self.parse_postfix(),
+        };
+        let start = self.advance().span;
+        let operand = self.parse_unary()?;
+        let span = start.to(operand.span);
+        Ok(self.mk_expr(
+            ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span,
+        ))
+    }
+
+    fn parse_postfix(&mut self) -> PResult<Expr> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.check(&Kind::LeftParen) && !self.on_new_line() {
+                let args = self.parse_call_args()?;
+                let span = expr.span.to(self.prev_span());
+                expr = self.mk_expr(
+                    ExprKind::Call {
+                        callee: Box::new(expr),
+                        args,
+                    },
+                    span,
+                );
+            } else if self.check(&Kind::LeftBracket) && !self.on_new_line() {
+                self.advance();
+                let index = self.parse_expr()?;
+                let end = self.expect(Kind::RightBracket, "`]`")?.span;
+                let span = expr.span.to(end);
+                expr = self.mk_expr(
+                    ExprKind::Index {
+                        base: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                    span,
+                );
+            } else if let Some(dot) = self.eat(&Kind::Dot).or_else(|| self.eat(&Kind::SafeDot)) {
+                let safe = dot.kind == Kind::SafeDot;
+                let name = self.expect_ident("a field or method name")?;
+                if self.check(&Kind::LeftParen) && !self.on_new_line() {
+                    let args = self.parse_call_args()?;
+                    let span = expr.span.to(self.prev_span());
+                    expr = self.mk_expr(
+                        ExprKind::MethodCall {
+                            receiver: Box::new(expr),
+                            method: name,
+                            args,
+                            safe,
+                        },
+                        span,
+                    );
+                } else {
+                    let span = expr.span.to(name.span);
+                    expr = self.mk_expr(
+                        ExprKind::Field {
+                            base: Box::new(expr),
+                            name,
+                            safe,
+                        },
+                        span,
+                    );
+                }
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    fn parse_call_args(&mut self) -> PResult<Vec<Expr>> {
+        self.expect(Kind::LeftParen, "`(`")?;
+        let args = self.parse_expr_list(Kind::RightParen)?;
+        self.expect(Kind::RightParen, "`)`")?;
+        Ok(args)
+    }
+
+    // comma separated expressions up to, but not including, `closer`
+    fn parse_expr_list(&mut self, closer: Kind) -> PResult<Vec<Expr>> {
+        let mut exprs = Vec::new();
+        while !self.check(&closer) {
+            exprs.push(self.parse_expr()?);
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(exprs)
+    }
+
+    // `(a)` only groups, a comma makes a tuple: `(a, b)`, `(a,)` and `()`
+    fn parse_paren_or_tuple(&mut self) -> PResult<Expr> {
+        let start = self.expect(Kind::LeftParen, "`(`")?.span;
+        if let Some(end) = self.eat(&Kind::RightParen) {
+            return Ok(self.mk_expr(ExprKind::Tuple(Vec::new()), start.to(end.span)));
+        }
+        let mut inner = self.parse_expr()?;
+        if self.eat(&Kind::Comma).is_none() {
+            let end = self.expect(Kind::RightParen, "`)`")?.span;
+            inner.span = start.to(end);
+            return Ok(inner);
+        }
+        let mut elements = vec![inner];
+        elements.extend(self.parse_expr_list(Kind::RightParen)?);
+        let end = self.expect(Kind::RightParen, "`)`")?.span;
+        Ok(self.mk_expr(ExprKind::Tuple(elements), start.to(end)))
+    }
+
+    fn parse_map_literal(&mut self) -> PResult<Expr> {
+        let start = self.expect(Kind::LeftCurly, "`{`")?.span;
+        let mut entries = Vec::new();
+        while !self.check(&Kind::RightCurly) {
+            let key = self.parse_expr()?;
+            self.expect(Kind::FatArrow, "`=>`")?;
+            let value = self.parse_expr()?;
+            let span = key.span.to(value.span);
+            entries.push(MapEntry { key, value, span });
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        let end = self.expect(Kind::RightCurly, "`}`")?.span;
+        Ok(self.mk_expr(ExprKind::Map(entries), start.to(end)))
+    }
+
+    fn parse_primary(&mut self) -> PResult<Expr> {
+        let kind = match self.peek_kind() {
+            Some(kind) => kind.clone(),
+            None => return Ok(self.missing_expr()),
+        };
+        let literal = self.peek().and_then(literal_of);
+        if let Some(literal) = literal {
+            let span = self.advance().span;
+            return Ok(self.mk_expr(ExprKind::Literal(literal), span));
+        }
+
+        match kind {
+            Kind::Identifier if self.at_labeled_loop() => self.parse_loop(),
+            Kind::While | Kind::For => self.parse_loop(),
+            Kind::Break | Kind::Continue => self.parse_break_or_continue(),
+            Kind::Identifier => {
+                let name = self.expect_ident("an identifier")?;
+                if self.check(&Kind::LeftCurly) && !self.on_new_line() {
+                    return self.parse_struct_literal(name);
+                }
+                let span = name.span;
+                Ok(self.mk_expr(ExprKind::Ident(name), span))
+            }
+            Kind::LeftParen => self.parse_paren_or_tuple(),
+            Kind::LeftBracket => {
+                let start = self.advance().span;
+                let elements = self.parse_expr_list(Kind::RightBracket)?;
+                let end = self.expect(Kind::RightBracket, "`]`")?.span;
+                Ok(self.mk_expr(ExprKind::List(elements), start.to(end)))
+            }
+            Kind::LeftCurly => self.parse_map_literal(),
+            Kind::Fn => self.parse_lambda(),
+            Kind::If => self.parse_if(),
+            Kind::Match => self.parse_match(),
+            Kind::Do => {
+                let block = self.parse_do_block()?;
+                let span = block.span;
+                Ok(self.mk_expr(ExprKind::Block(block), span))
+            }
+            Kind::Return => {
+                let start = self.advance().span;
+                let value = if !self.on_new_line() && self.peek_kind().is_some_and(starts_expr) {
+                    Some(Box::new(self.parse_expr()?))
+                } else {
+                    None
+                };
+                let span = match &value {
+                    Some(value) => start.to(value.span),
+                    None => start,
+                };
+                Ok(self.mk_expr(ExprKind::Return(value), span))
+            }
+            Kind::End
+            | Kind::Else
+            | Kind::Arrow
+            | Kind::FatArrow
+            | Kind::Comma
+            | Kind::Semicolon
+            | Kind::RightParen
+            | Kind::RightCurly
+            | Kind::RightBracket => Ok(self.missing_expr()),
+            _ => Err(self.unexpected("an expression")),
+        }
+    }
+
+    // an expression that should be here but isn't, the following token belongs
+    // to the surrounding construct so nothing is consumed
+    fn missing_expr(&mut self) -> Expr {
+        let diagnostic = self.unexpected("an expression");
+        self.diagnostics.push(diagnostic);
+        let start = self.current_span().start;
+        self.mk_expr(ExprKind::Missing, Span::new(start, start))
+    }
+
+    // an `else if` on the same line continues the chain, so the whole chain
+    // shares a single `end`
+    fn parse_if(&mut self) -> PResult<Expr> {
+        let start = self.expect(Kind::If, "`if`")?.span;
+        let mut branches = Vec::new();
+        let mut else_block = None;
+        loop {
+            let condition = self.parse_expr()?;
+            let do_span = self.expect(Kind::Do, "`do`")?.span;
+            let stmts = self.parse_stmts_until(&[Kind::Else, Kind::End]);
+            let block = Block {
+                stmts,
+                span: do_span.to(self.prev_span()),
+            };
+            branches.push(IfBranch { condition, block });
+            let else_span = match self.eat(&Kind::Else) {
+                Some(tok) => tok.span,
+                None => break,
+            };
+            if self.check(&Kind::If) && !self.on_new_line() {
+                self.advance();
+                continue;
+            }
+            let stmts = self.parse_stmts_until(&[Kind::End]);
+            else_block = Some(Block {
+                stmts,
+                span: else_span.to(self.prev_span()),
+            });
+            break;
+        }
+        let end = self.expect_end();
+        Ok(self.mk_expr(
+            ExprKind::If(IfExpr {
+                branches,
+                else_block,
+            }),
+            start.to(end),
+        ))
+    }
+
+    fn parse_match(&mut self) -> PResult<Expr> {
+        let start = self.expect(Kind::Match, "`match`")?.span;
+        let scrutinee = self.parse_expr()?;
+        self.expect(Kind::Do, "`do`")?;
+        let mut arms = Vec::new();
+        while !self.check(&Kind::End) && !self.at_eof() && !self.at_item_start() {
+            if self.eat(&Kind::Semicolon).is_some() {
+                continue;
+            }
+            let arm = self.parse_match_arm().and_then(|arm| {
+                if !self.check(&Kind::End) {
+                    self.expect_terminator()?;
+                }
+                Ok(arm)
+            });
+            match arm {
+                Ok(arm) => arms.push(arm),
+                Err(diagnostic) => {
+                    self.diagnostics.push(diagnostic);
+                    self.synchronize_stmt(&[Kind::End]);
+                }
+            }
+        }
+        let end = self.expect_end();
+        Ok(self.mk_expr(
+            ExprKind::Match(MatchExpr {
+                scrutinee: Box::new(scrutinee),
+                arms,
+            }),
+            start.to(end),
+        ))
+    }
+
+    // `pattern [if guard] -> expr`
+    fn parse_match_arm(&mut self) -> PResult<MatchArm> {
+        let pattern = self.parse_pattern()?;
+        let guard = match self.eat(&Kind::If) {
+            Some(_) => Some(self.parse_expr()?),
+            None => None,
+        };
+        self.expect(Kind::Arrow, "`->`")?;
+        let body = self.parse_expr()?;
+        let span = pattern.span.to(body.span);
+        Ok(MatchArm {
+            pattern,
+            guard,
+            body,
+            span,
+        })
+    }
+
+    fn parse_pattern(&mut self) -> PResult<Pattern> {
+        let first = self.parse_single_pattern()?;
+        if !self.check(&Kind::Pipe) {
+            return Ok(first);
+        }
+        let mut alternatives = vec![first];
+        while self.eat(&Kind::Pipe).is_some() {
+            alternatives.push(self.parse_single_pattern()?);
+        }
+        let span = alternatives[0].span.to(self.prev_span());
+        Ok(Pattern {
+            kind: PatternKind::Or(alternatives),
+            span,
+        })
+    }
+
+    fn parse_single_pattern(&mut self) -> PResult<Pattern> {
+        let start = self.current_span();
+        if let Some(literal) = self.peek().and_then(literal_of) {
+            self.advance();
+            return Ok(Pattern {
+                kind: PatternKind::Literal(literal),
+                span: start,
+            });
+        }
+        let kind = match self.peek_kind() {
+            Some(Kind::Identifier) => {
+                let name = self.expect_ident("a pattern")?;
+                if name.name == "_" {
+                    PatternKind::Wildcard
+                } else if self.check(&Kind::LeftCurly) && !self.on_new_line() {
+                    self.parse_struct_pattern(name)?
+                } else {
+                    PatternKind::Binding(name)
+                }
+            }
+            Some(Kind::Minus) => {
+                self.advance();
+                match self.peek().and_then(literal_of) {
+                    Some(Literal::Int(text)) => {
+                        self.advance();
+                        PatternKind::Literal(Literal::Int(format!("-{}", text)))
+                    }
+                    Some(Literal::Float(text)) => {
+                        self.advance();
+                        PatternKind::Literal(Literal::Float(format!("-{}", text)))
+                    }
+                    _ => return Err(self.unexpected("a number")),
+                }
+            }
+            Some(Kind::LeftParen) => {
+                self.advance();
+                let mut elements = Vec::new();
+                let mut trailing_comma = false;
+                while !self.check(&Kind::RightParen) {
+                    elements.push(self.parse_pattern()?);
+                    trailing_comma = self.eat(&Kind::Comma).is_some();
+                    if !trailing_comma {
+                        break;
+                    }
+                }
+                self.expect(Kind::RightParen, "`)`")?;
+                if elements.len() == 1 && !trailing_comma {
+                    let mut inner = elements.remove(0);
+                    inner.span = start.to(self.prev_span());
+                    return Ok(inner);
+                }
+                PatternKind::Tuple(elements)
+            }
+            _ => return Err(self.unexpected("a pattern")),
+        };
+        Ok(Pattern {
+            kind,
+            span: start.to(self.prev_span()),
+        })
+    }
+
+    // `Point{x, y: 0}`, a bare field name binds the field to a local of that name
+    fn parse_struct_pattern(&mut self, name: Ident) -> PResult<PatternKind> {
+        self.expect(Kind::LeftCurly, "`{`")?;
+        let mut fields = Vec::new();
+        while !self.check(&Kind::RightCurly) {
+            let field = self.expect_ident("a field name")?;
+            let pattern = match self.eat(&Kind::Colon) {
+                Some(_) => self.parse_pattern()?,
+                None => Pattern {
+                    kind: PatternKind::Binding(field.clone()),
+                    span: field.span,
+                },
+            };
+            let span = field.span.to(pattern.span);
+            fields.push(FieldPattern {
+                name: field,
+                pattern,
+                span,
+            });
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        self.expect(Kind::RightCurly, "`}`")?;
+        Ok(PatternKind::Struct { name, fields })
+    }
+
+    fn at_labeled_loop(&self) -> bool {
+        self.check(&Kind::Identifier)
+            && self.peek_nth_kind(1) == Some(&Kind::Colon)
+            && matches!(self.peek_nth_kind(2), Some(Kind::While | Kind::For))
+    }
+
+    // `[label:] while cond do ... end` and `[label:] for pattern in expr do ... end`
+    fn parse_loop(&mut self) -> PResult<Expr> {
+        let start = self.current_span();
+        let label = if self.at_labeled_loop() {
+            let label = self.expect_ident("a label")?;
+            self.advance();
+            Some(label)
+        } else {
+            None
+        };
+        let kind = match self.peek_kind() {
+            Some(Kind::While) => {
+                self.advance();
+                let condition = self.parse_expr()?;
+                let body = self.parse_loop_body(&label)?;
+                ExprKind::While(WhileExpr {
+                    label,
+                    condition: Box::new(condition),
+                    body,
+                })
+            }
+            Some(Kind::For) => {
+                self.advance();
+                let pattern = self.parse_pattern()?;
+                self.expect(Kind::In, "`in`")?;
+                let iterable = self.parse_expr()?;
+                let body = self.parse_loop_body(&label)?;
+                ExprKind::For(ForExpr {
+                    label,
+                    pattern,
+                    iterable: Box::new(iterable),
+                    body,
+                })
+            }
+            _ => return Err(self.unexpected("`while` or `for`")),
+        };
+        let span = start.to(self.prev_span());
+        Ok(self.mk_expr(kind, span))
+    }
+
+    fn parse_loop_body(&mut self, label: &Option<Ident>) -> PResult<Block> {
+        if let Some(label) = label {
+            self.loop_labels.push(label.name.clone());
+        }
+        let body = self.parse_do_block();
+        if label.is_some() {
+            self.loop_labels.pop();
+        }
+        body
+    }
+
+    // `break [label] [value]` and `continue [label]`
+    fn parse_break_or_continue(&mut self) -> PResult<Expr> {
+        let keyword = self.advance();
+        let names_label = match self.peek() {
+            Some(tok) => {
+                tok.kind == Kind::Identifier
+                    && !self.on_new_line()
+                    && self.loop_labels.contains(&tok.lexeme)
+            }
+            None => false,
+        };
+        let label = match names_label {
+            true => Some(self.expect_ident("a label")?),
+            false => None,
+        };
+        if keyword.kind == Kind::Continue {
+            let span = keyword.span.to(self.prev_span());
+            return Ok(self.mk_expr(ExprKind::Continue { label }, span));
+        }
+        let value = if !self.on_new_line() && self.peek_kind().is_some_and(starts_expr) {
+            Some(Box::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        let span = keyword.span.to(self.prev_span());
+        Ok(self.mk_expr(ExprKind::Break { label, value }, span))
+    }
+
+    // `fn x, y: i32 -> i32 do ... end`, parameter and return types are optional
+    fn parse_lambda(&mut self) -> PResult<Expr> {
+        let start = self.expect(Kind::Fn, "`fn`")?.span;
+        let mut params = Vec::new();
+        while self.check(&Kind::Identifier) {
+            let name = self.expect_ident("a parameter name")?;
+            let ty = match self.eat(&Kind::Colon) {
+                Some(_) => Some(self.parse_type()?),
+                None => None,
+            };
+            let span = name.span.to(self.prev_span());
+            params.push(LambdaParam { name, ty, span });
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        let return_type = match self.eat(&Kind::Arrow) {
+            Some(_) => Some(self.parse_type()?),
+            None => None,
+        };
+        let body = self.parse_do_block()?;
+        let span = start.to(body.span);
+        Ok(self.mk_expr(
+            ExprKind::Lambda(Lambda {
+                params,
+                return_type,
+                body,
+            }),
+            span,
+        ))
+    }
+
+    // `Point{x: 1, y: 2}`
+    fn parse_struct_literal(&mut self, name: Ident) -> PResult<Expr> {
+        self.expect(Kind::LeftCurly, "`{`")?;
+        let mut fields = Vec::new();
+        while !self.check(&Kind::RightCurly) {
+            let field = self.expect_ident("a field name")?;
+            self.expect(Kind::Colon, "`:`")?;
+            let value = self.parse_expr()?;
+            let span = field.span.to(value.span);
+            fields.push(FieldInit {
+                name: field,
+                value,
+                span,
+            });
+            if self.eat(&Kind::Comma).is_none() {
+                break;
+            }
+        }
+        let end = self.expect(Kind::RightCurly, "`}`")?.span;
+        let span = name.span.to(end);
+        Ok(self.mk_expr(ExprKind::StructLit { name, fields }, span))
+    }
+}
+
+fn primitive_type(kind: &Kind) -> Option<PrimitiveType> {
+    let prim = match kind {
+        Kind::I8 => PrimitiveType::I8,
+        Kind::U8 => PrimitiveType::U8,
+        Kind::I16 => PrimitiveType::I16,
+        Kind::U16 => PrimitiveType::U16,
+        Kind::I32 => PrimitiveType::I32,
+        Kind::U32 => PrimitiveType::U32,
+        Kind::I64 => PrimitiveType::I64,
+        Kind::U64 => PrimitiveType::U64,
+        Kind::I128 => PrimitiveType::I128,
+        Kind::U128 => PrimitiveType::U128,
+        Kind::ISize => PrimitiveType::ISize,
+        Kind::USize => PrimitiveType::USize,
+        Kind::Float => PrimitiveType::Float,
+        Kind::Double => PrimitiveType::Double,
+        Kind::Bool => PrimitiveType::Bool,
+        Kind::Char => PrimitiveType::Char,
+        Kind::String => PrimitiveType::String,
+        _ => return None,
+    };
+    Some(prim)
+}
+
+fn literal_of(tok: &Token) -> Option<Literal> {
+    let literal = match tok.kind {
+        Kind::IntLiteral => Literal::Int(tok.lexeme.clone()),
+        Kind::DoubleLiteral => Literal::Float(tok.lexeme.clone()),
+        Kind::StringLiteral => Literal::Str(strip_delims(&tok.lexeme, 1)),
+        Kind::CharLiteral => Literal::Char(strip_delims(&tok.lexeme, 1)),
+        Kind::RegexLiteral => Literal::Regex(strip_delims(&tok.lexeme, 2)),
+        Kind::True => Literal::Bool(true),
+        Kind::False => Literal::Bool(false),
+        Kind::Null => Literal::Null,
+        _ => return None,
+    };
+    Some(literal)
+}
+
+fn starts_expr(kind: &Kind) -> bool {
+    matches!(
+        kind,
+        Kind::IntLiteral
+            | Kind::DoubleLiteral
+            | Kind::StringLiteral
+            | Kind::CharLiteral
+            | Kind::RegexLiteral
+            | Kind::True
+            | Kind::False
+            | Kind::Null
+            | Kind::Identifier
+            | Kind::LeftParen
+            | Kind::LeftBracket
+            | Kind::LeftCurly
+            | Kind::Minus
+            | Kind::Exclaim
+            | Kind::Fn
+            | Kind::If
+            | Kind::Match
+            | Kind::While
+            | Kind::For
+            | Kind::Return
+    )
+}
+
+fn is_pipe_stage(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Call { .. } | ExprKind::MethodCall { .. } | ExprKind::Ident(_)
+    )
+}
+
+fn is_place(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Ident(_) | ExprKind::Field { safe: false, .. } | ExprKind::Index { .. }
+    )
+}
+
+fn strip_delims(lexeme: &str, open: usize) -> String {
+    let chars: Vec<char> = lexeme.chars().collect();
+    if chars.len() < open + 1 {
+        return String::new();
+    }
+    chars[open..chars.len() - 1].iter().collect()
+}
+
+/// Lexes and parses a complete rot source file.
+pub fn parse_source(source: &str) -> ParseResult {
+    let mut lex = Lexer::new(source.chars().collect());
+    lex.lex();
+    let mut result = Parser::new(lex.tokens).parse();
+    lex.diagnostics.append(&mut result.diagnostics);
+    result.diagnostics = lex.diagnostics;
+    result
+}
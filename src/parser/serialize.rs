@@ -0,0 +1,514 @@
+//! Language neutral dumps of the AST.
+//!
+//! # JSON schema, version 1
+//!
+//! The document is a single object:
+//!
+//! ```text
+//! {"version": 1, "module": <node>, "diagnostics": [<diagnostic>, ...]}
+//! ```
+//!
+//! Every node is an object with
+//!
+//! - `kind`, the node type, for example `"Function"`, `"Binary"` or `"TuplePattern"`
+//! - `span`, `[start, end]` as char offsets into the source, end exclusive
+//! - `role`, how the node relates to its parent (`"lhs"`, `"body"`, `"arg"`, ...),
+//!   absent on the module
+//! - `id`, the `NodeId` of expression nodes
+//! - node specific attributes, all strings or booleans: `name`, `op`, `label`,
+//!   `value`, `literal` (`int`, `float`, `string`, `char`, `regex`, `bool`,
+//!   `null`), `type` (primitive names), `public`, `static`, `safe`, `mutable`.
+//!   The `op` of a compound `Assign` is the full operator, `"+="`
+//! - `children`, the child nodes in source order
+//!
+//! A diagnostic is `{"severity": "error" | "warning", "message": ..., "span": [start, end]}`.
+//!
+//! New kinds and attributes may be added without bumping `version`. Renaming or
+//! removing anything bumps it.
+//!
+//! # S-expressions
+//!
+//! A compact form meant for golden tests, one node per line:
+//! `(Binary :op "+"` with the children indented below it. Spans and ids are left
+//! out so tests do not churn on unrelated edits.
+
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Serializes the module and the diagnostics produced while parsing it.
+pub fn to_json(module: &Module, diagnostics: &[Diagnostic]) -> String {
+    let mut out = format!("{{\"version\":{},\"module\":", SCHEMA_VERSION);
+    write_json(&module_node(module), None, &mut out);
+    out.push_str(",\"diagnostics\":[");
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&format!(
+            "{{\"severity\":\"{}\",\"message\":{},\"span\":[{},{}]}}",
+            diagnostic.severity,
+            json_string(&diagnostic.message),
+            diagnostic.span.start,
+            diagnostic.span.end
+        ));
+    }
+    out.push_str("]}");
+    out
+}
+
+pub fn to_sexpr(module: &Module) -> String {
+    let mut out = String::new();
+    write_sexpr(&module_node(module), 0, &mut out);
+    out.push('\n');
+    out
+}
+
+// ----- generic node tree -----
+
+enum Attr {
+    Str(String),
+    Bool(bool),
+}
+
+struct Node {
+    kind: &'static str,
+    span: Span,
+    id: Option<NodeId>,
+    attrs: Vec<(&'static str, Attr)>,
+    children: Vec<(&'static str, Node)>,
+}
+
+impl Node {
+    fn new(kind: &'static str, span: Span) -> Node {
+        Node {
+            kind,
+            span,
+            id: None,
+            attrs: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn attr(mut self, key: &'static str, value: impl Into<String>) -> Node {
+        self.attrs.push((key, Attr::Str(value.into())));
+        self
+    }
+
+    fn flag(mut self, key: &'static str, value: bool) -> Node {
+        self.attrs.push((key, Attr::Bool(value)));
+        self
+    }
+
+    fn child(mut self, role: &'static str, node: Node) -> Node {
+        self.children.push((role, node));
+        self
+    }
+
+    fn children(mut self, role: &'static str, nodes: impl IntoIterator<Item = Node>) -> Node {
+        self.children
+            .extend(nodes.into_iter().map(|node| (role, node)));
+        self
+    }
+}
+
+fn write_json(node: &Node, role: Option<&str>, out: &mut String) {
+    out.push_str(&format!("{{\"kind\":\"{}\"", node.kind));
+    if let Some(role) = role {
+        out.push_str(&format!(",\"role\":\"{}\"", role));
+    }
+    out.push_str(&format!(
+        ",\"span\":[{},{}]",
+        node.span.start, node.span.end
+    ));
+    if let Some(id) = node.id {
+        out.push_str(&format!(",\"id\":{}", id.0));
+    }
+    for (key, value) in &node.attrs {
+        let value = match value {
+            Attr::Str(text) => json_string(text),
+            Attr::Bool(flag) => flag.to_string(),
+        };
+        out.push_str(&format!(",\"{}\":{}", key, value));
+    }
+    out.push_str(",\"children\":[");
+    for (i, (role, child)) in node.children.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_json(child, Some(role), out);
+    }
+    out.push_str("]}");
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn write_sexpr(node: &Node, depth: usize, out: &mut String) {
+    out.push_str(&format!("{:indent$}({}", "", node.kind, indent = depth * 2));
+    for (key, value) in &node.attrs {
+        match value {
+            Attr::Str(text) => out.push_str(&format!(" :{} {}", key, json_string(text))),
+            Attr::Bool(flag) => out.push_str(&format!(" :{} {}", key, flag)),
+        }
+    }
+    for (_, child) in &node.children {
+        out.push('\n');
+        write_sexpr(child, depth + 1, out);
+    }
+    out.push(')');
+}
+
+// ----- conversion from the AST -----
+
+fn module_node(module: &Module) -> Node {
+    Node::new("Module", module.span).children("item", module.items.iter().map(item))
+}
+
+fn ident(kind: &'static str, ident: &Ident) -> Node {
+    Node::new(kind, ident.span).attr("name", &ident.name)
+}
+
+fn item(item: &Item) -> Node {
+    match &item.kind {
+        ItemKind::Function(decl) => function(decl, item.span),
+        ItemKind::Struct(decl) => Node::new("Struct", item.span)
+            .attr("name", &decl.name.name)
+            .children("generic", decl.generics.iter().map(generic_param))
+            .children(
+                "field",
+                decl.fields.iter().map(|field| {
+                    let node = Node::new("StructField", field.span)
+                        .attr("name", &field.name.name)
+                        .flag("public", field.visibility == Visibility::Public)
+                        .flag("mutable", field.mutable)
+                        .child("type", type_expr(&field.ty));
+                    match &field.default {
+                        Some(default) => node.child("default", expr(default)),
+                        None => node,
+                    }
+                }),
+            ),
+        ItemKind::Impl(block) => Node::new("Impl", item.span)
+            .attr("target", &block.target.name)
+            .children(
+                "method",
+                block.methods.iter().map(|method| {
+                    Node::new("Method", method.span)
+                        .flag("static", method.is_static)
+                        .child("function", function(&method.function, method.span))
+                }),
+            ),
+        ItemKind::TypeAlias(alias) => Node::new("TypeAlias", item.span)
+            .attr("name", &alias.name.name)
+            .children("generic", alias.generics.iter().map(generic_param))
+            .child("type", type_expr(&alias.ty)),
+        ItemKind::Macro(decl) => Node::new("Macro", item.span)
+            .attr("name", &decl.name.name)
+            .children(
+                "param",
+                decl.params.iter().map(|param| ident("MacroParam", param)),
+            )
+            .child("body", block(&decl.body)),
+        ItemKind::Use(decl) => {
+            let node = Node::new("Use", item.span).attr("path", decl.module_name());
+            match &decl.names {
+                Some(names) => {
+                    node.children("name", names.iter().map(|name| ident("UseName", name)))
+                }
+                None => node,
+            }
+        }
+    }
+}
+
+fn function(decl: &FunctionDecl, span: Span) -> Node {
+    let mut node = Node::new("Function", span)
+        .attr("name", &decl.name.name)
+        .children("generic", decl.generics.iter().map(generic_param));
+    if let Some(receiver) = &decl.receiver {
+        node = node.child("receiver", Node::new("Receiver", receiver.span));
+    }
+    node = node.children(
+        "param",
+        decl.params.iter().map(|param| {
+            Node::new("Param", param.span)
+                .attr("name", &param.name.name)
+                .flag("mutable", param.mutable)
+                .child("type", type_expr(&param.ty))
+        }),
+    );
+    if let Some(ty) = &decl.return_type {
+        node = node.child("return_type", type_expr(ty));
+    }
+    node.child("body", block(&decl.body))
+}
+
+fn generic_param(param: &GenericParam) -> Node {
+    Node::new("GenericParam", param.span)
+        .attr("name", &param.name.name)
+        .children("bound", param.bounds.iter().map(type_expr))
+}
+
+fn type_expr(ty: &TypeExpr) -> Node {
+    match &ty.kind {
+        TypeKind::Primitive(prim) => Node::new("PrimitiveType", ty.span).attr("type", prim.name()),
+        TypeKind::Named(name) => Node::new("NamedType", ty.span).attr("name", &name.name),
+        TypeKind::Generic { name, args } => Node::new("GenericType", ty.span)
+            .attr("name", &name.name)
+            .children("arg", args.iter().map(type_expr)),
+        TypeKind::Function { params, ret } => Node::new("FunctionType", ty.span)
+            .children(
+                "param",
+                params.iter().map(|param| {
+                    let span = match &param.name {
+                        Some(name) => name.span.to(param.ty.span),
+                        None => param.ty.span,
+                    };
+                    let node = Node::new("FunctionTypeParam", span);
+                    let node = match &param.name {
+                        Some(name) => node.attr("name", &name.name),
+                        None => node,
+                    };
+                    node.child("type", type_expr(&param.ty))
+                }),
+            )
+            .child("return_type", type_expr(ret)),
+        TypeKind::Nullable(inner) => {
+            Node::new("NullableType", ty.span).child("type", type_expr(inner))
+        }
+        TypeKind::List(element) => {
+            Node::new("ListType", ty.span).child("element", type_expr(element))
+        }
+        TypeKind::Map { key, value } => Node::new("MapType", ty.span)
+            .child("key", type_expr(key))
+            .child("value", type_expr(value)),
+        TypeKind::Tuple(elements) => {
+            Node::new("TupleType", ty.span).children("element", elements.iter().map(type_expr))
+        }
+        TypeKind::Missing => Node::new("MissingType", ty.span),
+    }
+}
+
+fn block(block: &Block) -> Node {
+    Node::new("Block", block.span).children(
+        "stmt",
+        block.stmts.iter().map(|stmt| match &stmt.kind {
+            StmtKind::Let(binding) => {
+                let node = Node::new("Let", stmt.span)
+                    .attr("name", &binding.name.name)
+                    .flag("mutable", binding.mutable);
+                let node = match &binding.ty {
+                    Some(ty) => node.child("type", type_expr(ty)),
+                    None => node,
+                };
+                node.child("value", expr(&binding.value))
+            }
+            StmtKind::Expr(value) => Node::new("ExprStmt", stmt.span).child("expr", expr(value)),
+        }),
+    )
+}
+
+fn literal(node: Node, literal: &Literal) -> Node {
+    let (kind, value) = match literal {
+        Literal::Int(text) => ("int", text.clone()),
+        Literal::Float(text) => ("float", text.clone()),
+        Literal::Str(text) => ("string", text.clone()),
+        Literal::Char(text) => ("char", text.clone()),
+        Literal::Regex(text) => ("regex", text.clone()),
+        Literal::Bool(value) => ("bool", value.to_string()),
+        Literal::Null => ("null", "null".to_string()),
+    };
+    node.attr("literal", kind).attr("value", value)
+}
+
+fn label(node: Node, label: &Option<Ident>) -> Node {
+    match label {
+        Some(label) => node.attr("label", &label.name),
+        None => node,
+    }
+}
+
+fn expr(e: &Expr) -> Node {
+    let node = match &e.kind {
+        ExprKind::Literal(lit) => literal(Node::new("Literal", e.span), lit),
+        ExprKind::Ident(name) => Node::new("Ident", e.span).attr("name", &name.name),
+        ExprKind::Unary { op, operand } => Node::new("Unary", e.span)
+            .attr("op", op.symbol())
+            .child("operand", expr(operand)),
+        ExprKind::Binary { op, lhs, rhs } => Node::new("Binary", e.span)
+            .attr("op", op.symbol())
+            .child("lhs", expr(lhs))
+            .child("rhs", expr(rhs)),
+        ExprKind::Assign { target, op, value } => {
+            let node = Node::new("Assign", e.span);
+            let node = match op {
+                Some(op) => node.attr("op", format!("{}=", op.symbol())),
+                None => node,
+            };
+            node.child("target", expr(target))
+                .child("value", expr(value))
+        }
+        ExprKind::Call { callee, args } => Node::new("Call", e.span)
+            .child("callee", expr(callee))
+            .children("arg", args.iter().map(expr)),
+        ExprKind::Field { base, name, safe } => Node::new("Field", e.span)
+            .attr("name", &name.name)
+            .flag("safe", *safe)
+            .child("base", expr(base)),
+        ExprKind::MethodCall {
+            receiver,
+            method,
+            args,
+            safe,
+        } => Node::new("MethodCall", e.span)
+            .attr("name", &method.name)
+            .flag("safe", *safe)
+            .child("receiver", expr(receiver))
+            .children("arg", args.iter().map(expr)),
+        ExprKind::StructLit { name, fields } => Node::new("StructLit", e.span)
+            .attr("name", &name.name)
+            .children(
+                "field",
+                fields.iter().map(|field| {
+                    Node::new("FieldInit", field.span)
+                        .attr("name", &field.name.name)
+                        .child("value", expr(&field.value))
+                }),
+            ),
+        ExprKind::List(elements) => {
+            Node::new("List", e.span).children("element", elements.iter().map(expr))
+        }
+        ExprKind::Map(entries) => Node::new("Map", e.span).children(
+            "entry",
+            entries.iter().map(|entry| {
+                Node::new("MapEntry", entry.span)
+                    .child("key", expr(&entry.key))
+                    .child("value", expr(&entry.value))
+            }),
+        ),
+        ExprKind::Tuple(elements) => {
+            Node::new("Tuple", e.span).children("element", elements.iter().map(expr))
+        }
+        ExprKind::Index { base, index } => Node::new("Index", e.span)
+            .child("base", expr(base))
+            .child("index", expr(index)),
+        ExprKind::Cast { expr: value, ty } => Node::new("Cast", e.span)
+            .child("expr", expr(value))
+            .child("type", type_expr(ty)),
+        ExprKind::Lambda(lambda) => {
+            let node = Node::new("Lambda", e.span).children(
+                "param",
+                lambda.params.iter().map(|param| {
+                    let node = Node::new("LambdaParam", param.span).attr("name", &param.name.name);
+                    match &param.ty {
+                        Some(ty) => node.child("type", type_expr(ty)),
+                        None => node,
+                    }
+                }),
+            );
+            let node = match &lambda.return_type {
+                Some(ty) => node.child("return_type", type_expr(ty)),
+                None => node,
+            };
+            node.child("body", block(&lambda.body))
+        }
+        ExprKind::If(if_expr) => {
+            let node = Node::new("If", e.span).children(
+                "branch",
+                if_expr.branches.iter().map(|branch| {
+                    Node::new("IfBranch", branch.condition.span.to(branch.block.span))
+                        .child("condition", expr(&branch.condition))
+                        .child("body", block(&branch.block))
+                }),
+            );
+            match &if_expr.else_block {
+                Some(else_block) => node.child("else", block(else_block)),
+                None => node,
+            }
+        }
+        ExprKind::Match(match_expr) => Node::new("Match", e.span)
+            .child("scrutinee", expr(&match_expr.scrutinee))
+            .children(
+                "arm",
+                match_expr.arms.iter().map(|arm| {
+                    let node =
+                        Node::new("MatchArm", arm.span).child("pattern", pattern(&arm.pattern));
+                    let node = match &arm.guard {
+                        Some(guard) => node.child("guard", expr(guard)),
+                        None => node,
+                    };
+                    node.child("body", expr(&arm.body))
+                }),
+            ),
+        ExprKind::While(while_expr) => label(Node::new("While", e.span), &while_expr.label)
+            .child("condition", expr(&while_expr.condition))
+            .child("body", block(&while_expr.body)),
+        ExprKind::For(for_expr) => label(Node::new("For", e.span), &for_expr.label)
+            .child("pattern", pattern(&for_expr.pattern))
+            .child("iterable", expr(&for_expr.iterable))
+            .child("body", block(&for_expr.body)),
+        ExprKind::Break { label: name, value } => {
+            let node = label(Node::new("Break", e.span), name);
+            match value {
+                Some(value) => node.child("value", expr(value)),
+                None => node,
+            }
+        }
+        ExprKind::Continue { label: name } => label(Node::new("Continue", e.span), name),
+        ExprKind::Block(inner) => Node::new("BlockExpr", e.span).child("block", block(inner)),
+        ExprKind::Return(value) => {
+            let node = Node::new("Return", e.span);
+            match value {
+                Some(value) => node.child("value", expr(value)),
+                None => node,
+            }
+        }
+        ExprKind::Missing => Node::new("Missing", e.span),
+    };
+    Node {
+        id: Some(e.id),
+        ..node
+    }
+}
+
+fn pattern(p: &Pattern) -> Node {
+    match &p.kind {
+        PatternKind::Wildcard => Node::new("WildcardPattern", p.span),
+        PatternKind::Binding(name) => ident("BindingPattern", name),
+        PatternKind::Literal(lit) => literal(Node::new("LiteralPattern", p.span), lit),
+        PatternKind::Tuple(elements) => {
+            Node::new("TuplePattern", p.span).children("element", elements.iter().map(pattern))
+        }
+        PatternKind::Struct { name, fields } => Node::new("StructPattern", p.span)
+            .attr("name", &name.name)
+            .children(
+                "field",
+                fields.iter().map(|field| {
+                    Node::new("FieldPattern", field.span)
+                        .attr("name", &field.name.name)
+                        .child("pattern", pattern(&field.pattern))
+                }),
+            ),
+        PatternKind::Or(alternatives) => {
+            Node::new("OrPattern", p.span).children("alternative", alternatives.iter().map(pattern))
+        }
+    }
+}
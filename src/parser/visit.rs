@@ -0,0 +1,411 @@
+use crate::parser::ast::*;
+
+/// Read-only traversal of the AST. Every method defaults to the matching
+/// `walk_*` function, so a pass overrides the nodes it cares about and calls
+/// the walk function from there to keep descending.
+pub trait Visitor: Sized {
+    fn visit_module(&mut self, module: &Module) {
+        walk_module(self, module)
+    }
+
+    fn visit_item(&mut self, item: &Item) {
+        walk_item(self, item)
+    }
+
+    fn visit_function(&mut self, decl: &FunctionDecl) {
+        walk_function(self, decl)
+    }
+
+    fn visit_generic_param(&mut self, param: &GenericParam) {
+        walk_generic_param(self, param)
+    }
+
+    fn visit_param(&mut self, param: &Param) {
+        walk_param(self, param)
+    }
+
+    fn visit_struct(&mut self, decl: &StructDecl) {
+        walk_struct(self, decl)
+    }
+
+    fn visit_struct_field(&mut self, field: &StructField) {
+        walk_struct_field(self, field)
+    }
+
+    fn visit_impl(&mut self, block: &ImplBlock) {
+        walk_impl(self, block)
+    }
+
+    fn visit_method(&mut self, method: &Method) {
+        walk_method(self, method)
+    }
+
+    fn visit_type_alias(&mut self, alias: &TypeAlias) {
+        walk_type_alias(self, alias)
+    }
+
+    fn visit_macro(&mut self, decl: &MacroDecl) {
+        walk_macro(self, decl)
+    }
+
+    fn visit_use(&mut self, decl: &UseDecl) {
+        walk_use(self, decl)
+    }
+
+    fn visit_type(&mut self, ty: &TypeExpr) {
+        walk_type(self, ty)
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        walk_block(self, block)
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_let(&mut self, binding: &LetStmt) {
+        walk_let(self, binding)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_field_init(&mut self, field: &FieldInit) {
+        walk_field_init(self, field)
+    }
+
+    fn visit_map_entry(&mut self, entry: &MapEntry) {
+        walk_map_entry(self, entry)
+    }
+
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        walk_lambda(self, lambda)
+    }
+
+    fn visit_lambda_param(&mut self, param: &LambdaParam) {
+        walk_lambda_param(self, param)
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        walk_match_arm(self, arm)
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        walk_pattern(self, pattern)
+    }
+
+    fn visit_ident(&mut self, _ident: &Ident) {}
+
+    fn visit_literal(&mut self, _literal: &Literal) {}
+}
+
+pub fn walk_module<V: Visitor>(visitor: &mut V, module: &Module) {
+    for item in &module.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<V: Visitor>(visitor: &mut V, item: &Item) {
+    match &item.kind {
+        ItemKind::Function(decl) => visitor.visit_function(decl),
+        ItemKind::Struct(decl) => visitor.visit_struct(decl),
+        ItemKind::Impl(block) => visitor.visit_impl(block),
+        ItemKind::TypeAlias(alias) => visitor.visit_type_alias(alias),
+        ItemKind::Macro(decl) => visitor.visit_macro(decl),
+        ItemKind::Use(decl) => visitor.visit_use(decl),
+    }
+}
+
+pub fn walk_function<V: Visitor>(visitor: &mut V, decl: &FunctionDecl) {
+    visitor.visit_ident(&decl.name);
+    for param in &decl.generics {
+        visitor.visit_generic_param(param);
+    }
+    for param in &decl.params {
+        visitor.visit_param(param);
+    }
+    if let Some(ty) = &decl.return_type {
+        visitor.visit_type(ty);
+    }
+    visitor.visit_block(&decl.body);
+}
+
+pub fn walk_generic_param<V: Visitor>(visitor: &mut V, param: &GenericParam) {
+    visitor.visit_ident(&param.name);
+    for bound in &param.bounds {
+        visitor.visit_type(bound);
+    }
+}
+
+pub fn walk_param<V: Visitor>(visitor: &mut V, param: &Param) {
+    visitor.visit_ident(&param.name);
+    visitor.visit_type(&param.ty);
+}
+
+pub fn walk_struct<V: Visitor>(visitor: &mut V, decl: &StructDecl) {
+    visitor.visit_ident(&decl.name);
+    for param in &decl.generics {
+        visitor.visit_generic_param(param);
+    }
+    for field in &decl.fields {
+        visitor.visit_struct_field(field);
+    }
+}
+
+pub fn walk_struct_field<V: Visitor>(visitor: &mut V, field: &StructField) {
+    visitor.visit_ident(&field.name);
+    visitor.visit_type(&field.ty);
+    if let Some(default) = &field.default {
+        visitor.visit_expr(default);
+    }
+}
+
+pub fn walk_impl<V: Visitor>(visitor: &mut V, block: &ImplBlock) {
+    visitor.visit_ident(&block.target);
+    for method in &block.methods {
+        visitor.visit_method(method);
+    }
+}
+
+pub fn walk_method<V: Visitor>(visitor: &mut V, method: &Method) {
+    visitor.visit_function(&method.function);
+}
+
+pub fn walk_type_alias<V: Visitor>(visitor: &mut V, alias: &TypeAlias) {
+    visitor.visit_ident(&alias.name);
+    for param in &alias.generics {
+        visitor.visit_generic_param(param);
+    }
+    visitor.visit_type(&alias.ty);
+}
+
+pub fn walk_macro<V: Visitor>(visitor: &mut V, decl: &MacroDecl) {
+    visitor.visit_ident(&decl.name);
+    for param in &decl.params {
+        visitor.visit_ident(param);
+    }
+    visitor.visit_block(&decl.body);
+}
+
+pub fn walk_use<V: Visitor>(visitor: &mut V, decl: &UseDecl) {
+    for segment in &decl.path {
+        visitor.visit_ident(segment);
+    }
+    for name in decl.names.iter().flatten() {
+        visitor.visit_ident(name);
+    }
+}
+
+pub fn walk_type<V: Visitor>(visitor: &mut V, ty: &TypeExpr) {
+    match &ty.kind {
+        TypeKind::Primitive(_) | TypeKind::Missing => {}
+        TypeKind::Named(name) => visitor.visit_ident(name),
+        TypeKind::Generic { name, args } => {
+            visitor.visit_ident(name);
+            for arg in args {
+                visitor.visit_type(arg);
+            }
+        }
+        TypeKind::Function { params, ret } => {
+            for param in params {
+                if let Some(name) = &param.name {
+                    visitor.visit_ident(name);
+                }
+                visitor.visit_type(&param.ty);
+            }
+            visitor.visit_type(ret);
+        }
+        TypeKind::Nullable(inner) | TypeKind::List(inner) => visitor.visit_type(inner),
+        TypeKind::Map { key, value } => {
+            visitor.visit_type(key);
+            visitor.visit_type(value);
+        }
+        TypeKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_type(element);
+            }
+        }
+    }
+}
+
+pub fn walk_block<V: Visitor>(visitor: &mut V, block: &Block) {
+    for stmt in &block.stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_stmt<V: Visitor>(visitor: &mut V, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Let(binding) => visitor.visit_let(binding),
+        StmtKind::Expr(expr) => visitor.visit_expr(expr),
+    }
+}
+
+// the value is walked before the name, it cannot see the binding it initializes
+pub fn walk_let<V: Visitor>(visitor: &mut V, binding: &LetStmt) {
+    if let Some(ty) = &binding.ty {
+        visitor.visit_type(ty);
+    }
+    visitor.visit_expr(&binding.value);
+    visitor.visit_ident(&binding.name);
+}
+
+pub fn walk_expr<V: Visitor>(visitor: &mut V, expr: &Expr) {
+    match &expr.kind {
+        ExprKind::Literal(literal) => visitor.visit_literal(literal),
+        ExprKind::Ident(ident) => visitor.visit_ident(ident),
+        ExprKind::Unary { operand, .. } => visitor.visit_expr(operand),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Assign { target, value, .. } => {
+            visitor.visit_expr(value);
+            visitor.visit_expr(target);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr(callee);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::Field { base, name, .. } => {
+            visitor.visit_expr(base);
+            visitor.visit_ident(name);
+        }
+        ExprKind::MethodCall {
+            receiver,
+            method,
+            args,
+            ..
+        } => {
+            visitor.visit_expr(receiver);
+            visitor.visit_ident(method);
+            for arg in args {
+                visitor.visit_expr(arg);
+            }
+        }
+        ExprKind::StructLit { name, fields } => {
+            visitor.visit_ident(name);
+            for field in fields {
+                visitor.visit_field_init(field);
+            }
+        }
+        ExprKind::List(elements) | ExprKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_expr(element);
+            }
+        }
+        ExprKind::Map(entries) => {
+            for entry in entries {
+                visitor.visit_map_entry(entry);
+            }
+        }
+        ExprKind::Index { base, index } => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(index);
+        }
+        ExprKind::Cast { expr, ty } => {
+            visitor.visit_expr(expr);
+            visitor.visit_type(ty);
+        }
+        ExprKind::Lambda(lambda) => visitor.visit_lambda(lambda),
+        ExprKind::If(if_expr) => {
+            for branch in &if_expr.branches {
+                visitor.visit_expr(&branch.condition);
+                visitor.visit_block(&branch.block);
+            }
+            if let Some(block) = &if_expr.else_block {
+                visitor.visit_block(block);
+            }
+        }
+        ExprKind::Match(match_expr) => {
+            visitor.visit_expr(&match_expr.scrutinee);
+            for arm in &match_expr.arms {
+                visitor.visit_match_arm(arm);
+            }
+        }
+        ExprKind::While(while_expr) => {
+            visitor.visit_expr(&while_expr.condition);
+            visitor.visit_block(&while_expr.body);
+        }
+        ExprKind::For(for_expr) => {
+            visitor.visit_expr(&for_expr.iterable);
+            visitor.visit_pattern(&for_expr.pattern);
+            visitor.visit_block(&for_expr.body);
+        }
+        ExprKind::Break { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+        ExprKind::Continue { .. } | ExprKind::Missing => {}
+        ExprKind::Block(block) => visitor.visit_block(block),
+        ExprKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+pub fn walk_field_init<V: Visitor>(visitor: &mut V, field: &FieldInit) {
+    visitor.visit_ident(&field.name);
+    visitor.visit_expr(&field.value);
+}
+
+pub fn walk_map_entry<V: Visitor>(visitor: &mut V, entry: &MapEntry) {
+    visitor.visit_expr(&entry.key);
+    visitor.visit_expr(&entry.value);
+}
+
+pub fn walk_lambda<V: Visitor>(visitor: &mut V, lambda: &Lambda) {
+    for param in &lambda.params {
+        visitor.visit_lambda_param(param);
+    }
+    if let Some(ty) = &lambda.return_type {
+        visitor.visit_type(ty);
+    }
+    visitor.visit_block(&lambda.body);
+}
+
+pub fn walk_lambda_param<V: Visitor>(visitor: &mut V, param: &LambdaParam) {
+    visitor.visit_ident(&param.name);
+    if let Some(ty) = &param.ty {
+        visitor.visit_type(ty);
+    }
+}
+
+pub fn walk_match_arm<V: Visitor>(visitor: &mut V, arm: &MatchArm) {
+    visitor.visit_pattern(&arm.pattern);
+    if let Some(guard) = &arm.guard {
+        visitor.visit_expr(guard);
+    }
+    visitor.visit_expr(&arm.body);
+}
+
+pub fn walk_pattern<V: Visitor>(visitor: &mut V, pattern: &Pattern) {
+    match &pattern.kind {
+        PatternKind::Wildcard => {}
+        PatternKind::Binding(ident) => visitor.visit_ident(ident),
+        PatternKind::Literal(literal) => visitor.visit_literal(literal),
+        PatternKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_pattern(element);
+            }
+        }
+        PatternKind::Struct { name, fields } => {
+            visitor.visit_ident(name);
+            for field in fields {
+                visitor.visit_pattern(&field.pattern);
+            }
+        }
+        PatternKind::Or(alternatives) => {
+            for alternative in alternatives {
+                visitor.visit_pattern(alternative);
+            }
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use crate::parser::ast::*;
+
+/// Traversal of the AST that may edit nodes in place, the mutable twin of
+/// `Visitor`. Methods default to the matching `walk_*_mut` function.
+pub trait VisitorMut: Sized {
+    fn visit_module_mut(&mut self, module: &mut Module) {
+        walk_module_mut(self, module)
+    }
+
+    fn visit_item_mut(&mut self, item: &mut Item) {
+        walk_item_mut(self, item)
+    }
+
+    fn visit_function_mut(&mut self, decl: &mut FunctionDecl) {
+        walk_function_mut(self, decl)
+    }
+
+    fn visit_generic_param_mut(&mut self, param: &mut GenericParam) {
+        walk_generic_param_mut(self, param)
+    }
+
+    fn visit_param_mut(&mut self, param: &mut Param) {
+        walk_param_mut(self, param)
+    }
+
+    fn visit_struct_mut(&mut self, decl: &mut StructDecl) {
+        walk_struct_mut(self, decl)
+    }
+
+    fn visit_struct_field_mut(&mut self, field: &mut StructField) {
+        walk_struct_field_mut(self, field)
+    }
+
+    fn visit_impl_mut(&mut self, block: &mut ImplBlock) {
+        walk_impl_mut(self, block)
+    }
+
+    fn visit_method_mut(&mut self, method: &mut Method) {
+        walk_method_mut(self, method)
+    }
+
+    fn visit_type_alias_mut(&mut self, alias: &mut TypeAlias) {
+        walk_type_alias_mut(self, alias)
+    }
+
+    fn visit_macro_mut(&mut self, decl: &mut MacroDecl) {
+        walk_macro_mut(self, decl)
+    }
+
+    fn visit_use_mut(&mut self, decl: &mut UseDecl) {
+        walk_use_mut(self, decl)
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut TypeExpr) {
+        walk_type_mut(self, ty)
+    }
+
+    fn visit_block_mut(&mut self, block: &mut Block) {
+        walk_block_mut(self, block)
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut Stmt) {
+        walk_stmt_mut(self, stmt)
+    }
+
+    fn visit_let_mut(&mut self, binding: &mut LetStmt) {
+        walk_let_mut(self, binding)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_field_init_mut(&mut self, field: &mut FieldInit) {
+        walk_field_init_mut(self, field)
+    }
+
+    fn visit_map_entry_mut(&mut self, entry: &mut MapEntry) {
+        walk_map_entry_mut(self, entry)
+    }
+
+    fn visit_lambda_mut(&mut self, lambda: &mut Lambda) {
+        walk_lambda_mut(self, lambda)
+    }
+
+    fn visit_lambda_param_mut(&mut self, param: &mut LambdaParam) {
+        walk_lambda_param_mut(self, param)
+    }
+
+    fn visit_match_arm_mut(&mut self, arm: &mut MatchArm) {
+        walk_match_arm_mut(self, arm)
+    }
+
+    fn visit_pattern_mut(&mut self, pattern: &mut Pattern) {
+        walk_pattern_mut(self, pattern)
+    }
+
+    fn visit_ident_mut(&mut self, _ident: &mut Ident) {}
+
+    fn visit_literal_mut(&mut self, _literal: &mut Literal) {}
+}
+
+pub fn walk_module_mut<V: VisitorMut>(visitor: &mut V, module: &mut Module) {
+    for item in &mut module.items {
+        visitor.visit_item_mut(item);
+    }
+}
+
+pub fn walk_item_mut<V: VisitorMut>(visitor: &mut V, item: &mut Item) {
+    match &mut item.kind {
+        ItemKind::Function(decl) => visitor.visit_function_mut(decl),
+        ItemKind::Struct(decl) => visitor.visit_struct_mut(decl),
+        ItemKind::Impl(block) => visitor.visit_impl_mut(block),
+        ItemKind::TypeAlias(alias) => visitor.visit_type_alias_mut(alias),
+        ItemKind::Macro(decl) => visitor.visit_macro_mut(decl),
+        ItemKind::Use(decl) => visitor.visit_use_mut(decl),
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut>(visitor: &mut V, decl: &mut FunctionDecl) {
+    visitor.visit_ident_mut(&mut decl.name);
+    for param in &mut decl.generics {
+        visitor.visit_generic_param_mut(param);
+    }
+    for param in &mut decl.params {
+        visitor.visit_param_mut(param);
+    }
+    if let Some(ty) = &mut decl.return_type {
+        visitor.visit_type_mut(ty);
+    }
+    visitor.visit_block_mut(&mut decl.body);
+}
+
+pub fn walk_generic_param_mut<V: VisitorMut>(visitor: &mut V, param: &mut GenericParam) {
+    visitor.visit_ident_mut(&mut param.name);
+    for bound in &mut param.bounds {
+        visitor.visit_type_mut(bound);
+    }
+}
+
+pub fn walk_param_mut<V: VisitorMut>(visitor: &mut V, param: &mut Param) {
+    visitor.visit_ident_mut(&mut param.name);
+    visitor.visit_type_mut(&mut param.ty);
+}
+
+pub fn walk_struct_mut<V: VisitorMut>(visitor: &mut V, decl: &mut StructDecl) {
+    visitor.visit_ident_mut(&mut decl.name);
+    for param in &mut decl.generics {
+        visitor.visit_generic_param_mut(param);
+    }
+    for field in &mut decl.fields {
+        visitor.visit_struct_field_mut(field);
+    }
+}
+
+pub fn walk_struct_field_mut<V: VisitorMut>(visitor: &mut V, field: &mut StructField) {
+    visitor.visit_ident_mut(&mut field.name);
+    visitor.visit_type_mut(&mut field.ty);
+    if let Some(default) = &mut field.default {
+        visitor.visit_expr_mut(default);
+    }
+}
+
+pub fn walk_impl_mut<V: VisitorMut>(visitor: &mut V, block: &mut ImplBlock) {
+    visitor.visit_ident_mut(&mut block.target);
+    for method in &mut block.methods {
+        visitor.visit_method_mut(method);
+    }
+}
+
+pub fn walk_method_mut<V: VisitorMut>(visitor: &mut V, method: &mut Method) {
+    visitor.visit_function_mut(&mut method.function);
+}
+
+pub fn walk_type_alias_mut<V: VisitorMut>(visitor: &mut V, alias: &mut TypeAlias) {
+    visitor.visit_ident_mut(&mut alias.name);
+    for param in &mut alias.generics {
+        visitor.visit_generic_param_mut(param);
+    }
+    visitor.visit_type_mut(&mut alias.ty);
+}
+
+pub fn walk_macro_mut<V: VisitorMut>(visitor: &mut V, decl: &mut MacroDecl) {
+    visitor.visit_ident_mut(&mut decl.name);
+    for param in &mut decl.params {
+        visitor.visit_ident_mut(param);
+    }
+    visitor.visit_block_mut(&mut decl.body);
+}
+
+pub fn walk_use_mut<V: VisitorMut>(visitor: &mut V, decl: &mut UseDecl) {
+    for segment in &mut decl.path {
+        visitor.visit_ident_mut(segment);
+    }
+    for name in decl.names.iter_mut().flatten() {
+        visitor.visit_ident_mut(name);
+    }
+}
+
+pub fn walk_type_mut<V: VisitorMut>(visitor: &mut V, ty: &mut TypeExpr) {
+    match &mut ty.kind {
+        TypeKind::Primitive(_) | TypeKind::Missing => {}
+        TypeKind::Named(name) => visitor.visit_ident_mut(name),
+        TypeKind::Generic { name, args } => {
+            visitor.visit_ident_mut(name);
+            for arg in args {
+                visitor.visit_type_mut(arg);
+            }
+        }
+        TypeKind::Function { params, ret } => {
+            for param in params {
+                if let Some(name) = &mut param.name {
+                    visitor.visit_ident_mut(name);
+                }
+                visitor.visit_type_mut(&mut param.ty);
+            }
+            visitor.visit_type_mut(ret);
+        }
+        TypeKind::Nullable(inner) | TypeKind::List(inner) => visitor.visit_type_mut(inner),
+        TypeKind::Map { key, value } => {
+            visitor.visit_type_mut(key);
+            visitor.visit_type_mut(value);
+        }
+        TypeKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_type_mut(element);
+            }
+        }
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut>(visitor: &mut V, block: &mut Block) {
+    for stmt in &mut block.stmts {
+        visitor.visit_stmt_mut(stmt);
+    }
+}
+
+pub fn walk_stmt_mut<V: VisitorMut>(visitor: &mut V, stmt: &mut Stmt) {
+    match &mut stmt.kind {
+        StmtKind::Let(binding) => visitor.visit_let_mut(binding),
+        StmtKind::Expr(expr) => visitor.visit_expr_mut(expr),
+    }
+}
+
+// the value is walked before the name, it cannot see the binding it initializes
+pub fn walk_let_mut<V: VisitorMut>(visitor: &mut V, binding: &mut LetStmt) {
+    if let Some(ty) = &mut binding.ty {
+        visitor.visit_type_mut(ty);
+    }
+    visitor.visit_expr_mut(&mut binding.value);
+    visitor.visit_ident_mut(&mut binding.name);
+}
+
+pub fn walk_expr_mut<V: VisitorMut>(visitor: &mut V, expr: &mut Expr) {
+    match &mut expr.kind {
+        ExprKind::Literal(literal) => visitor.visit_literal_mut(literal),
+        ExprKind::Ident(ident) => visitor.visit_ident_mut(ident),
+        ExprKind::Unary { operand, .. } => visitor.visit_expr_mut(operand),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr_mut(lhs);
+            visitor.visit_expr_mut(rhs);
+        }
+        ExprKind::Assign { target, value, .. } => {
+            visitor.visit_expr_mut(value);
+            visitor.visit_expr_mut(target);
+        }
+        ExprKind::Call { callee, args } => {
+            visitor.visit_expr_mut(callee);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprKind::Field { base, name, .. } => {
+            visitor.visit_expr_mut(base);
+            visitor.visit_ident_mut(name);
+        }
+        ExprKind::MethodCall {
+            receiver,
+            method,
+            args,
+            ..
+        } => {
+            visitor.visit_expr_mut(receiver);
+            visitor.visit_ident_mut(method);
+            for arg in args {
+                visitor.visit_expr_mut(arg);
+            }
+        }
+        ExprKind::StructLit { name, fields } => {
+            visitor.visit_ident_mut(name);
+            for field in fields {
+                visitor.visit_field_init_mut(field);
+            }
+        }
+        ExprKind::List(elements) | ExprKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_expr_mut(element);
+            }
+        }
+        ExprKind::Map(entries) => {
+            for entry in entries {
+                visitor.visit_map_entry_mut(entry);
+            }
+        }
+        ExprKind::Index { base, index } => {
+            visitor.visit_expr_mut(base);
+            visitor.visit_expr_mut(index);
+        }
+        ExprKind::Cast { expr, ty } => {
+            visitor.visit_expr_mut(expr);
+            visitor.visit_type_mut(ty);
+        }
+        ExprKind::Lambda(lambda) => visitor.visit_lambda_mut(lambda),
+        ExprKind::If(if_expr) => {
+            for branch in &mut if_expr.branches {
+                visitor.visit_expr_mut(&mut branch.condition);
+                visitor.visit_block_mut(&mut branch.block);
+            }
+            if let Some(block) = &mut if_expr.else_block {
+                visitor.visit_block_mut(block);
+            }
+        }
+        ExprKind::Match(match_expr) => {
+            visitor.visit_expr_mut(&mut match_expr.scrutinee);
+            for arm in &mut match_expr.arms {
+                visitor.visit_match_arm_mut(arm);
+            }
+        }
+        ExprKind::While(while_expr) => {
+            visitor.visit_expr_mut(&mut while_expr.condition);
+            visitor.visit_block_mut(&mut while_expr.body);
+        }
+        ExprKind::For(for_expr) => {
+            visitor.visit_expr_mut(&mut for_expr.iterable);
+            visitor.visit_pattern_mut(&mut for_expr.pattern);
+            visitor.visit_block_mut(&mut for_expr.body);
+        }
+        ExprKind::Break { value, .. } => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+        ExprKind::Continue { .. } | ExprKind::Missing => {}
+        ExprKind::Block(block) => visitor.visit_block_mut(block),
+        ExprKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr_mut(value);
+            }
+        }
+    }
+}
+
+pub fn walk_field_init_mut<V: VisitorMut>(visitor: &mut V, field: &mut FieldInit) {
+    visitor.visit_ident_mut(&mut field.name);
+    visitor.visit_expr_mut(&mut field.value);
+}
+
+pub fn walk_map_entry_mut<V: VisitorMut>(visitor: &mut V, entry: &mut MapEntry) {
+    visitor.visit_expr_mut(&mut entry.key);
+    visitor.visit_expr_mut(&mut entry.value);
+}
+
+pub fn walk_lambda_mut<V: VisitorMut>(visitor: &mut V, lambda: &mut Lambda) {
+    for param in &mut lambda.params {
+        visitor.visit_lambda_param_mut(param);
+    }
+    if let Some(ty) = &mut lambda.return_type {
+        visitor.visit_type_mut(ty);
+    }
+    visitor.visit_block_mut(&mut lambda.body);
+}
+
+pub fn walk_lambda_param_mut<V: VisitorMut>(visitor: &mut V, param: &mut LambdaParam) {
+    visitor.visit_ident_mut(&mut param.name);
+    if let Some(ty) = &mut param.ty {
+        visitor.visit_type_mut(ty);
+    }
+}
+
+pub fn walk_match_arm_mut<V: VisitorMut>(visitor: &mut V, arm: &mut MatchArm) {
+    visitor.visit_pattern_mut(&mut arm.pattern);
+    if let Some(guard) = &mut arm.guard {
+        visitor.visit_expr_mut(guard);
+    }
+    visitor.visit_expr_mut(&mut arm.body);
+}
+
+pub fn walk_pattern_mut<V: VisitorMut>(visitor: &mut V, pattern: &mut Pattern) {
+    match &mut pattern.kind {
+        PatternKind::Wildcard => {}
+        PatternKind::Binding(ident) => visitor.visit_ident_mut(ident),
+        PatternKind::Literal(literal) => visitor.visit_literal_mut(literal),
+        PatternKind::Tuple(elements) => {
+            for element in elements {
+                visitor.visit_pattern_mut(element);
+            }
+        }
+        PatternKind::Struct { name, fields } => {
+            visitor.visit_ident_mut(name);
+            for field in fields {
+                visitor.visit_pattern_mut(&mut field.pattern);
+            }
+        }
+        PatternKind::Or(alternatives) => {
+            for alternative in alternatives {
+                visitor.visit_pattern_mut(alternative);
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use crate::parser::visit::{self, Visitor};
+use crate::semantic_analyzer::resolve::{DefId, Resolution};
+use std::collections::HashMap;
+
+/// An outer local a lambda refers to, in order of first use.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Capture {
+    pub name: String,
+    pub declared_at: Span,
+}
+
+/// Captures keyed by the `NodeId` of each lambda expression.
+pub type CaptureMap = HashMap<NodeId, Vec<Capture>>;
+
+struct CaptureCollector<'a> {
+    resolution: &'a Resolution,
+    // number of lambdas enclosing the definition of each local, absent for
+    // the parameters and function level locals
+    depths: HashMap<DefId, usize>,
+    lambdas: Vec<NodeId>,
+    captures: CaptureMap,
+}
+
+/// The outer locals each lambda of `module` refers to, with its names
+/// resolved in `resolution`.
+pub fn compute_captures(module: &Module, resolution: &Resolution) -> CaptureMap {
+    let mut collector = CaptureCollector {
+        resolution,
+        depths: HashMap::new(),
+        lambdas: Vec::new(),
+        captures: HashMap::new(),
+    };
+    collector.visit_module(module);
+    collector.captures
+}
+
+impl CaptureCollector<'_> {
+    fn declare(&mut self, name: &Ident) {
+        if let Some(def) = self.resolution.binding(name) {
+            self.depths.insert(def, self.lambdas.len());
+        }
+    }
+
+    // every lambda between the definition and the use has to carry the value
+    // along. `id` is the identifier expression
+    fn use_name(&mut self, id: NodeId) {
+        let Some(&def) = self.resolution.values.get(&id) else {
+            return;
+        };
+        let definition = self.resolution.definition(def);
+        if !definition.kind.is_local() {
+            return;
+        }
+        let depth = self.depths.get(&def).copied().unwrap_or(0);
+        for lambda in self.lambdas.iter().skip(depth) {
+            let captures = self.captures.entry(*lambda).or_default();
+            if !captures
+                .iter()
+                .any(|capture| capture.name == definition.name)
+            {
+                captures.push(Capture {
+                    name: definition.name.clone(),
+                    declared_at: definition.span,
+                });
+            }
+        }
+    }
+}
+
+impl Visitor for CaptureCollector<'_> {
+    fn visit_let(&mut self, binding: &LetStmt) {
+        self.visit_expr(&binding.value);
+        self.declare(&binding.name);
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        for ident in arm.pattern.bindings() {
+            self.declare(ident);
+        }
+        visit::walk_match_arm(self, arm);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Ident(_) => self.use_name(expr.id),
+            ExprKind::Assign { target, value, .. } => {
+                self.visit_expr(value);
+                match &target.kind {
+                    // the assignment defining a name is the first one walked
+                    ExprKind::Ident(ident) => {
+                        if let Some(def) = self.resolution.binding(ident) {
+                            self.depths.entry(def).or_insert(self.lambdas.len());
+                        }
+                        self.use_name(target.id);
+                    }
+                    _ => self.visit_expr(target),
+                }
+            }
+            ExprKind::Lambda(lambda) => {
+                self.captures.entry(expr.id).or_default();
+                self.lambdas.push(expr.id);
+                for param in &lambda.params {
+                    self.declare(&param.name);
+                }
+                self.visit_block(&lambda.body);
+                self.lambdas.pop();
+            }
+            ExprKind::For(for_expr) => {
+                self.visit_expr(&for_expr.iterable);
+                for ident in for_expr.pattern.bindings() {
+                    self.declare(ident);
+                }
+                self.visit_block(&for_expr.body);
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
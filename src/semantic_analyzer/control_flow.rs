@@ -0,0 +1,72 @@
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::parser::ast::*;
+use crate::parser::visit::{self, Visitor};
+
+/// Reports `break` and `continue` that have no enclosing loop to act on. Loops
+/// do not extend into lambdas defined inside them.
+pub fn check_control_flow(module: &Module) -> Vec<Diagnostic> {
+    let mut checker = ControlFlowChecker {
+        loops: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    checker.visit_module(module);
+    checker.diagnostics
+}
+
+struct ControlFlowChecker {
+    // labels of the enclosing loops, innermost last
+    loops: Vec<Option<String>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl ControlFlowChecker {
+    fn jump(&mut self, keyword: &str, label: &Option<Ident>, expr: &Expr) {
+        if self.loops.is_empty() {
+            self.diagnostics.push(Diagnostic::error(
+                format!("`{}` outside of a loop", keyword),
+                expr.span,
+            ));
+        } else if let Some(label) = label {
+            if !self.loops.contains(&Some(label.name.clone())) {
+                self.diagnostics.push(Diagnostic::error(
+                    format!("use of undeclared loop label `{}`", label.name),
+                    label.span,
+                ));
+            }
+        }
+    }
+
+    fn loop_body(&mut self, label: &Option<Ident>, body: &Block) {
+        self.loops
+            .push(label.as_ref().map(|label| label.name.clone()));
+        self.visit_block(body);
+        self.loops.pop();
+    }
+}
+
+impl Visitor for ControlFlowChecker {
+    fn visit_lambda(&mut self, lambda: &Lambda) {
+        let outer = std::mem::take(&mut self.loops);
+        visit::walk_lambda(self, lambda);
+        self.loops = outer;
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::While(while_expr) => {
+                self.visit_expr(&while_expr.condition);
+                self.loop_body(&while_expr.label, &while_expr.body);
+            }
+            ExprKind::For(for_expr) => {
+                self.visit_expr(&for_expr.iterable);
+                self.loop_body(&for_expr.label, &for_expr.body);
+            }
+            ExprKind::Break { label, .. } => {
+                self.jump("break", label, expr);
+                visit::walk_expr(self, expr);
+            }
+            ExprKind::Continue { label } => self.jump("continue", label, expr),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
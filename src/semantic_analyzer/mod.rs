@@ -1 +1,8 @@
-mod semantic_analyzer;
\ No newline at end of file
+pub mod captures;
+pub mod control_flow;
+pub mod mutability;
+pub mod resolve;
+#[allow(clippy::module_inception)]
+pub mod semantic_analyzer;
+pub mod type_checker;
+pub mod types;
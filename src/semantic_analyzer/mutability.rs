@@ -0,0 +1,129 @@
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::parser::ast::*;
+use crate::parser::visit::{self, Visitor};
+use crate::semantic_analyzer::resolve::DefKind;
+use crate::semantic_analyzer::type_checker::TypeckResults;
+use crate::semantic_analyzer::types::Type;
+use std::collections::HashMap;
+
+/// Reports assignments, compound ones included, to bindings, parameters and
+/// struct fields that are not declared `mut`. The binding an assignment
+/// targets is found through the names resolved in `types`, the field through
+/// the type of its base.
+pub fn check_mutability(module: &Module, types: &TypeckResults) -> Vec<Diagnostic> {
+    let mut checker = MutabilityChecker {
+        structs: HashMap::new(),
+        types,
+        diagnostics: Vec::new(),
+    };
+    for item in &module.items {
+        if let ItemKind::Struct(decl) = &item.kind {
+            checker.structs.insert(decl.name.name.clone(), decl);
+        }
+    }
+    checker.visit_module(module);
+    checker.diagnostics
+}
+
+struct MutabilityChecker<'a> {
+    structs: HashMap<String, &'a StructDecl>,
+    types: &'a TypeckResults,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl MutabilityChecker<'_> {
+    // `target` is the identifier expression assigned to
+    fn assign_to_name(&mut self, target: &Expr, ident: &Ident) {
+        let Some(def) = self.types.resolution.value(target.id) else {
+            return;
+        };
+        let name = ident.source_name();
+        let (message, note) = match def.kind {
+            _ if def.mutable => return,
+            DefKind::Local => (
+                format!("cannot assign twice to immutable binding `{}`", name),
+                format!(
+                    "`{}` is declared here, write `mut {}` to allow it",
+                    name, name
+                ),
+            ),
+            // the assignment defining the name
+            DefKind::Assigned if def.span == ident.span => return,
+            DefKind::Assigned => (
+                format!("cannot assign twice to immutable binding `{}`", name),
+                format!(
+                    "`{}` is first assigned here, declare it with `mut {} = ...` to allow it",
+                    name, name
+                ),
+            ),
+            DefKind::Param => (
+                format!("cannot assign to parameter `{}`", name),
+                format!(
+                    "`{}` is declared here, write `mut {}` to allow it",
+                    name, name
+                ),
+            ),
+            DefKind::LambdaParam => (
+                format!("cannot assign to parameter `{}`", name),
+                format!("`{}` is declared here", name),
+            ),
+            DefKind::Receiver => (
+                "cannot assign to `self`".to_string(),
+                "`self` is declared here".to_string(),
+            ),
+            DefKind::Binding => (
+                format!("cannot assign to immutable binding `{}`", name),
+                format!("`{}` is bound here", name),
+            ),
+            // only locals are assigned to, see `resolve_names`
+            _ => return,
+        };
+        self.diagnostics
+            .push(Diagnostic::error(message, ident.span).with_note(note, def.span));
+    }
+
+    fn assign_to_field(&mut self, base: &Expr, name: &Ident) {
+        let Some(Type::Struct(struct_name)) = self.types.types.get(&base.id).map(Type::non_null)
+        else {
+            return;
+        };
+        let Some(field) = self
+            .structs
+            .get(&struct_name)
+            .and_then(|decl| decl.fields.iter().find(|f| f.name.name == name.name))
+        else {
+            return;
+        };
+        if !field.mutable {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "cannot assign to field `{}` of `{}`, it is not declared `mut`",
+                        name.name, struct_name
+                    ),
+                    name.span,
+                )
+                .with_note(format!("`{}` is declared here", name.name), field.span),
+            );
+        }
+    }
+}
+
+impl Visitor for MutabilityChecker<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Assign { target, value, .. } => {
+                self.visit_expr(value);
+                match &target.kind {
+                    ExprKind::Ident(ident) => self.assign_to_name(target, ident),
+                    ExprKind::Field { base, name, .. } => {
+                        self.visit_expr(base);
+                        self.assign_to_field(base, name);
+                    }
+                    _ => self.visit_expr(target),
+                }
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
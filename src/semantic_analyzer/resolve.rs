@@ -0,0 +1,516 @@
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use crate::parser::visit::{self, Visitor};
+use std::collections::HashMap;
+
+/// Functions every module can call without importing them.
+pub const BUILTINS: &[&str] = &["print", "println"];
+
+/// Identifies a definition within the `Resolution` of one module.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, PartialOrd, Ord)]
+pub struct DefId(pub u32);
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DefKind {
+    Builtin,
+    /// `use geometry.shapes` defines `shapes`.
+    Module,
+    /// A name listed in `use geometry.shapes.{Circle, area}`.
+    Import,
+    Function,
+    Struct,
+    TypeAlias,
+    Generic,
+    Param,
+    /// `self` in an instance method.
+    Receiver,
+    /// A `x: T = v` or `mut x = v` binding.
+    Local,
+    /// The first assignment to a name, `x = v` where no local `x` is in scope.
+    Assigned,
+    LambdaParam,
+    /// A name bound by a `match` or `for` pattern.
+    Binding,
+}
+
+impl DefKind {
+    /// Whether the name can be used as a type. Imports may name either.
+    pub fn is_type(&self) -> bool {
+        matches!(
+            self,
+            DefKind::Struct | DefKind::TypeAlias | DefKind::Generic | DefKind::Import
+        )
+    }
+
+    /// Whether the definition is a value local to a function or lambda, the
+    /// only kind of name an assignment can target.
+    pub fn is_local(&self) -> bool {
+        matches!(
+            self,
+            DefKind::Param
+                | DefKind::Receiver
+                | DefKind::Local
+                | DefKind::Assigned
+                | DefKind::LambdaParam
+                | DefKind::Binding
+        )
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefKind,
+    /// The defining name, empty for builtins.
+    pub span: Span,
+    /// Declared `mut`, only locals and parameters can be.
+    pub mutable: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Resolution {
+    /// Indexed by `DefId`.
+    pub definitions: Vec<Definition>,
+    /// The definition each identifier expression refers to, by expression id.
+    pub values: HashMap<NodeId, DefId>,
+    /// The definition of each type name, including the names of struct
+    /// literals, struct patterns and impl targets, by the span of the name.
+    pub types: HashMap<Span, DefId>,
+    // the definition each defining name introduces. Every expansion of a
+    // macro repeats the names of its body at their span in the macro, renamed
+    // apart, so the name is part of the key
+    bindings: HashMap<(Span, String), DefId>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Resolution {
+    pub fn definition(&self, id: DefId) -> &Definition {
+        &self.definitions[id.0 as usize]
+    }
+
+    /// The definition an identifier expression refers to.
+    pub fn value(&self, id: NodeId) -> Option<&Definition> {
+        self.values.get(&id).map(|def| self.definition(*def))
+    }
+
+    /// The definition `name` introduces, where `name` is the defining name of
+    /// a binding, parameter or item.
+    pub fn binding(&self, name: &Ident) -> Option<DefId> {
+        self.bindings.get(&(name.span, name.name.clone())).copied()
+    }
+
+    /// The definition of `self` an instance method's receiver introduces.
+    pub fn receiver(&self, receiver: &Receiver) -> Option<DefId> {
+        self.binding(&receiver_name(receiver))
+    }
+}
+
+/// Resolves every identifier of `module` to its definition. Items are visible
+/// throughout the module, locals from their definition to the end of their
+/// block.
+///
+/// Defining a name twice in one scope is an error, and so is a local that
+/// shadows a parameter or local of the same function. Lambdas start afresh,
+/// their parameters and locals may shadow the enclosing ones.
+pub fn resolve_names(module: &Module) -> Resolution {
+    let mut resolver = Resolver {
+        scopes: Vec::new(),
+        resolution: Resolution::default(),
+    };
+    resolver.push(true);
+    for name in BUILTINS {
+        let ident = Ident {
+            name: name.to_string(),
+            span: Span::default(),
+        };
+        resolver.define(&ident, DefKind::Builtin);
+    }
+    resolver.visit_module(module);
+    resolver.resolution
+}
+
+// the builtins and the module items, the scopes a macro body's names are
+// resolved in
+const MODULE_SCOPES: usize = 2;
+
+struct Scope {
+    names: Vec<(String, DefId)>,
+    // a function, lambda or the module begins here, shadowing stops at it
+    boundary: bool,
+}
+
+struct Resolver {
+    scopes: Vec<Scope>,
+    resolution: Resolution,
+}
+
+impl Resolver {
+    fn push(&mut self, boundary: bool) {
+        self.scopes.push(Scope {
+            names: Vec::new(),
+            boundary,
+        });
+    }
+
+    fn pop(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn define(&mut self, ident: &Ident, kind: DefKind) -> DefId {
+        self.define_mut(ident, kind, false)
+    }
+
+    fn define_mut(&mut self, ident: &Ident, kind: DefKind, mutable: bool) -> DefId {
+        let id = DefId(self.resolution.definitions.len() as u32);
+        let name = &ident.name;
+        if let Some(previous) = self.in_scope(name) {
+            let span = self.resolution.definition(previous).span;
+            self.duplicate(ident, span);
+        } else if let Some(shadowed) = self.shadowed(name).filter(|_| kind.is_local()) {
+            let span = self.resolution.definition(shadowed).span;
+            self.resolution.diagnostics.push(
+                Diagnostic::error(
+                    format!(
+                        "`{}` shadows a binding of an enclosing scope",
+                        ident.source_name()
+                    ),
+                    ident.span,
+                )
+                .with_note(
+                    format!("`{}` is first bound here", ident.source_name()),
+                    span,
+                ),
+            );
+        }
+        self.resolution.definitions.push(Definition {
+            name: name.clone(),
+            kind,
+            span: ident.span,
+            mutable,
+        });
+        self.resolution
+            .bindings
+            .insert((ident.span, name.clone()), id);
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.push((name.clone(), id));
+        }
+        id
+    }
+
+    fn duplicate(&mut self, ident: &Ident, previous: Span) {
+        self.resolution.diagnostics.push(
+            Diagnostic::error(
+                format!(
+                    "the name `{}` is defined multiple times",
+                    ident.source_name()
+                ),
+                ident.span,
+            )
+            .with_note(
+                format!("previous definition of `{}` here", ident.source_name()),
+                previous,
+            ),
+        );
+    }
+
+    // members are not in scope anywhere, they only have to be distinct
+    fn distinct<'a>(&mut self, names: impl Iterator<Item = &'a Ident>) {
+        let mut seen: HashMap<&str, Span> = HashMap::new();
+        for name in names {
+            match seen.get(name.name.as_str()) {
+                Some(previous) => self.duplicate(name, *previous),
+                None => {
+                    seen.insert(&name.name, name.span);
+                }
+            }
+        }
+    }
+
+    fn in_scope(&self, name: &str) -> Option<DefId> {
+        let scope = self.scopes.last()?;
+        scope
+            .names
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, id)| *id)
+    }
+
+    // a local of the enclosing scopes of the same function
+    fn shadowed(&self, name: &str) -> Option<DefId> {
+        let (current, outer) = self.scopes.split_last()?;
+        if current.boundary {
+            return None;
+        }
+        for scope in outer.iter().rev() {
+            let found = scope.names.iter().rev().find(|(n, _)| n == name);
+            if let Some((_, id)) = found {
+                if self.resolution.definition(*id).kind.is_local() {
+                    return Some(*id);
+                }
+            }
+            if scope.boundary {
+                break;
+            }
+        }
+        None
+    }
+
+    fn lookup(&self, name: &str, wanted: impl Fn(DefKind) -> bool) -> Option<DefId> {
+        self.lookup_in(&self.scopes, name, wanted)
+    }
+
+    fn lookup_in(
+        &self,
+        scopes: &[Scope],
+        name: &str,
+        wanted: impl Fn(DefKind) -> bool,
+    ) -> Option<DefId> {
+        scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.names.iter().rev())
+            .find(|(n, id)| n == name && wanted(self.resolution.definition(*id).kind))
+            .map(|(_, id)| *id)
+    }
+
+    // a name a macro body uses skips the locals of the call site
+    fn lookup_value(&self, ident: &Ident) -> Option<DefId> {
+        match ident.module_level_name() {
+            Some(name) => self.lookup_in(&self.scopes[..MODULE_SCOPES], name, |_| true),
+            None => self.lookup(&ident.name, |_| true),
+        }
+    }
+
+    fn resolve_value(&mut self, id: NodeId, ident: &Ident) {
+        match self.lookup_value(ident) {
+            Some(def) => {
+                self.resolution.values.insert(id, def);
+            }
+            None => self.resolution.diagnostics.push(Diagnostic::error(
+                format!("cannot find `{}` in this scope", ident.source_name()),
+                ident.span,
+            )),
+        }
+    }
+
+    fn resolve_type(&mut self, ident: &Ident) {
+        match self.lookup(&ident.name, |kind| kind.is_type()) {
+            Some(def) => {
+                self.resolution.types.insert(ident.span, def);
+            }
+            None => self.resolution.diagnostics.push(Diagnostic::error(
+                format!("cannot find type `{}` in this scope", ident.source_name()),
+                ident.span,
+            )),
+        }
+    }
+
+    fn generics(&mut self, params: &[GenericParam]) {
+        for param in params {
+            self.define(&param.name, DefKind::Generic);
+        }
+        for param in params {
+            for bound in &param.bounds {
+                self.visit_type(bound);
+            }
+        }
+    }
+
+    fn pattern_bindings(&mut self, pattern: &Pattern) {
+        for ident in pattern.bindings() {
+            self.define(ident, DefKind::Binding);
+        }
+        self.visit_pattern(pattern);
+    }
+}
+
+impl Visitor for Resolver {
+    fn visit_module(&mut self, module: &Module) {
+        self.push(true);
+        for item in &module.items {
+            match &item.kind {
+                ItemKind::Function(decl) => {
+                    self.define(&decl.name, DefKind::Function);
+                }
+                ItemKind::Struct(decl) => {
+                    self.define(&decl.name, DefKind::Struct);
+                }
+                ItemKind::TypeAlias(alias) => {
+                    self.define(&alias.name, DefKind::TypeAlias);
+                }
+                ItemKind::Use(decl) => match &decl.names {
+                    Some(names) => {
+                        for name in names {
+                            self.define(name, DefKind::Import);
+                        }
+                    }
+                    None => {
+                        if let Some(last) = decl.path.last() {
+                            self.define(last, DefKind::Module);
+                        }
+                    }
+                },
+                ItemKind::Impl(_) | ItemKind::Macro(_) => {}
+            }
+        }
+        visit::walk_module(self, module);
+        self.pop();
+    }
+
+    fn visit_function(&mut self, decl: &FunctionDecl) {
+        self.push(true);
+        self.generics(&decl.generics);
+        if let Some(receiver) = &decl.receiver {
+            self.define(&receiver_name(receiver), DefKind::Receiver);
+        }
+        for param in &decl.params {
+            self.visit_type(&param.ty);
+            self.define_mut(&param.name, DefKind::Param, param.mutable);
+        }
+        if let Some(ty) = &decl.return_type {
+            self.visit_type(ty);
+        }
+        self.visit_block(&decl.body);
+        self.pop();
+    }
+
+    fn visit_struct(&mut self, decl: &StructDecl) {
+        self.push(true);
+        self.generics(&decl.generics);
+        self.distinct(decl.fields.iter().map(|field| &field.name));
+        for field in &decl.fields {
+            self.visit_type(&field.ty);
+            if let Some(default) = &field.default {
+                self.visit_expr(default);
+            }
+        }
+        self.pop();
+    }
+
+    fn visit_impl(&mut self, block: &ImplBlock) {
+        self.resolve_type(&block.target);
+        self.distinct(block.methods.iter().map(|method| &method.function.name));
+        for method in &block.methods {
+            self.visit_function(&method.function);
+        }
+    }
+
+    fn visit_type_alias(&mut self, alias: &TypeAlias) {
+        self.push(true);
+        self.generics(&alias.generics);
+        self.visit_type(&alias.ty);
+        self.pop();
+    }
+
+    fn visit_use(&mut self, _decl: &UseDecl) {}
+
+    fn visit_type(&mut self, ty: &TypeExpr) {
+        match &ty.kind {
+            TypeKind::Named(name) => self.resolve_type(name),
+            TypeKind::Generic { name, args } => {
+                self.resolve_type(name);
+                for arg in args {
+                    self.visit_type(arg);
+                }
+            }
+            TypeKind::Function { params, ret } => {
+                for param in params {
+                    self.visit_type(&param.ty);
+                }
+                self.visit_type(ret);
+            }
+            _ => visit::walk_type(self, ty),
+        }
+    }
+
+    fn visit_block(&mut self, block: &Block) {
+        self.push(false);
+        visit::walk_block(self, block);
+        self.pop();
+    }
+
+    fn visit_let(&mut self, binding: &LetStmt) {
+        if let Some(ty) = &binding.ty {
+            self.visit_type(ty);
+        }
+        self.visit_expr(&binding.value);
+        self.define_mut(&binding.name, DefKind::Local, binding.mutable);
+    }
+
+    fn visit_match_arm(&mut self, arm: &MatchArm) {
+        self.push(false);
+        self.pattern_bindings(&arm.pattern);
+        if let Some(guard) = &arm.guard {
+            self.visit_expr(guard);
+        }
+        self.visit_expr(&arm.body);
+        self.pop();
+    }
+
+    fn visit_pattern(&mut self, pattern: &Pattern) {
+        if let PatternKind::Struct { name, .. } = &pattern.kind {
+            self.resolve_type(name);
+        }
+        visit::walk_pattern(self, pattern);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::Ident(ident) => self.resolve_value(expr.id, ident),
+            ExprKind::Assign { target, op, value } => {
+                self.visit_expr(value);
+                match &target.kind {
+                    // `x += v` reads `x` first
+                    ExprKind::Ident(ident) if op.is_some() => self.resolve_value(target.id, ident),
+                    // only locals can be assigned, assigning to any other name
+                    // introduces a local of that name in the current scope.
+                    // Later passes go by the definition recorded here
+                    ExprKind::Ident(ident) => {
+                        let def = match self.lookup(&ident.name, |kind| kind.is_local()) {
+                            Some(def) => def,
+                            None => self.define(ident, DefKind::Assigned),
+                        };
+                        self.resolution.values.insert(target.id, def);
+                    }
+                    _ => self.visit_expr(target),
+                }
+            }
+            ExprKind::StructLit { name, fields } => {
+                self.resolve_type(name);
+                for field in fields {
+                    self.visit_expr(&field.value);
+                }
+            }
+            ExprKind::Lambda(lambda) => {
+                self.push(true);
+                for param in &lambda.params {
+                    if let Some(ty) = &param.ty {
+                        self.visit_type(ty);
+                    }
+                    self.define(&param.name, DefKind::LambdaParam);
+                }
+                if let Some(ty) = &lambda.return_type {
+                    self.visit_type(ty);
+                }
+                self.visit_block(&lambda.body);
+                self.pop();
+            }
+            ExprKind::For(for_expr) => {
+                self.visit_expr(&for_expr.iterable);
+                self.push(false);
+                self.pattern_bindings(&for_expr.pattern);
+                self.visit_block(&for_expr.body);
+                self.pop();
+            }
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+}
+
+fn receiver_name(receiver: &Receiver) -> Ident {
+    Ident {
+        name: "self".to_string(),
+        span: receiver.span,
+    }
+}
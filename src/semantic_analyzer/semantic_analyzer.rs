@@ -0,0 +1,17 @@
+use crate::diagnostics::diagnostic::Diagnostic;
+use crate::parser::ast::Module;
+use crate::semantic_analyzer::control_flow::check_control_flow;
+use crate::semantic_analyzer::mutability::check_mutability;
+use crate::semantic_analyzer::type_checker::check_module;
+
+/// Runs every check on a parsed and macro expanded module, see `expand`, and
+/// returns what they report in source order.
+pub fn analyze(module: &Module) -> Vec<Diagnostic> {
+    let mut types = check_module(module);
+    let mut diagnostics = std::mem::take(&mut types.resolution.diagnostics);
+    diagnostics.extend(check_control_flow(module));
+    diagnostics.extend(check_mutability(module, &types));
+    diagnostics.extend(types.diagnostics);
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
@@ -0,0 +1,1803 @@
+use crate::diagnostics::diagnostic::{plural, Diagnostic};
+use crate::lexer::token::Span;
+use crate::parser::ast::*;
+use crate::parser::desugar::desugar;
+use crate::parser::visit::{self, Visitor};
+use crate::semantic_analyzer::resolve::{resolve_names, DefId, DefKind, Resolution};
+use crate::semantic_analyzer::types::{integer_range, Type, TypeVar, VarKind};
+use std::collections::{HashMap, HashSet};
+
+/// The type of every checked expression along with the problems found.
+#[derive(Debug, Default)]
+pub struct TypeckResults {
+    pub types: HashMap<NodeId, Type>,
+    /// What each resolved method call expression calls.
+    pub methods: HashMap<NodeId, MethodTarget>,
+    /// The names of the checked module resolved, pipelines desugared.
+    pub resolution: Resolution,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// The function behind `receiver.name(args)`. Methods from a `defimpl` win,
+/// otherwise a free function whose first parameter takes the receiver is
+/// called with it, so `xs.len()` is `len(xs)`.
+#[derive(PartialEq, Debug, Clone)]
+pub enum MethodTarget {
+    Method { ty: String, name: String },
+    Function(String),
+}
+
+// a `defimpl` method, its type leaves out `self`
+struct MethodSig {
+    has_receiver: bool,
+    ty: Type,
+}
+
+/// Gives every expression a type and checks operands, call arguments,
+/// returns, assignments and annotated bindings against what they need.
+/// A compound assignment `x += v` applies its operator like `x + v` and
+/// assigns the result back to `x`.
+///
+/// Unannotated bindings and lambda parameters are inferred within the item
+/// they appear in. Number literals nothing constrains are `i32` or `double`,
+/// any other type that stays open is an error asking for an annotation.
+/// Integer literals have to fit the type they end up with.
+pub fn check_module(module: &Module) -> TypeckResults {
+    let module = &desugar(module.clone());
+    let resolution = resolve_names(module);
+    let mut checker = TypeChecker {
+        structs: HashMap::new(),
+        aliases: HashMap::new(),
+        functions: HashMap::new(),
+        methods: HashMap::new(),
+        resolution: &resolution,
+        locals: HashMap::new(),
+        return_types: Vec::new(),
+        loops: Vec::new(),
+        narrowed: Vec::new(),
+        vars: Vec::new(),
+        int_literals: Vec::new(),
+        results: TypeckResults::default(),
+    };
+    checker.collect(module);
+    for item in &module.items {
+        let first_var = checker.vars.len();
+        checker.item(item);
+        checker.finish(first_var);
+    }
+    let types = std::mem::take(&mut checker.results.types);
+    checker.results.types = types
+        .into_iter()
+        .map(|(id, ty)| (id, checker.zonk(&ty)))
+        .collect();
+    let results = checker.results;
+    TypeckResults {
+        resolution,
+        ..results
+    }
+}
+
+struct TypeChecker<'a> {
+    structs: HashMap<String, &'a StructDecl>,
+    aliases: HashMap<String, &'a TypeExpr>,
+    functions: HashMap<String, Type>,
+    // keyed by struct and method name
+    methods: HashMap<(String, String), MethodSig>,
+    resolution: &'a Resolution,
+    // the declared type of every local checked so far
+    locals: HashMap<DefId, Type>,
+    return_types: Vec<Option<Type>>,
+    loops: Vec<LoopContext>,
+    // what the control flow so far proves about variables, one frame per
+    // block or branch entered
+    narrowed: Vec<Narrowings>,
+    // indexed by `TypeVar::id`
+    vars: Vec<VarInfo>,
+    // checked against their type once inference has settled it
+    int_literals: Vec<IntLiteral>,
+    results: TypeckResults,
+}
+
+struct IntLiteral {
+    // with a leading `-` when negated
+    text: String,
+    ty: Type,
+    span: Span,
+}
+
+struct VarInfo {
+    kind: VarKind,
+    value: Option<Type>,
+    // what to name when nothing pins the variable down
+    origin: Option<(String, Span)>,
+}
+
+/// Variables known not to be `null`, with their non-null type.
+type Narrowings = HashMap<DefId, Type>;
+
+struct LoopContext {
+    label: Option<String>,
+    // type handed to `break`, unset until the first `break` is checked
+    break_type: Option<Type>,
+}
+
+impl<'a> TypeChecker<'a> {
+    fn error(&mut self, message: impl Into<String>, span: Span) {
+        self.results
+            .diagnostics
+            .push(Diagnostic::error(message, span));
+    }
+
+    // reports `found` where a value of type `expected` is needed
+    fn expect(&mut self, expected: &Type, found: &Type, span: Span) {
+        if !self.accepts(expected, found) {
+            let (expected, found) = (self.shown(expected), self.shown(found));
+            let mut diagnostic =
+                Diagnostic::error(format!("expected `{}`, found `{}`", expected, found), span);
+            // numbers only narrow, or turn into the other kind of number, with `as`
+            if let (Type::Primitive(to), true) = (&expected, is_number(&found)) {
+                let note = match integer_range(*to) {
+                    Some((min, max)) => Some(format!(
+                        "`{}` holds `{}..={}`, convert with `as {}` if the value fits",
+                        to, min, max, to
+                    )),
+                    None if is_number(&expected) => Some(format!("convert with `as {}`", to)),
+                    None => None,
+                };
+                if let Some(note) = note {
+                    diagnostic = diagnostic.with_note(note, span);
+                }
+            }
+            self.results.diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Whether a `found` value fits where `expected` is needed, binding the
+    /// variables on either side that make it fit.
+    fn accepts(&mut self, expected: &Type, found: &Type) -> bool {
+        self.unify(expected, found);
+        self.resolve(expected).accepts(&self.resolve(found))
+    }
+
+    /// A number literal takes the numeric type `expected`, without one it is
+    /// pinned down by how it is used and falls back to `i32` or `double`.
+    fn literal(&mut self, literal: &Literal, expected: Option<&Type>) -> Type {
+        let expected = expected.map(|ty| self.resolve(ty));
+        if let (Literal::Null, Some(ty)) = (literal, &expected) {
+            if ty.is_nullable() {
+                return ty.clone();
+            }
+        }
+        let expected = expected.map(|ty| ty.non_null());
+        match literal {
+            Literal::Int(_) => match expected {
+                Some(ty) if ty.is_integer() => ty,
+                _ => self.fresh(VarKind::Integer, None),
+            },
+            Literal::Float(_) => match expected {
+                Some(ty) if ty.is_float() => ty,
+                _ => self.fresh(VarKind::Float, None),
+            },
+            Literal::Str(_) => Type::Primitive(PrimitiveType::String),
+            Literal::Char(_) => Type::Primitive(PrimitiveType::Char),
+            Literal::Bool(_) => Type::Primitive(PrimitiveType::Bool),
+            Literal::Null => Type::Null,
+            Literal::Regex(_) => Type::Unknown,
+        }
+    }
+
+    fn fresh(&mut self, kind: VarKind, origin: Option<(String, Span)>) -> Type {
+        let id = self.vars.len() as u32;
+        self.vars.push(VarInfo {
+            kind,
+            value: None,
+            origin,
+        });
+        Type::Var(TypeVar { id, kind })
+    }
+
+    /// `ty` with every bound variable replaced by what it is bound to.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match &self.vars[var.id as usize].value {
+                Some(value) => self.resolve(value),
+                None => ty.clone(),
+            },
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|param| self.resolve(param)).collect(),
+                Box::new(self.resolve(ret)),
+            ),
+            Type::List(element) => Type::List(Box::new(self.resolve(element))),
+            Type::Map(key, value) => {
+                Type::Map(Box::new(self.resolve(key)), Box::new(self.resolve(value)))
+            }
+            Type::Tuple(elements) => {
+                Type::Tuple(elements.iter().map(|e| self.resolve(e)).collect())
+            }
+            Type::Nullable(inner) => self.resolve(inner).nullable(),
+            other => other.clone(),
+        }
+    }
+
+    // `ty` as a diagnostic shows it, literals not pinned down yet have their
+    // default type
+    fn shown(&self, ty: &Type) -> Type {
+        self.replace_vars(&self.resolve(ty), &|var| match var.kind {
+            VarKind::General => Type::Var(var),
+            kind => default_type(kind),
+        })
+    }
+
+    // `ty` once inference is over, variables nothing pinned down are unknown
+    fn zonk(&self, ty: &Type) -> Type {
+        self.replace_vars(&self.resolve(ty), &|_| Type::Unknown)
+    }
+
+    fn replace_vars(&self, ty: &Type, with: &dyn Fn(TypeVar) -> Type) -> Type {
+        match ty {
+            Type::Var(var) => with(*var),
+            Type::Function(params, ret) => Type::Function(
+                params.iter().map(|p| self.replace_vars(p, with)).collect(),
+                Box::new(self.replace_vars(ret, with)),
+            ),
+            Type::List(element) => Type::List(Box::new(self.replace_vars(element, with))),
+            Type::Map(key, value) => Type::Map(
+                Box::new(self.replace_vars(key, with)),
+                Box::new(self.replace_vars(value, with)),
+            ),
+            Type::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|e| self.replace_vars(e, with))
+                    .collect(),
+            ),
+            Type::Nullable(inner) => self.replace_vars(inner, with).nullable(),
+            other => other.clone(),
+        }
+    }
+
+    // an unknown type, or a variable that could still be anything
+    fn is_open(&self, ty: &Type) -> bool {
+        match self.resolve(ty) {
+            Type::Unknown => true,
+            Type::Var(var) => var.kind == VarKind::General,
+            _ => false,
+        }
+    }
+
+    fn bind(&mut self, var: TypeVar, ty: Type) {
+        self.vars[var.id as usize].value = Some(ty);
+    }
+
+    /// Binds variables so that `found` fits `expected` where the two differ
+    /// only in them. Whatever still does not fit is left for `accepts` to report.
+    fn unify(&mut self, expected: &Type, found: &Type) {
+        let expected = self.resolve(expected);
+        let found = self.resolve(found);
+        match (&expected, &found) {
+            (Type::Var(a), Type::Var(b)) if a == b => {}
+            // the more specific of the two survives
+            (Type::Var(a), Type::Var(b)) => {
+                if a.kind == VarKind::General {
+                    self.bind(*a, found.clone());
+                } else if b.kind == VarKind::General || a.kind == b.kind {
+                    self.bind(*b, expected.clone());
+                }
+            }
+            (Type::Var(var), other) | (other, Type::Var(var))
+                if var.admits(other) && *other != Type::Never && !occurs(*var, other) =>
+            {
+                self.bind(*var, other.clone());
+            }
+            (Type::Var(_), _) | (_, Type::Var(_)) => {}
+            (Type::Function(params, ret), Type::Function(other_params, other_ret))
+                if params.len() == other_params.len() =>
+            {
+                for (param, other) in params.iter().zip(other_params) {
+                    self.unify(param, other);
+                }
+                self.unify(ret, other_ret);
+            }
+            (Type::List(element), Type::List(other)) => self.unify(element, other),
+            (Type::Map(key, value), Type::Map(other_key, other_value)) => {
+                self.unify(key, other_key);
+                self.unify(value, other_value);
+            }
+            (Type::Tuple(elements), Type::Tuple(others)) if elements.len() == others.len() => {
+                for (element, other) in elements.iter().zip(others) {
+                    self.unify(element, other);
+                }
+            }
+            (Type::Nullable(inner), other) if *other != Type::Null => {
+                self.unify(inner, &other.non_null())
+            }
+            _ => {}
+        }
+    }
+
+    /// Gives the literals created since `first_var` that nothing pinned down
+    /// their default type, and asks for annotations where a variable could
+    /// still be anything.
+    fn finish(&mut self, first_var: usize) {
+        for id in first_var..self.vars.len() {
+            let var = TypeVar {
+                id: id as u32,
+                kind: self.vars[id].kind,
+            };
+            if self.vars[id].value.is_none() && var.kind != VarKind::General {
+                self.bind(var, default_type(var.kind));
+            }
+        }
+        for literal in std::mem::take(&mut self.int_literals) {
+            self.check_range(&literal);
+        }
+        let mut reported = HashSet::new();
+        for id in first_var..self.vars.len() {
+            let Some((what, span)) = self.vars[id].origin.clone() else {
+                continue;
+            };
+            let var = TypeVar {
+                id: id as u32,
+                kind: VarKind::General,
+            };
+            // report each variable once, under the first name it goes by, and each
+            // place once
+            if let Type::Var(root) = self.resolve(&Type::Var(var)) {
+                if reported.insert(span) {
+                    self.error(format!("type annotations needed for {}", what), span);
+                }
+                self.bind(root, Type::Unknown);
+            }
+        }
+    }
+
+    fn collect(&mut self, module: &'a Module) {
+        for item in &module.items {
+            match &item.kind {
+                ItemKind::Struct(decl) => {
+                    self.structs.insert(decl.name.name.clone(), decl);
+                }
+                ItemKind::TypeAlias(alias) => {
+                    self.aliases.insert(alias.name.name.clone(), &alias.ty);
+                }
+                _ => {}
+            }
+        }
+        for item in &module.items {
+            match &item.kind {
+                ItemKind::Function(decl) => {
+                    let signature = self.signature(decl);
+                    self.functions.insert(decl.name.name.clone(), signature);
+                }
+                ItemKind::Impl(block) => {
+                    for method in &block.methods {
+                        let decl = &method.function;
+                        let key = (block.target.name.clone(), decl.name.name.clone());
+                        let signature = MethodSig {
+                            has_receiver: decl.receiver.is_some(),
+                            ty: self.signature(decl),
+                        };
+                        self.methods.insert(key, signature);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn signature(&self, decl: &FunctionDecl) -> Type {
+        let params = decl.params.iter().map(|p| self.lower(&p.ty)).collect();
+        let ret = match &decl.return_type {
+            Some(ty) => self.lower(ty),
+            None => Type::Unit,
+        };
+        Type::Function(params, Box::new(ret))
+    }
+
+    fn lower(&self, ty: &TypeExpr) -> Type {
+        self.lower_with_depth(ty, 0)
+    }
+
+    // the depth guards against aliases that refer to themselves
+    fn lower_with_depth(&self, ty: &TypeExpr, depth: usize) -> Type {
+        match &ty.kind {
+            TypeKind::Primitive(prim) => Type::Primitive(*prim),
+            TypeKind::Named(ident) => {
+                if self.structs.contains_key(&ident.name) {
+                    Type::Struct(ident.name.clone())
+                } else if let Some(target) = self.aliases.get(&ident.name) {
+                    if depth > 32 {
+                        return Type::Unknown;
+                    }
+                    self.lower_with_depth(target, depth + 1)
+                } else {
+                    Type::Unknown
+                }
+            }
+            TypeKind::Function { params, ret } => Type::Function(
+                params
+                    .iter()
+                    .map(|p| self.lower_with_depth(&p.ty, depth))
+                    .collect(),
+                Box::new(self.lower_with_depth(ret, depth)),
+            ),
+            TypeKind::List(element) => Type::List(Box::new(self.lower_with_depth(element, depth))),
+            TypeKind::Nullable(inner) => self.lower_with_depth(inner, depth).nullable(),
+            TypeKind::Map { key, value } => Type::Map(
+                Box::new(self.lower_with_depth(key, depth)),
+                Box::new(self.lower_with_depth(value, depth)),
+            ),
+            TypeKind::Tuple(elements) if elements.is_empty() => Type::Unit,
+            TypeKind::Tuple(elements) => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|e| self.lower_with_depth(e, depth))
+                    .collect(),
+            ),
+            TypeKind::Generic { .. } | TypeKind::Missing => Type::Unknown,
+        }
+    }
+
+    // the local introduced by the defining name `name`
+    fn declare(&mut self, name: &Ident, ty: Type) {
+        if let Some(def) = self.resolution.binding(name) {
+            self.locals.insert(def, ty);
+        }
+    }
+
+    /// The type of the identifier expression `id` where it is read, narrowed
+    /// by the control flow.
+    fn lookup(&self, id: NodeId) -> Option<Type> {
+        let def = *self.resolution.values.get(&id)?;
+        let definition = self.resolution.definition(def);
+        if !definition.kind.is_local() {
+            return self.functions.get(&definition.name).cloned();
+        }
+        let narrowed = self.narrowed.iter().rev().find_map(|frame| frame.get(&def));
+        narrowed.or_else(|| self.locals.get(&def)).cloned()
+    }
+
+    // the local the identifier expression `id` refers to, with its declared type
+    fn local(&self, id: NodeId) -> Option<(DefId, Type)> {
+        let def = *self.resolution.values.get(&id)?;
+        self.locals.get(&def).map(|ty| (def, ty.clone()))
+    }
+
+    // nothing is known about `def` once it is assigned
+    fn forget(&mut self, def: DefId) {
+        for frame in &mut self.narrowed {
+            frame.remove(&def);
+        }
+    }
+
+    fn narrow(&mut self, narrowings: Narrowings) {
+        if let Some(frame) = self.narrowed.last_mut() {
+            frame.extend(narrowings);
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        match &item.kind {
+            ItemKind::Function(decl) => self.function(decl, None),
+            ItemKind::Impl(block) => {
+                let target = match self.structs.contains_key(&block.target.name) {
+                    true => Type::Struct(block.target.name.clone()),
+                    false => Type::Unknown,
+                };
+                for method in &block.methods {
+                    self.function(&method.function, Some(target.clone()));
+                }
+            }
+            ItemKind::Struct(decl) => {
+                for field in &decl.fields {
+                    if let Some(default) = &field.default {
+                        let expected = self.lower(&field.ty);
+                        let ty = self.expr(default, Some(&expected));
+                        self.expect(&expected, &ty, default.span);
+                    }
+                }
+            }
+            // macros are expanded before analysis, see `expand`
+            ItemKind::TypeAlias(_) | ItemKind::Macro(_) | ItemKind::Use(_) => {}
+        }
+    }
+
+    fn function(&mut self, decl: &FunctionDecl, receiver: Option<Type>) {
+        if let (Some(def), Some(ty)) = (
+            decl.receiver
+                .as_ref()
+                .and_then(|r| self.resolution.receiver(r)),
+            receiver,
+        ) {
+            self.locals.insert(def, ty);
+        }
+        for param in &decl.params {
+            let ty = self.lower(&param.ty);
+            self.declare(&param.name, ty);
+        }
+        let ret = decl.return_type.as_ref().map(|ty| self.lower(ty));
+        // a function without `-> T` returns `()`
+        self.return_types
+            .push(Some(ret.clone().unwrap_or(Type::Unit)));
+        let body = self.block(&decl.body, ret.as_ref(), ret.is_some());
+        if let Some(ret) = &ret {
+            self.expect(ret, &body, block_value_span(&decl.body));
+        }
+        self.return_types.pop();
+    }
+
+    /// Checks a block, its type is the type of the trailing expression when `used`.
+    fn block(&mut self, block: &Block, expected: Option<&Type>, used: bool) -> Type {
+        self.narrowed.push(HashMap::new());
+        let mut ty = Type::Unit;
+        for (i, stmt) in block.stmts.iter().enumerate() {
+            let tail = used && i + 1 == block.stmts.len();
+            ty = match &stmt.kind {
+                StmtKind::Let(binding) => {
+                    self.let_stmt(binding);
+                    Type::Unit
+                }
+                StmtKind::Expr(expr) if tail => self.expr(expr, expected),
+                StmtKind::Expr(expr) => {
+                    self.expr_stmt(expr);
+                    Type::Unit
+                }
+            };
+            // past `if x == null do return end` the rest of the block knows
+            // the condition failed
+            if let StmtKind::Expr(Expr {
+                kind: ExprKind::If(if_expr),
+                ..
+            }) = &stmt.kind
+            {
+                if if_expr.else_block.is_none()
+                    && if_expr.branches.iter().all(|b| diverges(&b.block))
+                {
+                    let narrowings = self.failed(if_expr, if_expr.branches.len());
+                    self.narrow(narrowings);
+                }
+            }
+        }
+        self.narrowed.pop();
+        ty
+    }
+
+    fn let_stmt(&mut self, binding: &LetStmt) {
+        let annotated = binding.ty.as_ref().map(|ty| self.lower(ty));
+        let value = self.expr(&binding.value, annotated.as_ref());
+        if let Some(annotated) = &annotated {
+            self.expect(annotated, &value, binding.value.span);
+        }
+        self.declare(&binding.name, annotated.unwrap_or(value));
+    }
+
+    // expressions whose value is discarded
+    fn expr_stmt(&mut self, expr: &Expr) {
+        match &expr.kind {
+            ExprKind::If(if_expr) => {
+                let ty = self.if_expr(if_expr, expr.span, None, false);
+                self.results.types.insert(expr.id, ty);
+            }
+            ExprKind::Match(match_expr) => {
+                let ty = self.match_expr(match_expr, None, false);
+                self.results.types.insert(expr.id, ty);
+            }
+            ExprKind::Block(block) => {
+                let ty = self.block(block, None, false);
+                self.results.types.insert(expr.id, ty);
+            }
+            _ => {
+                self.expr(expr, None);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, expected: Option<&Type>) -> Type {
+        let expected = expected.map(|ty| self.resolve(ty));
+        let ty = self.expr_kind(expr, expected.as_ref());
+        let ty = self.resolve(&ty);
+        self.results.types.insert(expr.id, ty.clone());
+        ty
+    }
+
+    fn expr_kind(&mut self, expr: &Expr, expected: Option<&Type>) -> Type {
+        match &expr.kind {
+            ExprKind::Literal(literal) => {
+                let ty = self.literal(literal, expected);
+                if let Literal::Int(text) = literal {
+                    self.int_literals.push(IntLiteral {
+                        text: text.clone(),
+                        ty: ty.clone(),
+                        span: expr.span,
+                    });
+                }
+                ty
+            }
+            ExprKind::Ident(_) => self.lookup(expr.id).unwrap_or(Type::Unknown),
+            ExprKind::Unary { op, operand } => match op {
+                UnaryOp::Neg => {
+                    let ty = self.expr(operand, expected);
+                    if let ExprKind::Literal(Literal::Int(_)) = operand.kind {
+                        if let Some(literal) = self.int_literals.last_mut() {
+                            literal.text.insert(0, '-');
+                            literal.span = expr.span;
+                        }
+                    }
+                    let ty = self.dereference(&ty, "negate", None, operand.span);
+                    if !self.is_open(&ty) && !ty.is_integer() && !ty.is_float() {
+                        self.error(format!("`-` cannot be applied to `{}`", ty), operand.span);
+                    }
+                    ty
+                }
+                UnaryOp::Not => {
+                    let boolean = Type::Primitive(PrimitiveType::Bool);
+                    let ty = self.expr(operand, Some(&boolean));
+                    self.expect(&boolean, &ty, operand.span);
+                    boolean
+                }
+            },
+            ExprKind::Binary { op, lhs, rhs } => match op {
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                    self.operands(*op, lhs, rhs, expected)
+                }
+                BinaryOp::Lt | BinaryOp::Gt | BinaryOp::LtEq | BinaryOp::GtEq => {
+                    self.operands(*op, lhs, rhs, None);
+                    Type::Primitive(PrimitiveType::Bool)
+                }
+                BinaryOp::Elvis => self.elvis(lhs, rhs, expected),
+                // already turned into a call by `desugar`
+                BinaryOp::Pipe => {
+                    self.expr(lhs, None);
+                    self.expr(rhs, None);
+                    Type::Unknown
+                }
+                // `x != null && x.y` may dereference `x` on the right
+                BinaryOp::And => {
+                    let boolean = Type::Primitive(PrimitiveType::Bool);
+                    let ty = self.expr(lhs, Some(&boolean));
+                    self.expect(&boolean, &ty, lhs.span);
+                    self.narrowed.push(self.narrowings(lhs, true));
+                    let ty = self.expr(rhs, Some(&boolean));
+                    self.expect(&boolean, &ty, rhs.span);
+                    self.narrowed.pop();
+                    boolean
+                }
+                // `x == null || x.y` only gets to the right with a non-null `x`
+                BinaryOp::Or => {
+                    let boolean = Type::Primitive(PrimitiveType::Bool);
+                    let ty = self.expr(lhs, Some(&boolean));
+                    self.expect(&boolean, &ty, lhs.span);
+                    self.narrowed.push(self.narrowings(lhs, false));
+                    let ty = self.expr(rhs, Some(&boolean));
+                    self.expect(&boolean, &ty, rhs.span);
+                    self.narrowed.pop();
+                    boolean
+                }
+                BinaryOp::Eq | BinaryOp::NotEq => {
+                    let (lhs_ty, rhs_ty) = self.operand_types(lhs, rhs, None);
+                    if !self.accepts(&lhs_ty, &rhs_ty) && !self.accepts(&rhs_ty, &lhs_ty) {
+                        let (lhs_ty, rhs_ty) = (self.shown(&lhs_ty), self.shown(&rhs_ty));
+                        self.error(
+                            format!("cannot compare `{}` with `{}`", lhs_ty, rhs_ty),
+                            expr.span,
+                        );
+                    }
+                    Type::Primitive(PrimitiveType::Bool)
+                }
+            },
+            ExprKind::Assign {
+                target,
+                op: Some(op),
+                value,
+            } => {
+                let ty = match &target.kind {
+                    ExprKind::Ident(_) => {
+                        let ty = self.lookup(target.id).unwrap_or(Type::Unknown);
+                        if let Some((def, declared)) = self.local(target.id) {
+                            self.results.types.insert(target.id, declared);
+                            self.forget(def);
+                        }
+                        ty
+                    }
+                    _ => self.expr(target, None),
+                };
+                let value_ty = self.expr(value, Some(&ty));
+                let result = self.apply(*op, (&ty, target.span), (&value_ty, value.span));
+                if !self.is_open(&ty) && !self.accepts(&ty, &result) {
+                    let (ty, result) = (self.shown(&ty), self.shown(&result));
+                    self.error(format!("expected `{}`, found `{}`", ty, result), expr.span);
+                }
+                Type::Unit
+            }
+            ExprKind::Assign { target, value, .. } => {
+                match &target.kind {
+                    ExprKind::Ident(ident) => match self.local(target.id) {
+                        Some((def, ty)) => {
+                            let value_ty = self.expr(value, Some(&ty));
+                            self.expect(&ty, &value_ty, value.span);
+                            self.results.types.insert(target.id, ty.clone());
+                            self.forget(def);
+                            let ty = self.resolve(&ty);
+                            if ty.is_nullable() && !value_ty.is_nullable() && value_ty != Type::Null
+                            {
+                                self.narrow(HashMap::from([(def, ty.non_null())]));
+                            }
+                        }
+                        // the assignment defining the name gives it the value's type
+                        None => {
+                            let ty = self.expr(value, None);
+                            self.results.types.insert(target.id, ty.clone());
+                            self.declare(ident, ty);
+                        }
+                    },
+                    _ => {
+                        let ty = self.expr(target, None);
+                        let value_ty = self.expr(value, Some(&ty));
+                        self.expect(&ty, &value_ty, value.span);
+                    }
+                }
+                Type::Unit
+            }
+            ExprKind::Call { callee, args } => {
+                let ty = self.callee(callee);
+                let name = match &callee.kind {
+                    ExprKind::Ident(ident) => Some(ident.source_name()),
+                    _ => None,
+                };
+                self.call_args(ty, name, callee.span, args)
+            }
+            ExprKind::Field { base, name, safe } => {
+                let base_ty = self.expr(base, None);
+                let action = format!("access field `{}` on", name.name);
+                let ty = match self.dereference(&base_ty, &action, Some(*safe), base.span) {
+                    Type::Struct(struct_name) => {
+                        let field = self
+                            .structs
+                            .get(&struct_name)
+                            .and_then(|decl| decl.fields.iter().find(|f| f.name.name == name.name));
+                        match field {
+                            Some(field) => self.lower(&field.ty),
+                            None => {
+                                self.error(
+                                    format!(
+                                        "struct `{}` has no field `{}`",
+                                        struct_name, name.name
+                                    ),
+                                    name.span,
+                                );
+                                Type::Unknown
+                            }
+                        }
+                    }
+                    _ => Type::Unknown,
+                };
+                match *safe && base_ty.is_nullable() {
+                    true => ty.nullable(),
+                    false => ty,
+                }
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+                safe,
+            } => self.method_call(expr.id, receiver, method, args, *safe),
+            ExprKind::StructLit { name, fields } => {
+                let decl = self.structs.get(&name.name).copied();
+                for field in fields {
+                    let expected = decl
+                        .and_then(|decl| {
+                            decl.fields.iter().find(|f| f.name.name == field.name.name)
+                        })
+                        .map(|f| self.lower(&f.ty));
+                    let ty = self.expr(&field.value, expected.as_ref());
+                    if let Some(expected) = &expected {
+                        self.expect(expected, &ty, field.value.span);
+                    }
+                }
+                match decl {
+                    Some(_) => Type::Struct(name.name.clone()),
+                    None => Type::Unknown,
+                }
+            }
+            ExprKind::List(elements) => {
+                let hint = match expected {
+                    Some(Type::List(element)) => Some(element.as_ref().clone()),
+                    _ => None,
+                };
+                let element = match elements.is_empty() && hint.is_none() {
+                    true => self.fresh(
+                        VarKind::General,
+                        Some(("this empty list".to_string(), expr.span)),
+                    ),
+                    false => self.elements("list elements", elements, hint),
+                };
+                Type::List(Box::new(element))
+            }
+            ExprKind::Map(entries) => {
+                let (key_hint, value_hint) = match expected {
+                    Some(Type::Map(key, value)) => {
+                        (Some(key.as_ref().clone()), Some(value.as_ref().clone()))
+                    }
+                    _ => (None, None),
+                };
+                if entries.is_empty() && key_hint.is_none() {
+                    let origin = Some(("this empty map".to_string(), expr.span));
+                    let key = self.fresh(VarKind::General, origin.clone());
+                    let value = self.fresh(VarKind::General, origin);
+                    return Type::Map(Box::new(key), Box::new(value));
+                }
+                let keys: Vec<&Expr> = entries.iter().map(|entry| &entry.key).collect();
+                let values: Vec<&Expr> = entries.iter().map(|entry| &entry.value).collect();
+                let key = self.elements("map keys", keys, key_hint);
+                let value = self.elements("map values", values, value_hint);
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            ExprKind::Tuple(elements) if elements.is_empty() => Type::Unit,
+            ExprKind::Tuple(elements) => {
+                let hints = match expected {
+                    Some(Type::Tuple(hints)) if hints.len() == elements.len() => hints.clone(),
+                    _ => Vec::new(),
+                };
+                let elements = elements
+                    .iter()
+                    .enumerate()
+                    .map(|(i, element)| self.expr(element, hints.get(i)))
+                    .collect();
+                Type::Tuple(elements)
+            }
+            ExprKind::Index { base, index } => self.index(base, index),
+            ExprKind::Cast { expr: value, ty } => self.cast(value, ty),
+            ExprKind::Lambda(lambda) => self.lambda(lambda, expected),
+            ExprKind::If(if_expr) => self.if_expr(if_expr, expr.span, expected, true),
+            ExprKind::Match(match_expr) => self.match_expr(match_expr, expected, true),
+            ExprKind::While(while_expr) => {
+                self.forget_assigned(expr);
+                let boolean = Type::Primitive(PrimitiveType::Bool);
+                let cond = self.expr(&while_expr.condition, Some(&boolean));
+                self.expect(&boolean, &cond, while_expr.condition.span);
+                self.narrowed
+                    .push(self.narrowings(&while_expr.condition, true));
+                let ty = self.loop_body(&while_expr.label, &while_expr.body);
+                self.narrowed.pop();
+                ty
+            }
+            ExprKind::For(for_expr) => {
+                self.forget_assigned(expr);
+                let iterable = self.expr(&for_expr.iterable, None);
+                let item = match self.resolve(&iterable) {
+                    Type::List(element) => *element,
+                    Type::Map(key, value) => Type::Tuple(vec![*key, *value]),
+                    other if self.is_open(&other) || other == Type::Never => Type::Unknown,
+                    other => {
+                        let shown = self.shown(&other);
+                        self.error(
+                            format!("`{}` is not iterable", shown),
+                            for_expr.iterable.span,
+                        );
+                        Type::Unknown
+                    }
+                };
+                self.pattern(&for_expr.pattern, &item);
+                self.loop_body(&for_expr.label, &for_expr.body)
+            }
+            ExprKind::Break { label, value } => {
+                self.break_expr(label, value.as_deref(), expr.span);
+                Type::Never
+            }
+            ExprKind::Continue { .. } => Type::Never,
+            ExprKind::Missing => Type::Unknown,
+            ExprKind::Block(block) => self.block(block, expected, true),
+            ExprKind::Return(value) => {
+                let ret = self.return_types.last().cloned().flatten();
+                match (value, ret) {
+                    (Some(value), Some(ret)) => {
+                        let ty = self.expr(value, Some(&ret));
+                        self.expect(&ret, &ty, value.span);
+                    }
+                    (Some(value), None) => {
+                        self.expr(value, None);
+                    }
+                    (None, Some(ret)) => self.expect(&ret, &Type::Unit, expr.span),
+                    (None, None) => {}
+                }
+                Type::Never
+            }
+        }
+    }
+
+    // the first element fixes the type the rest of them have to agree with
+    fn elements<'e>(
+        &mut self,
+        what: &str,
+        elements: impl IntoIterator<Item = &'e Expr>,
+        hint: Option<Type>,
+    ) -> Type {
+        let mut first = hint;
+        for element in elements {
+            let ty = self.expr(element, first.as_ref());
+            match &first {
+                _ if ty == Type::Never => {}
+                None => first = Some(ty),
+                Some(first) => {
+                    if !self.accepts(first, &ty) {
+                        let (first, ty) = (self.shown(first), self.shown(&ty));
+                        self.error(
+                            format!(
+                                "{} have incompatible types, expected `{}`, found `{}`",
+                                what, first, ty
+                            ),
+                            element.span,
+                        );
+                    }
+                }
+            }
+        }
+        first.map_or(Type::Unknown, |first| self.resolve(&first))
+    }
+
+    /// The type of `base` once it is known not to be `null`. Dereferencing a
+    /// nullable value is an error unless it goes through `?.`, `safe` is `None`
+    /// where there is no `?.` form.
+    fn dereference(&mut self, base: &Type, action: &str, safe: Option<bool>, span: Span) -> Type {
+        if base.is_nullable() && safe != Some(true) {
+            let hint = match safe {
+                Some(_) => ", check it against `null` first or use `?.`",
+                None => ", check it against `null` first",
+            };
+            self.error(
+                format!("cannot {} nullable `{}`{}", action, self.shown(base), hint),
+                span,
+            );
+        }
+        base.non_null()
+    }
+
+    fn method_call(
+        &mut self,
+        id: NodeId,
+        receiver: &Expr,
+        method: &Ident,
+        args: &[Expr],
+        safe: bool,
+    ) -> Type {
+        // `Point.origin()` calls a static method through the type
+        if let ExprKind::Ident(_) = &receiver.kind {
+            // by the definition, a macro body names the type as `Point#0`
+            let type_name = match self.resolution.value(receiver.id) {
+                Some(def) if def.kind == DefKind::Struct => Some(def.name.clone()),
+                _ => None,
+            };
+            if let Some(type_name) = type_name.filter(|name| self.structs.contains_key(name)) {
+                let key = (type_name, method.name.clone());
+                let ty = match self.methods.get(&key) {
+                    Some(sig) if !sig.has_receiver => sig.ty.clone(),
+                    _ => {
+                        self.error(
+                            format!("no static method `{}` found for `{}`", method.name, key.0),
+                            method.span,
+                        );
+                        Type::Unknown
+                    }
+                };
+                if !self.is_open(&ty) {
+                    let target = MethodTarget::Method {
+                        ty: key.0,
+                        name: key.1,
+                    };
+                    self.results.methods.insert(id, target);
+                }
+                return self.call_args(ty, Some(&method.name), method.span, args);
+            }
+        }
+        let receiver_ty = self.expr(receiver, None);
+        let action = format!("call method `{}` on", method.name);
+        let base = self.dereference(&receiver_ty, &action, Some(safe), receiver.span);
+        let ty = match self.resolve_method(&base, &method.name) {
+            Some((target, ty)) => {
+                self.results.methods.insert(id, target);
+                self.call_args(ty, Some(&method.name), method.span, args)
+            }
+            None => {
+                if !self.is_open(&base) {
+                    self.error(
+                        format!(
+                            "no method `{}` found for `{}`",
+                            method.name,
+                            self.shown(&base)
+                        ),
+                        method.span,
+                    );
+                }
+                self.call_args(Type::Unknown, None, method.span, args)
+            }
+        };
+        match safe && receiver_ty.is_nullable() {
+            true => ty.nullable(),
+            false => ty,
+        }
+    }
+
+    // the method's type without the receiver
+    fn resolve_method(&self, receiver: &Type, name: &str) -> Option<(MethodTarget, Type)> {
+        if let Type::Struct(struct_name) = receiver {
+            let key = (struct_name.clone(), name.to_string());
+            if let Some(sig) = self.methods.get(&key).filter(|sig| sig.has_receiver) {
+                let target = MethodTarget::Method {
+                    ty: key.0,
+                    name: key.1,
+                };
+                return Some((target, sig.ty.clone()));
+            }
+        }
+        match self.functions.get(name)? {
+            Type::Function(params, ret) if params.first()?.accepts(receiver) => Some((
+                MethodTarget::Function(name.to_string()),
+                Type::Function(params[1..].to_vec(), ret.clone()),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Checks the arguments against a callee of type `ty`, giving the call's
+    /// type. `name` is the function or method called, if it has one.
+    fn call_args(&mut self, ty: Type, name: Option<&str>, callee: Span, args: &[Expr]) -> Type {
+        // calling a value of unknown type, a lambda parameter say, makes it a
+        // function of the arguments
+        if let Type::Var(var) = self.resolve(&ty) {
+            if var.kind == VarKind::General {
+                let params = args
+                    .iter()
+                    .map(|_| self.fresh(VarKind::General, None))
+                    .collect();
+                let ret = self.fresh(VarKind::General, None);
+                let function = Type::Function(params, Box::new(ret));
+                self.bind(var, function.clone());
+                return self.call_args(function, name, callee, args);
+            }
+        }
+        let Type::Function(params, ret) = self.resolve(&ty) else {
+            args.iter().for_each(|arg| {
+                self.expr(arg, None);
+            });
+            return Type::Unknown;
+        };
+        // `desugar` leaves the value piped in with `|>` as the first argument,
+        // the only one that comes before the callee in the source
+        let piped = args.first().is_some_and(|arg| arg.span.end <= callee.start);
+        let what = match (piped, name) {
+            (true, Some(name)) => format!("pipeline stage `{}`", name),
+            (true, None) => "pipeline stage".to_string(),
+            (false, Some(name)) => format!("`{}`", name),
+            (false, None) => "this function".to_string(),
+        };
+        if params.len() != args.len() {
+            let counting = match piped {
+                true => ", counting the piped value",
+                false => "",
+            };
+            self.error(
+                format!(
+                    "{} takes {} but {} given{}",
+                    what,
+                    plural(params.len(), "argument"),
+                    match args.len() {
+                        1 => "1 was".to_string(),
+                        n => format!("{} were", n),
+                    },
+                    counting
+                ),
+                callee,
+            );
+        }
+        for (i, arg) in args.iter().enumerate() {
+            let ty = self.expr(arg, params.get(i));
+            let Some(param) = params.get(i) else { continue };
+            if self.accepts(param, &ty) {
+                continue;
+            }
+            let (param, ty) = (self.shown(param), self.shown(&ty));
+            // the arguments of a stage are counted from the piped value
+            match (piped, i) {
+                (true, 0) => self.error(
+                    format!("{} expects `{}`, found `{}`", what, param, ty),
+                    callee,
+                ),
+                (true, _) => self.error(
+                    format!(
+                        "{} expects `{}` for argument {}, found `{}`",
+                        what,
+                        param,
+                        i + 1,
+                        ty
+                    ),
+                    arg.span,
+                ),
+                (false, _) => self.error(format!("expected `{}`, found `{}`", param, ty), arg.span),
+            }
+        }
+        self.resolve(&ret)
+    }
+
+    // an integer literal has to fit the type it ended up with
+    fn check_range(&mut self, literal: &IntLiteral) {
+        let Type::Primitive(ty) = self.resolve(&literal.ty) else {
+            return;
+        };
+        let Some((min, max)) = integer_range(ty) else {
+            return;
+        };
+        let (negative, digits) = match literal.text.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, literal.text.as_str()),
+        };
+        let fits = match (digits.parse::<u128>(), negative) {
+            (Ok(value), false) => value <= max,
+            (Ok(value), true) => value <= min.unsigned_abs(),
+            (Err(_), _) => false,
+        };
+        if !fits {
+            self.error(
+                format!(
+                    "literal `{}` does not fit in `{}`, whose range is `{}..={}`",
+                    literal.text, ty, min, max
+                ),
+                literal.span,
+            );
+        }
+    }
+
+    /// `value as ty` converts between any two number types, truncating or
+    /// wrapping where `ty` cannot hold the value. A literal is checked against
+    /// `ty` itself.
+    fn cast(&mut self, value: &Expr, ty: &TypeExpr) -> Type {
+        let target = self.lower(ty);
+        let hint = match is_number(&target) {
+            true => Some(&target),
+            false => None,
+        };
+        let found = self.expr(value, hint);
+        let found = self.dereference(&found, "convert", None, value.span);
+        let convertible = is_number(&target) || target.is_unknown();
+        if !convertible || !(is_number(&found) || self.is_open(&found)) {
+            let found = self.shown(&found);
+            self.error(
+                format!(
+                    "cannot convert `{}` to `{}` with `as`, it only converts between numbers",
+                    found, target
+                ),
+                value.span.to(ty.span),
+            );
+        }
+        target
+    }
+
+    fn callee(&mut self, callee: &Expr) -> Type {
+        let ty = self.expr(callee, None);
+        self.dereference(&ty, "call", None, callee.span)
+    }
+
+    /// Types both operands, a numeric literal on the left takes its type from
+    /// the right so `1 + x` is as good as `x + 1`.
+    fn operand_types(&mut self, lhs: &Expr, rhs: &Expr, expected: Option<&Type>) -> (Type, Type) {
+        if is_numeric_literal(lhs) && !is_numeric_literal(rhs) {
+            let rhs_ty = self.expr(rhs, expected);
+            let lhs_ty = self.expr(lhs, Some(&rhs_ty));
+            return (lhs_ty, rhs_ty);
+        }
+        let lhs_ty = self.expr(lhs, expected);
+        let rhs_ty = self.expr(rhs, Some(&lhs_ty));
+        (lhs_ty, rhs_ty)
+    }
+
+    /// Arithmetic takes two numbers of the same type, `+` also joins strings.
+    /// Comparisons take numbers, chars or strings. Gives the operand type.
+    fn operands(&mut self, op: BinaryOp, lhs: &Expr, rhs: &Expr, expected: Option<&Type>) -> Type {
+        let (lhs_ty, rhs_ty) = self.operand_types(lhs, rhs, expected);
+        self.apply(op, (&lhs_ty, lhs.span), (&rhs_ty, rhs.span))
+    }
+
+    // `operands` once both have their type, with the span of each
+    fn apply(&mut self, op: BinaryOp, lhs: (&Type, Span), rhs: (&Type, Span)) -> Type {
+        let ((lhs_ty, lhs_span), (rhs_ty, rhs_span)) = (lhs, rhs);
+        let action = format!("apply `{}` to", op.symbol());
+        let lhs_ty = self.dereference(lhs_ty, &action, None, lhs_span);
+        let rhs_ty = self.dereference(rhs_ty, &action, None, rhs_span);
+        let ty = match lhs_ty.is_unknown() {
+            true => rhs_ty.clone(),
+            false => lhs_ty.clone(),
+        };
+        let string = Type::Primitive(PrimitiveType::String);
+        let allowed = self.is_open(&ty)
+            || ty.is_integer()
+            || ty.is_float()
+            || match op {
+                BinaryOp::Add => ty == string,
+                BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => false,
+                _ => ty == string || ty == Type::Primitive(PrimitiveType::Char),
+            };
+        if !allowed {
+            let span = match lhs_ty.is_unknown() {
+                true => rhs_span,
+                false => lhs_span,
+            };
+            self.error(
+                format!(
+                    "`{}` cannot be applied to `{}`",
+                    op.symbol(),
+                    self.shown(&ty)
+                ),
+                span,
+            );
+        } else if self.accepts(&rhs_ty, &lhs_ty) && !self.accepts(&lhs_ty, &rhs_ty) {
+            // the narrower operand widens to the other
+            return self.resolve(&rhs_ty);
+        } else {
+            self.expect(&lhs_ty, &rhs_ty, rhs_span);
+        }
+        self.resolve(&ty)
+    }
+
+    // `a ?: b` is `a` when it is not null and `b` otherwise
+    fn elvis(&mut self, lhs: &Expr, rhs: &Expr, expected: Option<&Type>) -> Type {
+        let hint = expected.map(|ty| ty.clone().nullable());
+        let lhs_ty = self.expr(lhs, hint.as_ref());
+        let value = lhs_ty.non_null();
+        let fallback = match value.is_unknown() {
+            true => self.expr(rhs, expected),
+            false => self.expr(rhs, Some(&value)),
+        };
+        if value.is_unknown() {
+            return fallback;
+        }
+        if !self.accepts(&value.clone().nullable(), &fallback) {
+            let (value, fallback) = (self.shown(&value), self.shown(&fallback));
+            self.error(
+                format!(
+                    "`?:` operands have incompatible types, expected `{}`, found `{}`",
+                    value, fallback
+                ),
+                rhs.span,
+            );
+        }
+        match fallback.is_nullable() {
+            true => self.resolve(&value).nullable(),
+            false => self.resolve(&value),
+        }
+    }
+
+    /// The variables `cond` proves non-null when it evaluates to `holds`.
+    /// `x != null` holding or `x == null` failing proves `x` non-null, so do
+    /// `&&` chains of proofs that hold, `||` chains of ones that fail and `!`
+    /// of either.
+    fn narrowings(&self, cond: &Expr, holds: bool) -> Narrowings {
+        let mut narrowings = HashMap::new();
+        let mut pending = vec![(cond, holds)];
+        while let Some((cond, holds)) = pending.pop() {
+            match &cond.kind {
+                ExprKind::Unary {
+                    op: UnaryOp::Not,
+                    operand,
+                } => pending.push((operand, !holds)),
+                ExprKind::Binary { op, lhs, rhs } => match (op, holds) {
+                    (BinaryOp::And, true) | (BinaryOp::Or, false) => {
+                        pending.extend([(lhs.as_ref(), holds), (rhs.as_ref(), holds)])
+                    }
+                    (BinaryOp::NotEq, true) | (BinaryOp::Eq, false) => {
+                        let Some(checked) = null_check(lhs, rhs) else {
+                            continue;
+                        };
+                        let Some((def, ty)) = self.local(checked.id) else {
+                            continue;
+                        };
+                        let ty = self.resolve(&ty);
+                        if ty.is_nullable() {
+                            narrowings.insert(def, ty.non_null());
+                        }
+                    }
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        narrowings
+    }
+
+    // what is known once the first `i` conditions of `if_expr` failed
+    fn failed(&self, if_expr: &IfExpr, i: usize) -> Narrowings {
+        let mut narrowings = HashMap::new();
+        for branch in if_expr.branches.iter().take(i) {
+            narrowings.extend(self.narrowings(&branch.condition, false));
+        }
+        narrowings
+    }
+
+    /// What is known on entering branch `i` of `if_expr`, the `else` when `i`
+    /// is past the last condition: every earlier condition failed and the
+    /// branch's own held.
+    fn branch_narrowings(&self, if_expr: &IfExpr, i: usize) -> Narrowings {
+        let mut narrowings = self.failed(if_expr, i);
+        if let Some(branch) = if_expr.branches.get(i) {
+            narrowings.extend(self.narrowings(&branch.condition, true));
+        }
+        narrowings
+    }
+
+    fn branch(
+        &mut self,
+        narrowings: Narrowings,
+        block: &Block,
+        expected: Option<&Type>,
+        used: bool,
+    ) -> Type {
+        self.narrowed.push(narrowings);
+        let ty = self.block(block, expected, used);
+        self.narrowed.pop();
+        ty
+    }
+
+    // a loop may run again after any assignment in it, so what was known
+    // before it no longer holds for the variables it assigns
+    fn forget_assigned(&mut self, loop_expr: &Expr) {
+        let mut assigned = Assigned(HashSet::new());
+        assigned.visit_expr(loop_expr);
+        for target in assigned.0 {
+            if let Some(def) = self.resolution.values.get(&target) {
+                self.forget(*def);
+            }
+        }
+    }
+
+    fn index(&mut self, base: &Expr, index: &Expr) -> Type {
+        let base_ty = self.expr(base, None);
+        match self.dereference(&base_ty, "index into", None, base.span) {
+            Type::List(element) => {
+                let ty = self.expr(index, Some(&Type::Primitive(PrimitiveType::USize)));
+                if !ty.is_integer() && !self.is_open(&ty) {
+                    self.error(
+                        format!("list indices must be integers, found `{}`", ty),
+                        index.span,
+                    );
+                }
+                *element
+            }
+            Type::Map(key, value) => {
+                let ty = self.expr(index, Some(&key));
+                self.expect(&key, &ty, index.span);
+                *value
+            }
+            Type::Tuple(elements) => {
+                self.expr(index, None);
+                let position = match &index.kind {
+                    ExprKind::Literal(Literal::Int(text)) => text.parse::<usize>().ok(),
+                    _ => None,
+                };
+                match position {
+                    Some(i) if i < elements.len() => elements[i].clone(),
+                    Some(i) => {
+                        self.error(
+                            format!(
+                                "index {} is out of range for `{}`",
+                                i,
+                                self.shown(&Type::Tuple(elements))
+                            ),
+                            index.span,
+                        );
+                        Type::Unknown
+                    }
+                    None => {
+                        self.error("tuples are indexed with an integer literal", index.span);
+                        Type::Unknown
+                    }
+                }
+            }
+            ty if self.is_open(&ty) => {
+                self.expr(index, None);
+                Type::Unknown
+            }
+            other => {
+                self.expr(index, None);
+                self.error(
+                    format!("cannot index into a value of type `{}`", self.shown(&other)),
+                    base.span,
+                );
+                Type::Unknown
+            }
+        }
+    }
+
+    // a loop evaluates to whatever its `break`s hand back
+    /// A loop may end without reaching a `break`, so its value is `null`
+    /// then and the type of the `break` values otherwise.
+    fn loop_body(&mut self, label: &Option<Ident>, body: &Block) -> Type {
+        self.loops.push(LoopContext {
+            label: label.as_ref().map(|label| label.name.clone()),
+            break_type: None,
+        });
+        self.block(body, None, false);
+        let context = self.loops.pop();
+        match context.and_then(|context| context.break_type) {
+            None | Some(Type::Unit) => Type::Unit,
+            Some(ty) => ty.nullable(),
+        }
+    }
+
+    fn break_expr(&mut self, label: &Option<Ident>, value: Option<&Expr>, span: Span) {
+        let target = match label {
+            Some(label) => self
+                .loops
+                .iter()
+                .rposition(|context| context.label.as_ref() == Some(&label.name)),
+            None => self.loops.len().checked_sub(1),
+        };
+        let hint = target.and_then(|i| self.loops[i].break_type.clone());
+        let (ty, value_span) = match value {
+            Some(value) => (self.expr(value, hint.as_ref()), value.span),
+            None => (Type::Unit, span),
+        };
+        let Some(target) = target else { return };
+        match hint {
+            None => self.loops[target].break_type = Some(ty),
+            Some(expected) => {
+                if !self.accepts(&expected, &ty) {
+                    let (expected, ty) = (self.shown(&expected), self.shown(&ty));
+                    self.error(
+                        format!(
+                            "`break` values have incompatible types, expected `{}`, found `{}`",
+                            expected, ty
+                        ),
+                        value_span,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Parameters without annotation take their type from the function type
+    /// `expected`, or from how the lambda uses them.
+    fn lambda(&mut self, lambda: &Lambda, expected: Option<&Type>) -> Type {
+        let hints = match expected {
+            Some(Type::Function(params, _)) if params.len() == lambda.params.len() => {
+                params.clone()
+            }
+            _ => Vec::new(),
+        };
+        // loops outside the lambda cannot be broken out of from inside it
+        let outer_loops = std::mem::take(&mut self.loops);
+        let mut params = Vec::new();
+        for (i, param) in lambda.params.iter().enumerate() {
+            let ty = match (&param.ty, hints.get(i)) {
+                (Some(ty), _) => self.lower(ty),
+                (None, Some(hint)) => hint.clone(),
+                (None, None) => {
+                    let what = format!("`{}`", param.name.source_name());
+                    self.fresh(VarKind::General, Some((what, param.name.span)))
+                }
+            };
+            self.declare(&param.name, ty.clone());
+            params.push(ty);
+        }
+        let declared = lambda.return_type.as_ref().map(|ty| self.lower(ty));
+        // the body's value is only used when a return type asks for one, or
+        // to infer it, unless the body ends in an `if` without `else` and so
+        // returns `()`
+        let returns = match (&declared, expected) {
+            (Some(declared), _) => Some(declared.clone()),
+            (None, Some(Type::Function(_, ret))) => Some(self.resolve(ret)),
+            _ => None,
+        };
+        let used = match returns.filter(|ty| !self.is_open(ty)) {
+            Some(ty) => ty != Type::Unit,
+            None => !ends_in_statement(&lambda.body),
+        };
+        self.return_types.push(declared.clone());
+        let body = self.block(&lambda.body, declared.as_ref(), used);
+        if let Some(declared) = &declared {
+            self.expect(declared, &body, block_value_span(&lambda.body));
+        }
+        self.return_types.pop();
+        self.loops = outer_loops;
+        Type::Function(params, Box::new(declared.unwrap_or(body)))
+    }
+
+    // branches only have to agree when the value of the `if` is used
+    fn if_expr(
+        &mut self,
+        if_expr: &IfExpr,
+        span: Span,
+        expected: Option<&Type>,
+        used: bool,
+    ) -> Type {
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        // `else if` conditions are only evaluated when the ones before failed
+        for (i, branch) in if_expr.branches.iter().enumerate() {
+            self.narrowed.push(self.failed(if_expr, i));
+            let cond = self.expr(&branch.condition, Some(&boolean));
+            self.narrowed.pop();
+            self.expect(&boolean, &cond, branch.condition.span);
+        }
+        let blocks: Vec<(Narrowings, &Block)> = if_expr
+            .branches
+            .iter()
+            .map(|branch| &branch.block)
+            .chain(if_expr.else_block.iter())
+            .enumerate()
+            .map(|(i, block)| (self.branch_narrowings(if_expr, i), block))
+            .collect();
+
+        if !used {
+            for (narrowings, block) in blocks {
+                self.branch(narrowings, block, None, false);
+            }
+            return Type::Unit;
+        }
+        if if_expr.else_block.is_none() {
+            for (narrowings, block) in blocks {
+                self.branch(narrowings, block, None, false);
+            }
+            self.error("`if` used as a value needs an `else` branch", span);
+            return Type::Unknown;
+        }
+
+        let mut first: Option<Type> = None;
+        for (narrowings, block) in blocks {
+            let hint = first.clone().or_else(|| expected.cloned());
+            let ty = self.branch(narrowings, block, hint.as_ref(), true);
+            if ty == Type::Never {
+                continue;
+            }
+            match &first {
+                None => first = Some(ty),
+                Some(first) => {
+                    if !self.accepts(first, &ty) {
+                        let (first, ty) = (self.shown(first), self.shown(&ty));
+                        self.error(
+                            format!(
+                                "`if` branches have incompatible types, expected `{}`, found `{}`",
+                                first, ty
+                            ),
+                            block_value_span(block),
+                        );
+                    }
+                }
+            }
+        }
+        first.map_or(Type::Never, |first| self.resolve(&first))
+    }
+
+    fn match_expr(&mut self, match_expr: &MatchExpr, expected: Option<&Type>, used: bool) -> Type {
+        let scrutinee = self.expr(&match_expr.scrutinee, None);
+        let boolean = Type::Primitive(PrimitiveType::Bool);
+        let mut first: Option<Type> = None;
+        for arm in &match_expr.arms {
+            self.pattern(&arm.pattern, &scrutinee);
+            if let Some(guard) = &arm.guard {
+                let ty = self.expr(guard, Some(&boolean));
+                self.expect(&boolean, &ty, guard.span);
+            }
+            if used {
+                let hint = first.clone().or_else(|| expected.cloned());
+                let ty = self.expr(&arm.body, hint.as_ref());
+                match &first {
+                    _ if ty == Type::Never => {}
+                    None => first = Some(ty),
+                    Some(first) => {
+                        if !self.accepts(first, &ty) {
+                            let (first, ty) = (self.shown(first), self.shown(&ty));
+                            self.error(
+                                format!(
+                                    "`match` arms have incompatible types, expected `{}`, found `{}`",
+                                    first, ty
+                                ),
+                                arm.body.span,
+                            );
+                        }
+                    }
+                }
+            } else {
+                self.expr_stmt(&arm.body);
+            }
+        }
+        match used {
+            true => first.map_or(Type::Never, |first| self.resolve(&first)),
+            false => Type::Unit,
+        }
+    }
+
+    /// Declares the bindings of `pattern` for a value of type `expected`.
+    fn pattern(&mut self, pattern: &Pattern, expected: &Type) {
+        match &pattern.kind {
+            PatternKind::Wildcard => {}
+            PatternKind::Binding(ident) => self.declare(ident, expected.clone()),
+            PatternKind::Literal(literal) => {
+                let ty = self.literal(literal, Some(expected));
+                if let Literal::Int(text) = literal {
+                    self.int_literals.push(IntLiteral {
+                        text: text.clone(),
+                        ty: ty.clone(),
+                        span: pattern.span,
+                    });
+                }
+                self.expect(expected, &ty, pattern.span);
+            }
+            PatternKind::Tuple(elements) => {
+                let types = match expected {
+                    Type::Tuple(types) if types.len() == elements.len() => types.clone(),
+                    Type::Tuple(types) => {
+                        self.error(
+                            format!(
+                                "expected a tuple of {} elements, found {}",
+                                types.len(),
+                                elements.len()
+                            ),
+                            pattern.span,
+                        );
+                        Vec::new()
+                    }
+                    _ => Vec::new(),
+                };
+                for (i, element) in elements.iter().enumerate() {
+                    self.pattern(element, types.get(i).unwrap_or(&Type::Unknown));
+                }
+            }
+            PatternKind::Struct { name, fields } => {
+                let decl = self.structs.get(&name.name).copied();
+                if let Type::Struct(expected_name) = expected {
+                    if *expected_name != name.name {
+                        self.error(
+                            format!("expected `{}`, found `{}`", expected_name, name.name),
+                            name.span,
+                        );
+                    }
+                }
+                for field in fields {
+                    let declared = decl
+                        .map(|decl| decl.fields.iter().find(|f| f.name.name == field.name.name));
+                    let ty = match declared {
+                        Some(Some(declared)) => self.lower(&declared.ty),
+                        Some(None) => {
+                            self.error(
+                                format!(
+                                    "struct `{}` has no field `{}`",
+                                    name.name, field.name.name
+                                ),
+                                field.name.span,
+                            );
+                            Type::Unknown
+                        }
+                        None => Type::Unknown,
+                    };
+                    self.pattern(&field.pattern, &ty);
+                }
+            }
+            PatternKind::Or(alternatives) => {
+                let names = |p: &Pattern| {
+                    let mut names: Vec<String> = p
+                        .bindings()
+                        .iter()
+                        .map(|i| i.source_name().to_string())
+                        .collect();
+                    names.sort();
+                    names
+                };
+                let expected_names = alternatives.first().map(names).unwrap_or_default();
+                for alternative in alternatives {
+                    let found = names(alternative);
+                    if let Some(missing) = expected_names
+                        .iter()
+                        .chain(found.iter())
+                        .find(|name| !expected_names.contains(name) || !found.contains(name))
+                    {
+                        self.error(
+                            format!("`{}` is not bound in every alternative", missing),
+                            alternative.span,
+                        );
+                    }
+                    self.pattern(alternative, expected);
+                }
+            }
+        }
+    }
+}
+
+fn is_numeric_literal(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Literal(Literal::Int(_) | Literal::Float(_))
+    )
+}
+
+// the variable `lhs op rhs` compares against `null`, either way round
+fn null_check<'e>(lhs: &'e Expr, rhs: &'e Expr) -> Option<&'e Expr> {
+    match (&lhs.kind, &rhs.kind) {
+        (ExprKind::Ident(_), ExprKind::Literal(Literal::Null)) => Some(lhs),
+        (ExprKind::Literal(Literal::Null), ExprKind::Ident(_)) => Some(rhs),
+        _ => None,
+    }
+}
+
+// whether the last statement of `block` is an `if` without `else`, which
+// has no value
+fn ends_in_statement(block: &Block) -> bool {
+    matches!(
+        block.stmts.last(),
+        Some(Stmt {
+            kind: StmtKind::Expr(Expr {
+                kind: ExprKind::If(IfExpr {
+                    else_block: None,
+                    ..
+                }),
+                ..
+            }),
+            ..
+        })
+    )
+}
+
+/// Whether control never reaches the end of `block`, because it always
+/// ends in `return`, `break` or `continue`.
+fn diverges(block: &Block) -> bool {
+    let Some(Stmt {
+        kind: StmtKind::Expr(expr),
+        ..
+    }) = block.stmts.last()
+    else {
+        return false;
+    };
+    match &expr.kind {
+        ExprKind::Return(_) | ExprKind::Break { .. } | ExprKind::Continue { .. } => true,
+        ExprKind::Block(block) => diverges(block),
+        ExprKind::If(if_expr) => {
+            if_expr.else_block.as_ref().is_some_and(diverges)
+                && if_expr
+                    .branches
+                    .iter()
+                    .all(|branch| diverges(&branch.block))
+        }
+        _ => false,
+    }
+}
+
+// the variables assigned anywhere in a node
+struct Assigned(HashSet<NodeId>);
+
+impl Visitor for Assigned {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Assign { target, .. } = &expr.kind {
+            if let ExprKind::Ident(_) = &target.kind {
+                self.0.insert(target.id);
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+// any integer or float type, including literals not pinned down yet
+fn is_number(ty: &Type) -> bool {
+    ty.is_integer() || ty.is_float()
+}
+
+fn default_type(kind: VarKind) -> Type {
+    match kind {
+        VarKind::Float => Type::Primitive(PrimitiveType::Double),
+        _ => Type::Primitive(PrimitiveType::I32),
+    }
+}
+
+fn occurs(var: TypeVar, ty: &Type) -> bool {
+    match ty {
+        Type::Var(other) => *other == var,
+        Type::Function(params, ret) => params.iter().any(|p| occurs(var, p)) || occurs(var, ret),
+        Type::List(inner) | Type::Nullable(inner) => occurs(var, inner),
+        Type::Map(key, value) => occurs(var, key) || occurs(var, value),
+        Type::Tuple(elements) => elements.iter().any(|e| occurs(var, e)),
+        _ => false,
+    }
+}
+
+fn block_value_span(block: &Block) -> Span {
+    match block.stmts.last() {
+        Some(stmt) => stmt.span,
+        None => block.span,
+    }
+}
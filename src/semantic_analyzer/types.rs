@@ -0,0 +1,214 @@
+use crate::parser::ast::PrimitiveType;
+use std::fmt;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub enum Type {
+    Primitive(PrimitiveType),
+    Unit,
+    Struct(String),
+    Function(Vec<Type>, Box<Type>),
+    List(Box<Type>),
+    Map(Box<Type>, Box<Type>),
+    /// Has at least one element, the empty tuple is `Unit`.
+    Tuple(Vec<Type>),
+    /// Either a value of the inner type or `null`, never nested.
+    Nullable(Box<Type>),
+    /// The type of the `null` literal when nothing nullable is expected.
+    Null,
+    /// The type of expressions that never produce a value, such as `return`.
+    Never,
+    /// A type still being inferred, see `TypeVar`.
+    Var(TypeVar),
+    /// Produced when a type could not be determined, it agrees with everything
+    /// so a single mistake is only reported once.
+    Unknown,
+}
+
+/// Stands for a type the checker has yet to infer from how a value is used.
+/// Integer and float variables come from literals and only ever become an
+/// integer or a float type.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct TypeVar {
+    pub id: u32,
+    pub kind: VarKind,
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum VarKind {
+    General,
+    Integer,
+    Float,
+}
+
+impl TypeVar {
+    /// Whether the variable may become `ty`, which is not a variable itself.
+    pub fn admits(&self, ty: &Type) -> bool {
+        match self.kind {
+            VarKind::General => true,
+            VarKind::Integer => ty.is_integer(),
+            VarKind::Float => ty.is_float(),
+        }
+    }
+}
+
+impl Type {
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Type::Unknown)
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, Type::Nullable(_) | Type::Null)
+    }
+
+    /// Wraps the type in `Nullable` unless it can already hold `null`.
+    pub fn nullable(self) -> Type {
+        match self {
+            Type::Nullable(_) | Type::Null | Type::Unknown | Type::Never => self,
+            other => Type::Nullable(Box::new(other)),
+        }
+    }
+
+    /// The type left once `null` is ruled out, unknown for `null` itself.
+    pub fn non_null(&self) -> Type {
+        match self {
+            Type::Nullable(inner) => inner.as_ref().clone(),
+            Type::Null => Type::Unknown,
+            other => other.clone(),
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            Type::Var(TypeVar {
+                kind: VarKind::Integer,
+                ..
+            }) | Type::Primitive(
+                PrimitiveType::I8
+                    | PrimitiveType::U8
+                    | PrimitiveType::I16
+                    | PrimitiveType::U16
+                    | PrimitiveType::I32
+                    | PrimitiveType::U32
+                    | PrimitiveType::I64
+                    | PrimitiveType::U64
+                    | PrimitiveType::I128
+                    | PrimitiveType::U128
+                    | PrimitiveType::ISize
+                    | PrimitiveType::USize
+            )
+        )
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(
+            self,
+            Type::Var(TypeVar {
+                kind: VarKind::Float,
+                ..
+            }) | Type::Primitive(PrimitiveType::Float | PrimitiveType::Double)
+        )
+    }
+
+    /// Whether a value of type `other` can be used where `self` is expected.
+    /// A variable accepts, and is accepted by, whatever it could still become.
+    pub fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) | (_, Type::Never) => true,
+            (Type::Var(a), Type::Var(b)) => {
+                a.kind == b.kind || a.kind == VarKind::General || b.kind == VarKind::General
+            }
+            (Type::Var(var), other) | (other, Type::Var(var)) => var.admits(other),
+            (Type::Function(params, ret), Type::Function(other_params, other_ret)) => {
+                params.len() == other_params.len()
+                    && params
+                        .iter()
+                        .zip(other_params)
+                        .all(|(a, b)| a.accepts(b) && b.accepts(a))
+                    && ret.accepts(other_ret)
+            }
+            (Type::List(element), Type::List(other)) => element.accepts(other),
+            (Type::Map(key, value), Type::Map(other_key, other_value)) => {
+                key.accepts(other_key) && value.accepts(other_value)
+            }
+            (Type::Tuple(elements), Type::Tuple(others)) => {
+                elements.len() == others.len()
+                    && elements.iter().zip(others).all(|(a, b)| a.accepts(b))
+            }
+            (Type::Nullable(_), Type::Null) => true,
+            (Type::Nullable(inner), other) => inner.accepts(&other.non_null()),
+            (Type::Primitive(to), Type::Primitive(from)) => widens(*from, *to),
+            _ => self == other,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Primitive(prim) => write!(f, "{}", prim),
+            Type::Unit => write!(f, "()"),
+            Type::Struct(name) => write!(f, "{}", name),
+            Type::Function(params, ret) => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "({}) -> {}", params.join(", "), ret)
+            }
+            Type::List(element) => write!(f, "[{}]", element),
+            Type::Map(key, value) => write!(f, "{{{} => {}}}", key, value),
+            Type::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                match elements.len() {
+                    1 => write!(f, "({},)", elements[0]),
+                    _ => write!(f, "({})", elements.join(", ")),
+                }
+            }
+            Type::Nullable(inner) => match inner.as_ref() {
+                Type::Function(..) => write!(f, "({})?", inner),
+                _ => write!(f, "{}?", inner),
+            },
+            Type::Null => write!(f, "null"),
+            Type::Never => write!(f, "never"),
+            Type::Unknown => write!(f, "{{unknown}}"),
+            Type::Var(var) => match var.kind {
+                VarKind::General => write!(f, "_"),
+                VarKind::Integer => write!(f, "{{integer}}"),
+                VarKind::Float => write!(f, "{{float}}"),
+            },
+        }
+    }
+}
+
+/// The smallest and largest value of an integer type. `isize` and `usize` are
+/// taken to be 64 bits wide.
+pub fn integer_range(ty: PrimitiveType) -> Option<(i128, u128)> {
+    let range = match ty {
+        PrimitiveType::I8 => (i8::MIN as i128, i8::MAX as u128),
+        PrimitiveType::U8 => (0, u8::MAX as u128),
+        PrimitiveType::I16 => (i16::MIN as i128, i16::MAX as u128),
+        PrimitiveType::U16 => (0, u16::MAX as u128),
+        PrimitiveType::I32 => (i32::MIN as i128, i32::MAX as u128),
+        PrimitiveType::U32 => (0, u32::MAX as u128),
+        PrimitiveType::I64 | PrimitiveType::ISize => (i64::MIN as i128, i64::MAX as u128),
+        PrimitiveType::U64 | PrimitiveType::USize => (0, u64::MAX as u128),
+        PrimitiveType::I128 => (i128::MIN, i128::MAX as u128),
+        PrimitiveType::U128 => (0, u128::MAX),
+        _ => return None,
+    };
+    Some(range)
+}
+
+/// Whether every value of `from` is also a value of `to`, so a `from` can be
+/// used as a `to` without an `as`. `float` widens to `double`, integers to
+/// any integer type that holds their whole range. Integers never implicitly
+/// become floats.
+pub fn widens(from: PrimitiveType, to: PrimitiveType) -> bool {
+    if from == to {
+        return true;
+    }
+    match (integer_range(from), integer_range(to)) {
+        (Some((from_min, from_max)), Some((to_min, to_max))) => {
+            to_min <= from_min && from_max <= to_max
+        }
+        _ => from == PrimitiveType::Float && to == PrimitiveType::Double,
+    }
+}
@@ -0,0 +1,90 @@
+use rot::cst::builder::parse_tree;
+use rot::cst::cst::SyntaxKind;
+use rot::lexer::token::{Kind, Span};
+
+#[test]
+fn test_tree_is_lossless() {
+    let source = std::fs::read_to_string("resources/samples/sample1.rot").unwrap();
+    let tree = parse_tree(&source);
+    assert_eq!(tree.root.text(), source);
+    assert_eq!(tree.root.span(), Span::new(0, source.chars().count()));
+}
+
+#[test]
+fn test_broken_code_is_lossless() {
+    let source = "def f() do\n    x = )\n    y: = 1 # note\n\ndefstruct ? P do\nend\n";
+    let tree = parse_tree(source);
+    assert!(!tree.diagnostics.is_empty());
+    assert_eq!(tree.root.text(), source);
+}
+
+#[test]
+fn test_tree_shape_and_trivia() {
+    let tree = parse_tree("def f() do\n    x: i32 = 1 + 2\nend\n");
+    let dump = format!("{:?}", tree.root);
+    assert_eq!(
+        dump,
+        r#"SourceFile@0..34
+  Function@0..33
+    Def@0..3 "def"
+    WhiteSpace@3..4 " "
+    Identifier@4..5 "f"
+    LeftParen@5..6 "("
+    RightParen@6..7 ")"
+    WhiteSpace@7..8 " "
+    Block@8..33
+      Do@8..10 "do"
+      WhiteSpace@10..15 "\n    "
+      LetStmt@15..29
+        Identifier@15..16 "x"
+        Colon@16..17 ":"
+        WhiteSpace@17..18 " "
+        PrimitiveType@18..21
+          I32@18..21 "i32"
+        WhiteSpace@21..22 " "
+        Equal@22..23 "="
+        WhiteSpace@23..24 " "
+        BinaryExpr@24..29
+          LiteralExpr@24..25
+            IntLiteral@24..25 "1"
+          WhiteSpace@25..26 " "
+          Plus@26..27 "+"
+          WhiteSpace@27..28 " "
+          LiteralExpr@28..29
+            IntLiteral@28..29 "2"
+      WhiteSpace@29..30 "\n"
+      End@30..33 "end"
+  WhiteSpace@33..34 "\n"
+"#
+    );
+}
+
+#[test]
+fn test_comments_are_kept_as_trivia() {
+    let tree = parse_tree("# leading\ndef f() do\n    1 # trailing\nend\n");
+    let comments: Vec<_> = tree
+        .root
+        .descendant_tokens()
+        .into_iter()
+        .filter(|token| *token.kind() == Kind::Comment)
+        .collect();
+    assert_eq!(comments.len(), 2);
+    assert!(comments.iter().all(|token| token.is_trivia()));
+    assert_eq!(comments[0].parent().kind(), SyntaxKind::SourceFile);
+    assert_eq!(comments[1].parent().kind(), SyntaxKind::Block);
+}
+
+#[test]
+fn test_parenthesized_expressions_nest() {
+    let tree = parse_tree("def f() do\n    (1 + 2) * 3\nend");
+    let binaries: Vec<_> = tree
+        .root
+        .descendants()
+        .into_iter()
+        .filter(|node| node.kind() == SyntaxKind::BinaryExpr)
+        .collect();
+    assert_eq!(binaries.len(), 2);
+    assert_eq!(binaries[0].text(), "(1 + 2) * 3");
+    assert_eq!(binaries[1].text(), "(1 + 2)");
+    assert_eq!(binaries[1].parent(), Some(binaries[0].clone()));
+}
@@ -0,0 +1,79 @@
+use rot::cst::builder::parse_tree;
+use rot::cst::cst::SyntaxKind;
+use rot::cst::nodes::*;
+
+fn source_file(source: &str) -> SourceFile {
+    SourceFile::cast(parse_tree(source).root).unwrap()
+}
+
+#[test]
+fn test_function_accessors() {
+    let file = source_file("def add(a: i32, b: i32) -> i32 do\n    a + b\nend");
+    let function = match &file.items()[0] {
+        Item::Function(function) => function.clone(),
+        other => panic!("expected a function, got {:?}", other),
+    };
+    assert_eq!(function.name().unwrap().text(), "add");
+    let params: Vec<String> = function
+        .params()
+        .iter()
+        .map(|param| param.syntax().text())
+        .collect();
+    assert_eq!(params, ["a: i32", "b: i32"]);
+    assert_eq!(function.return_type().unwrap().syntax().text(), "i32");
+    let body = function.body().unwrap();
+    assert_eq!(body.end_token().unwrap().text(), "end");
+    let stmt = ExprStmt::cast(body.statements().remove(0)).unwrap();
+    let binary = BinaryExpr::cast(stmt.expr().unwrap().syntax().clone()).unwrap();
+    assert_eq!(binary.op_token().unwrap().text(), "+");
+    assert_eq!(binary.lhs().unwrap().syntax().text(), "a");
+    assert_eq!(binary.rhs().unwrap().syntax().text(), "b");
+}
+
+#[test]
+fn test_struct_and_impl_accessors() {
+    let file = source_file(
+        "defstruct Point do\n    public x: i32 = 0\n    y: i32\nend\ndefimpl Point do\n    static def origin() do\n    end\n    def len(self) do\n    end\nend",
+    );
+    let items = file.items();
+    let point = match &items[0] {
+        Item::Struct(point) => point.clone(),
+        other => panic!("expected a struct, got {:?}", other),
+    };
+    let fields = point.fields();
+    assert!(fields[0].is_public());
+    assert!(!fields[1].is_public());
+    assert_eq!(fields[0].default().unwrap().kind(), SyntaxKind::LiteralExpr);
+    assert!(fields[1].default().is_none());
+
+    let block = match &items[1] {
+        Item::Impl(block) => block.clone(),
+        other => panic!("expected an impl, got {:?}", other),
+    };
+    assert_eq!(block.target().unwrap().text(), "Point");
+    let methods = block.methods();
+    assert!(methods[0].is_static() && !methods[0].has_receiver());
+    assert!(!methods[1].is_static() && methods[1].has_receiver());
+    assert_eq!(methods[1].name().unwrap().text(), "len");
+}
+
+#[test]
+fn test_missing_pieces_are_none() {
+    let file = source_file("def f() do\n    x: = 1\n    print(, 2)\nend");
+    let function = match &file.items()[0] {
+        Item::Function(function) => function.clone(),
+        other => panic!("expected a function, got {:?}", other),
+    };
+    let stmts = function.body().unwrap().statements();
+    let binding = LetStmt::cast(stmts[0].clone()).unwrap();
+    assert_eq!(
+        binding.ty().unwrap().syntax().kind(),
+        SyntaxKind::MissingType
+    );
+    assert_eq!(binding.value().unwrap().syntax().text(), "1");
+    let call = ExprStmt::cast(stmts[1].clone()).unwrap().expr().unwrap();
+    let call = CallExpr::cast(call.syntax().clone()).unwrap();
+    assert_eq!(call.callee().unwrap().syntax().text(), "print");
+    let args: Vec<SyntaxKind> = call.args().iter().map(|arg| arg.kind()).collect();
+    assert_eq!(args, [SyntaxKind::MissingExpr, SyntaxKind::LiteralExpr]);
+}
@@ -0,0 +1,145 @@
+use rot::formatter::formatter::format_source;
+use std::process::Command;
+
+const MESSY: &str = r#"
+
+# header comment
+type F=(x:usize)->string
+defstruct Point do
+  public x : i32=0   # trailing
+      y:i32
+end
+defimpl Point do
+static def origin()->Point do
+Point{x:0,y:0}
+end
+
+
+def len(self)->i32 do
+   v = self.x*self.x+
+   self.y
+   return -v
+end
+end
+"#;
+
+const CANONICAL: &str = r#"# header comment
+type F = (x: usize) -> string
+defstruct Point do
+    public x: i32 = 0 # trailing
+    y: i32
+end
+defimpl Point do
+    static def origin() -> Point do
+        Point{x: 0, y: 0}
+    end
+
+    def len(self) -> i32 do
+        v = self.x * self.x +
+            self.y
+        return -v
+    end
+end
+"#;
+
+#[test]
+fn test_formats_canonical_style() {
+    assert_eq!(format_source(MESSY), CANONICAL);
+}
+
+#[test]
+fn test_formatting_is_idempotent() {
+    assert_eq!(format_source(CANONICAL), CANONICAL);
+    let source = "def f(a: i32) do\n  r = if a>0 do a else -a end\n  match a do\n  1|2 -> 3\n  -1 -> 0\n  _ -> !true\n  end\n  h(1,\n  2)\nend";
+    let once = format_source(source);
+    assert_eq!(
+        once,
+        "def f(a: i32) do\n    r = if a > 0 do a else -a end\n    match a do\n        1 | 2 -> 3\n        -1 -> 0\n        _ -> !true\n    end\n    h(1,\n        2)\nend\n"
+    );
+    assert_eq!(format_source(&once), once);
+}
+
+#[test]
+fn test_else_branches_align_with_if() {
+    let source = "def f(a: i32) do\nif a > 1 do\nprint(\"a\")\nelse if a > 0 do\nprint(\"b\")\nelse\nprint(\"c\")\nend\nend\n";
+    assert_eq!(
+        format_source(source),
+        "def f(a: i32) do\n    if a > 1 do\n        print(\"a\")\n    else if a > 0 do\n        print(\"b\")\n    else\n        print(\"c\")\n    end\nend\n"
+    );
+}
+
+#[test]
+fn test_generic_angles_hug_their_contents() {
+    let source = "type P < A,B > = Map < A , List< B > >\ndef f< T:Show+Eq >(x:Box <T>)->bool do\n    x < y\nend\n";
+    assert_eq!(
+        format_source(source),
+        "type P<A, B> = Map<A, List<B>>\ndef f<T: Show + Eq>(x: Box<T>) -> bool do\n    x < y\nend\n"
+    );
+}
+
+#[test]
+fn test_nullable_markers_attach() {
+    let source = "def f(p:Person ?)->string? do\n    p ?. name?:p?.nick()\n    ?.trim()\nend\n";
+    assert_eq!(
+        format_source(source),
+        "def f(p: Person?) -> string? do\n    p?.name ?: p?.nick()\n        ?.trim()\nend\n"
+    );
+}
+
+#[test]
+fn test_compound_assignment_is_spaced() {
+    let source = "def f(mut a:i32) do\n    mut x=1\n    x+=a\n    x  *=  2\nend\n";
+    assert_eq!(
+        format_source(source),
+        "def f(mut a: i32) do\n    mut x = 1\n    x += a\n    x *= 2\nend\n"
+    );
+}
+
+#[test]
+fn test_pipelines_are_spaced_and_continue() {
+    let source = "def f() do\n    xs|>map(g)\n    |>sum()\nend\n";
+    assert_eq!(
+        format_source(source),
+        "def f() do\n    xs |> map(g)\n        |> sum()\nend\n"
+    );
+}
+
+#[test]
+fn test_comment_on_the_last_line() {
+    let source = "def f() do\nend\n# note";
+    assert_eq!(format_source(source), "def f() do\nend\n# note\n");
+    assert_eq!(
+        format_source("def f() do\nend\n#---"),
+        "def f() do\nend\n#---\n"
+    );
+}
+
+#[test]
+fn test_unparsable_source_is_left_alone() {
+    let source = "def f( do\n   x\nend";
+    assert_eq!(format_source(source), source);
+}
+
+#[test]
+fn test_fmt_check_exit_code() {
+    let dir = std::env::temp_dir().join(format!("rot-fmt-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let messy = dir.join("messy.rot");
+    let clean = dir.join("clean.rot");
+    std::fs::write(&messy, MESSY).unwrap();
+    std::fs::write(&clean, CANONICAL).unwrap();
+    let run = |args: &[&std::path::Path]| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_rot"));
+        cmd.arg("fmt");
+        cmd.args(args);
+        cmd.status().unwrap().code()
+    };
+
+    let check = std::path::Path::new("--check");
+    assert_eq!(run(&[check, &clean]), Some(0));
+    assert_eq!(run(&[check, &messy]), Some(1));
+    assert_eq!(std::fs::read_to_string(&messy).unwrap(), MESSY);
+    assert_eq!(run(&[&messy]), Some(0));
+    assert_eq!(std::fs::read_to_string(&messy).unwrap(), CANONICAL);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
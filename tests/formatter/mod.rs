@@ -0,0 +1,2 @@
+#[allow(clippy::module_inception)]
+mod formatter;
@@ -0,0 +1,113 @@
+//! Snapshot tests over `resources/samples`. Every `.rot` file there is lexed,
+//! parsed and checked, and the results are compared with the `.tokens`, `.ast`
+//! and `.diagnostics` files next to it. After an intentional change, rewrite
+//! the snapshots with `BLESS=1 cargo test golden` and review the diff.
+
+use rot::lexer::lexer::Lexer;
+use rot::parser::expand::expand_macros;
+use rot::parser::parser::parse_source;
+use rot::parser::serialize::to_sexpr;
+use rot::semantic_analyzer::semantic_analyzer::analyze;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn samples_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("resources/samples")
+}
+
+fn samples() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(samples_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rot"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn tokens(source: &str) -> String {
+    let mut lex = Lexer::new(source.chars().collect());
+    lex.lex();
+    lex.tokens
+        .iter()
+        .map(|token| format!("{}\n", token))
+        .collect()
+}
+
+// parse and expansion errors and what the analysis reports on the expanded
+// module, in source order
+fn diagnostics(source: &str) -> String {
+    let result = parse_source(source);
+    let (module, expansion_errors) = expand_macros(result.module);
+    let mut diagnostics = result.diagnostics;
+    diagnostics.extend(expansion_errors);
+    diagnostics.extend(analyze(&module));
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+        .iter()
+        .map(|d| format!("{}\n", d.render(source)))
+        .collect()
+}
+
+// the first line that differs, 1-based
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => line += 1,
+            (None, None) => return "line endings differ".to_string(),
+            (e, a) => {
+                return format!(
+                    "line {}\n  expected: {}\n  actual:   {}",
+                    line,
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+}
+
+#[test]
+fn test_samples_match_snapshots() {
+    let bless = env::var_os("BLESS").is_some();
+    let mut failures = Vec::new();
+    let paths = samples();
+    assert!(
+        !paths.is_empty(),
+        "no samples in {}",
+        samples_dir().display()
+    );
+    for path in paths {
+        let source = fs::read_to_string(&path).unwrap();
+        let snapshots = [
+            ("tokens", tokens(&source)),
+            ("ast", to_sexpr(&parse_source(&source).module)),
+            ("diagnostics", diagnostics(&source)),
+        ];
+        for (extension, actual) in snapshots {
+            let snapshot = path.with_extension(extension);
+            if bless {
+                fs::write(&snapshot, &actual).unwrap();
+                continue;
+            }
+            match fs::read_to_string(&snapshot) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!(
+                    "{} differs at {}",
+                    snapshot.display(),
+                    first_difference(&expected, &actual)
+                )),
+                Err(_) => failures.push(format!("{} is missing", snapshot.display())),
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\nrun `BLESS=1 cargo test golden` to accept the changes",
+        failures.join("\n")
+    );
+}
@@ -55,7 +55,7 @@ fn test_complex_evaluation() {
     lex.lex();
     let toks = lex.tokens;
 
-    assert_eq!(toks.is_empty(), false);
+    assert!(!toks.is_empty());
     assert_eq!(toks.len(), 47);
 }
 
@@ -63,9 +63,9 @@ fn lexer_helper(term: String, expected_kind: Kind) {
     let mut lex = Lexer::new(term.chars().collect());
     lex.lex();
     let toks = lex.tokens;
-    assert_eq!(toks.is_empty(), false);
+    assert!(!toks.is_empty());
     assert_eq!(toks.len(), 1);
-    match toks.get(0) {
+    match toks.first() {
         Some(token) => {
             let t = token.clone();
             assert_eq!(t.kind, expected_kind);
@@ -79,7 +79,10 @@ fn lexer_helper(term: String, expected_kind: Kind) {
 
 #[test]
 fn test_keyword_lex() {
-    lexer_helper("def".to_string(), Kind::Def)
+    lexer_helper("def".to_string(), Kind::Def);
+    lexer_helper("in".to_string(), Kind::In);
+    lexer_helper("break".to_string(), Kind::Break);
+    lexer_helper("continue".to_string(), Kind::Continue);
 }
 
 #[test]
@@ -110,3 +113,85 @@ fn test_symbol_lex() {
 fn test_regex_literal() {
     lexer_helper("r\"some regex\"".to_string(), Kind::RegexLiteral)
 }
+
+#[test]
+fn test_token_spans() {
+    let sample = "x: i32 = 10;\nfoo(1)";
+    let mut lex = Lexer::new(sample.chars().collect());
+    lex.lex();
+    let spans: Vec<(usize, usize)> = lex
+        .tokens
+        .iter()
+        .map(|t| (t.span.start, t.span.end))
+        .collect();
+    assert_eq!(
+        spans,
+        vec![
+            (0, 1),
+            (1, 2),
+            (3, 6),
+            (7, 8),
+            (9, 11),
+            (11, 12),
+            (13, 16),
+            (16, 17),
+            (17, 18),
+            (18, 19)
+        ]
+    );
+}
+
+#[test]
+fn test_operator_after_closing_paren() {
+    let mut lex = Lexer::new("f()->i32".chars().collect());
+    lex.lex();
+    let kinds: Vec<Kind> = lex.tokens.into_iter().map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Kind::Identifier,
+            Kind::LeftParen,
+            Kind::RightParen,
+            Kind::Arrow,
+            Kind::I32
+        ]
+    );
+}
+
+#[test]
+fn test_token_spans_cover_their_lexemes() {
+    let sample = "# note\nname = \"a b\" |> f(x)->y\nc: char = 'é' ?: r\"[0-9]+\"\n";
+    let input: Vec<char> = sample.chars().collect();
+    let mut lex = Lexer::new(input.clone());
+    lex.lex();
+    for token in &lex.tokens {
+        let text: String = input[token.span.start..token.span.end].iter().collect();
+        assert_eq!(text, token.lexeme);
+    }
+}
+
+#[test]
+fn test_comment_at_end_of_input() {
+    lexer_helper("# note".to_string(), Kind::Comment);
+    lexer_helper("#---".to_string(), Kind::Comment);
+}
+
+#[test]
+fn test_identifier_r_before_a_symbol() {
+    let mut lex = Lexer::new("f(r).r: r".chars().collect());
+    lex.lex();
+    let tokens: Vec<(Kind, String)> = lex.tokens.into_iter().map(|t| (t.kind, t.lexeme)).collect();
+    assert_eq!(
+        tokens,
+        vec![
+            (Kind::Identifier, "f".to_string()),
+            (Kind::LeftParen, "(".to_string()),
+            (Kind::Identifier, "r".to_string()),
+            (Kind::RightParen, ")".to_string()),
+            (Kind::Dot, ".".to_string()),
+            (Kind::Identifier, "r".to_string()),
+            (Kind::Colon, ":".to_string()),
+            (Kind::Identifier, "r".to_string()),
+        ]
+    );
+}
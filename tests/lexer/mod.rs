@@ -1,2 +1,3 @@
+#[allow(clippy::module_inception)]
 mod lexer;
-mod token;
\ No newline at end of file
+mod token;
@@ -8,3 +8,31 @@ fn test_evaluate_elvis_kind() {
     let opt_kind = find_kind(cdict, sdict, sample.to_string());
     assert_eq!(Some(Kind::Elvis), opt_kind);
 }
+
+#[test]
+fn test_evaluate_compound_assignment_kinds() {
+    let cases = [
+        ("+=", Kind::PlusEqual),
+        ("-=", Kind::MinusEqual),
+        ("*=", Kind::AsteriskEqual),
+        ("/=", Kind::SlashEqual),
+    ];
+    for (sample, kind) in cases {
+        let opt_kind = find_kind(
+            build_complex_dictionary(),
+            build_simple_dictionary(),
+            sample.to_string(),
+        );
+        assert_eq!(Some(kind), opt_kind);
+    }
+}
+
+#[test]
+fn test_evaluate_pipe_forward_kind() {
+    let opt_kind = find_kind(
+        build_complex_dictionary(),
+        build_simple_dictionary(),
+        "|>".to_string(),
+    );
+    assert_eq!(Some(Kind::PipeForward), opt_kind);
+}
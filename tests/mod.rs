@@ -1 +1,7 @@
-mod lexer;
\ No newline at end of file
+mod cst;
+mod formatter;
+mod golden;
+mod lexer;
+mod modules;
+mod parser;
+mod semantic_analyzer;
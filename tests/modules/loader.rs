@@ -0,0 +1,104 @@
+use rot::modules::loader::{load_program_with, module_path, Program};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// loads `main` from an in-memory tree of files
+fn load(files: &[(&str, &str)]) -> Program {
+    let files: HashMap<PathBuf, String> = files
+        .iter()
+        .map(|(path, source)| (PathBuf::from(path), source.to_string()))
+        .collect();
+    load_program_with(Path::new(""), "main", |path| {
+        files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    })
+    .unwrap()
+}
+
+fn errors(program: &Program, module: &str) -> Vec<String> {
+    program
+        .module(module)
+        .unwrap()
+        .diagnostics
+        .iter()
+        .map(|d| d.message.clone())
+        .collect()
+}
+
+#[test]
+fn test_module_path_mapping() {
+    assert_eq!(
+        module_path(Path::new("src"), "geometry.shapes"),
+        PathBuf::from("src/geometry/shapes.rot")
+    );
+}
+
+#[test]
+fn test_loads_imports_dependencies_first() {
+    let program = load(&[
+        (
+            "main.rot",
+            "use geometry.shapes.{Circle, area}\nuse util\ndef main() do\nend",
+        ),
+        (
+            "geometry/shapes.rot",
+            "use util\ndefstruct Circle do\n    radius: i32\nend\ndef area(c: Circle) -> i32 do\n    c.radius\nend",
+        ),
+        ("util.rot", "def id(x: i32) -> i32 do\n    x\nend"),
+    ]);
+    let names: Vec<&str> = program.modules.iter().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, ["util", "geometry.shapes", "main"]);
+    assert!(!program.has_errors());
+    let shapes = program.module("geometry.shapes").unwrap();
+    assert_eq!(
+        program.source_map.path(shapes.file),
+        Path::new("geometry/shapes.rot")
+    );
+}
+
+#[test]
+fn test_missing_modules_and_items() {
+    let program = load(&[
+        ("main.rot", "use util.{id, nope}\nuse gone.away"),
+        ("util.rot", "def id(x: i32) -> i32 do\n    x\nend"),
+    ]);
+    assert_eq!(
+        errors(&program, "main"),
+        [
+            "module `util` has no item `nope`",
+            "cannot find module `gone.away`, looked for `gone/away.rot`"
+        ]
+    );
+}
+
+#[test]
+fn test_import_cycles_are_reported() {
+    let program = load(&[
+        ("main.rot", "use a"),
+        ("a.rot", "use b"),
+        ("b.rot", "use main"),
+    ]);
+    assert_eq!(
+        errors(&program, "b"),
+        ["import cycle: main -> a -> b -> main"]
+    );
+    assert!(errors(&program, "main").is_empty());
+    assert_eq!(program.modules.len(), 3);
+}
+
+#[test]
+fn test_macros_are_expanded_when_loading() {
+    let program = load(&[(
+        "main.rot",
+        "defmacro twice(e) do\n    e\n    e\nend\ndef main() do\n    twice(print(1))\n    twice()\nend",
+    )]);
+    let main = program.module("main").unwrap();
+    assert_eq!(main.module.items.len(), 1);
+    assert_eq!(
+        errors(&program, "main"),
+        ["macro `twice` takes 1 argument but 0 were given"]
+    );
+}
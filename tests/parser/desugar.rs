@@ -0,0 +1,38 @@
+use rot::parser::ast::*;
+use rot::parser::desugar::desugar;
+use rot::parser::parser::parse_source;
+use rot::parser::serialize::to_sexpr;
+use rot::parser::visit::{self, Visitor};
+
+struct Calls(usize);
+
+impl Visitor for Calls {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Call { .. } = expr.kind {
+            self.0 += 1;
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+#[test]
+fn test_compound_assignments_evaluate_their_target_once() {
+    let source = "def f(mut xs: [i32], mut i: i32) do\n    xs[next()] *= 2\n    obj().n += 1\n    i += 1\nend";
+    let module = parse_source(source).into_result().unwrap();
+    let desugared = desugar(module.clone());
+    assert_eq!(to_sexpr(&desugared), to_sexpr(&module));
+    let mut calls = Calls(0);
+    calls.visit_module(&desugared);
+    assert_eq!(calls.0, 2);
+}
+
+#[test]
+fn test_pipelines_become_calls() {
+    let module = parse_source("def f() do\n    x |> f(a) |> g\n    y |> s.push()\nend")
+        .into_result()
+        .unwrap();
+    let expected = parse_source("def f() do\n    g(f(x, a))\n    s.push(y)\nend")
+        .into_result()
+        .unwrap();
+    assert_eq!(to_sexpr(&desugar(module)), to_sexpr(&expected));
+}
@@ -0,0 +1,192 @@
+use rot::lexer::token::Span;
+use rot::parser::ast::*;
+use rot::parser::expand::expand_macros;
+use rot::parser::parser::parse_source;
+use rot::parser::serialize::to_sexpr;
+use rot::parser::visit::{self, Visitor};
+use rot::semantic_analyzer::resolve::resolve_names;
+use rot::semantic_analyzer::semantic_analyzer::analyze;
+
+fn expand(source: &str) -> (Module, Vec<String>) {
+    let module = parse_source(source).into_result().unwrap();
+    let (module, diagnostics) = expand_macros(module);
+    (module, diagnostics.into_iter().map(|d| d.message).collect())
+}
+
+fn body(module: &Module) -> &Block {
+    match &module.items[0].kind {
+        ItemKind::Function(decl) => &decl.body,
+        kind => panic!("expected a function, found {:?}", kind),
+    }
+}
+
+#[test]
+fn test_calls_become_the_macro_body() {
+    let (module, errors) = expand(
+        "defmacro unless(cond, then) do\n    if !cond do\n        then\n    end\nend\n\
+         def f(x: i32) do\n    unless(x > 0, print(x))\nend",
+    );
+    assert_eq!(errors, Vec::<String>::new());
+    let expected = parse_source(
+        "def f(x: i32) do\n    do\n        if !(x > 0) do\n            print(x)\n        end\n    end\nend",
+    )
+    .into_result()
+    .unwrap();
+    assert_eq!(to_sexpr(&module), to_sexpr(&expected));
+}
+
+#[test]
+fn test_macro_bindings_do_not_capture_call_site_names() {
+    let (module, errors) = expand(
+        "defmacro square(e) do\n    result = e\n    result * result\nend\n\
+         def f() do\n    result = 2\n    square(result + 1)\nend",
+    );
+    assert_eq!(errors, Vec::<String>::new());
+    let StmtKind::Expr(Expr {
+        kind: ExprKind::Block(expansion),
+        ..
+    }) = &body(&module).stmts[1].kind
+    else {
+        panic!("expected an expanded block");
+    };
+    let StmtKind::Expr(Expr {
+        kind: ExprKind::Assign { target, value, .. },
+        ..
+    }) = &expansion.stmts[0].kind
+    else {
+        panic!("expected an assignment");
+    };
+    assert!(matches!(&target.kind, ExprKind::Ident(i) if i.name == "result#1"));
+    let ExprKind::Binary { lhs, .. } = &value.kind else {
+        panic!("expected the argument");
+    };
+    assert!(matches!(&lhs.kind, ExprKind::Ident(i) if i.name == "result"));
+}
+
+// the diagnostics of analyzing the expanded module
+fn analyze_expansion(source: &str) -> Vec<String> {
+    let (module, errors) = expand(source);
+    assert_eq!(errors, Vec::<String>::new());
+    analyze(&module).into_iter().map(|d| d.message).collect()
+}
+
+struct LambdaParams(Vec<Ident>);
+
+impl Visitor for LambdaParams {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Lambda(lambda) = &expr.kind {
+            self.0
+                .extend(lambda.params.iter().map(|param| param.name.clone()));
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+#[test]
+fn test_macro_bindings_of_one_name_stay_apart() {
+    let source = "defmacro both(e) do\n    \
+                  inc = fn a: i32 -> i32 do a + 1 end\n    \
+                  twice = fn a: string -> string do a + a end\n    \
+                  twice(e)\n    inc(1)\nend\n\
+                  def f() -> i32 do\n    both(\"x\")\nend";
+    assert_eq!(analyze_expansion(source), Vec::<String>::new());
+
+    let (module, _) = expand(source);
+    let mut params = LambdaParams(Vec::new());
+    params.visit_module(&module);
+    let resolution = resolve_names(&module);
+    let defs: Vec<_> = params
+        .0
+        .iter()
+        .map(|name| resolution.binding(name).unwrap())
+        .collect();
+    assert_eq!(defs.len(), 2);
+    assert_ne!(defs[0], defs[1]);
+}
+
+#[test]
+fn test_macro_bodies_call_module_functions() {
+    let errors = analyze_expansion(
+        "def helper(x: i32) -> i32 do\n    x + 1\nend\n\
+         defmacro bump(e) do\n    helper(e)\nend\n\
+         def f() -> i32 do\n    helper = fn s: string -> string do s end\n    bump(1)\nend",
+    );
+    assert_eq!(errors, Vec::<String>::new());
+}
+
+#[test]
+fn test_diagnostics_name_macro_bindings_as_written() {
+    let errors = analyze_expansion(
+        "defmacro store(e) do\n    tmp = e\n    tmp = 2\n    missing\nend\n\
+         def f() do\n    store(1)\nend",
+    );
+    assert_eq!(
+        errors,
+        [
+            "cannot assign twice to immutable binding `tmp`",
+            "cannot find `missing` in this scope",
+        ]
+    );
+}
+
+struct Exprs(Vec<(String, Span, NodeId)>);
+
+impl Visitor for Exprs {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Ident(ident) = &expr.kind {
+            self.0.push((ident.name.clone(), expr.span, expr.id));
+        }
+        visit::walk_expr(self, expr);
+    }
+}
+
+#[test]
+fn test_expansions_point_at_the_call_site() {
+    let source =
+        "defmacro twice(e) do\n    e\n    e\nend\ndefmacro four(e) do\n    twice(twice(e))\nend\n\
+                  def f() do\n    four(tick)\nend";
+    let (module, errors) = expand(source);
+    assert_eq!(errors, Vec::<String>::new());
+    let call = source.find("four(tick)").unwrap();
+    let arg = call + "four(".len();
+
+    let mut exprs = Exprs(Vec::new());
+    exprs.visit_module(&module);
+    assert_eq!(exprs.0.len(), 4);
+    for (name, span, _) in &exprs.0 {
+        assert_eq!(name, "tick");
+        assert_eq!((span.start, span.end), (arg, arg + "tick".len()));
+    }
+    let mut ids: Vec<NodeId> = exprs.0.iter().map(|(_, _, id)| *id).collect();
+    ids.sort();
+    ids.dedup();
+    assert_eq!(ids.len(), 4, "copies of an argument share ids");
+
+    let StmtKind::Expr(expansion) = &body(&module).stmts[0].kind else {
+        panic!("expected an expression");
+    };
+    assert_eq!(
+        (expansion.span.start, expansion.span.end),
+        (call, call + "four(tick)".len())
+    );
+}
+
+#[test]
+fn test_macro_arity_and_recursion_errors() {
+    let (module, errors) = expand(
+        "defmacro twice(e) do\n    e\n    e\nend\ndefmacro forever() do\n    forever()\nend\n\
+         def f() do\n    twice(1, 2)\n    forever()\nend",
+    );
+    assert_eq!(
+        errors,
+        [
+            "macro `twice` takes 1 argument but 2 were given",
+            "recursion limit reached while expanding `forever`",
+        ]
+    );
+    assert_eq!(module.items.len(), 1);
+    let StmtKind::Expr(call) = &body(&module).stmts[0].kind else {
+        panic!("expected an expression");
+    };
+    assert_eq!(call.kind, ExprKind::Missing);
+}
@@ -0,0 +1,85 @@
+use rot::parser::ast::*;
+use rot::parser::fold::{self, Folder};
+use rot::parser::parser::parse_source;
+
+// folds `a - b` into `a + -b`
+struct Desugar;
+
+impl Folder for Desugar {
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        let expr = fold::fold_expr(self, expr);
+        match expr.kind {
+            ExprKind::Binary {
+                op: BinaryOp::Sub,
+                lhs,
+                rhs,
+            } => {
+                let negated = Expr {
+                    id: rhs.id,
+                    span: rhs.span,
+                    kind: ExprKind::Unary {
+                        op: UnaryOp::Neg,
+                        operand: rhs,
+                    },
+                };
+                Expr {
+                    id: expr.id,
+                    span: expr.span,
+                    kind: ExprKind::Binary {
+                        op: BinaryOp::Add,
+                        lhs,
+                        rhs: Box::new(negated),
+                    },
+                }
+            }
+            kind => Expr { kind, ..expr },
+        }
+    }
+}
+
+struct Identity;
+
+impl Folder for Identity {}
+
+#[test]
+fn test_identity_fold_preserves_module() {
+    let module = parse_source(
+        "type F = (x: i32) -> i32\ndefstruct P do\n    x: i32 = 1\nend\ndef f(p: P) -> i32 do\n    match p do\n        P{x: 1} | P{x: 2} -> 0\n        _ -> if p.x > 1 do p.x else -1 end\n    end\nend",
+    )
+    .into_result()
+    .unwrap();
+    assert_eq!(Identity.fold_module(module.clone()), module);
+}
+
+#[test]
+fn test_folder_rewrites_nodes() {
+    let module = parse_source("def f(a: i32) do\n    a - (a - 1)\nend")
+        .into_result()
+        .unwrap();
+    let module = Desugar.fold_module(module);
+    let decl = match &module.items[0].kind {
+        ItemKind::Function(decl) => decl,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    let expr = match &decl.body.stmts[0].kind {
+        StmtKind::Expr(expr) => expr,
+        other => panic!("expected an expression, got {:?}", other),
+    };
+    match &expr.kind {
+        ExprKind::Binary {
+            op: BinaryOp::Add,
+            rhs,
+            ..
+        } => match &rhs.kind {
+            ExprKind::Unary { operand, .. } => assert!(matches!(
+                operand.kind,
+                ExprKind::Binary {
+                    op: BinaryOp::Add,
+                    ..
+                }
+            )),
+            other => panic!("expected a negation, got {:?}", other),
+        },
+        other => panic!("expected an addition, got {:?}", other),
+    }
+}
@@ -0,0 +1,8 @@
+mod desugar;
+mod expand;
+mod fold;
+#[allow(clippy::module_inception)]
+mod parser;
+mod serialize;
+mod visit;
+mod visit_mut;
@@ -0,0 +1,886 @@
+use rot::parser::ast::*;
+use rot::parser::parser::parse_source;
+use rot::parser::serialize::to_sexpr;
+
+fn parse_items(source: &str) -> Vec<Item> {
+    match parse_source(source).into_result() {
+        Ok(module) => module.items,
+        Err(diags) => panic!("unexpected parse error: {}", diags[0]),
+    }
+}
+
+fn errors(source: &str) -> Vec<String> {
+    parse_source(source)
+        .diagnostics
+        .into_iter()
+        .map(|diag| diag.message)
+        .collect()
+}
+
+fn single_struct(source: &str) -> StructDecl {
+    let mut items = parse_items(source);
+    assert_eq!(items.len(), 1);
+    match items.remove(0).kind {
+        ItemKind::Struct(decl) => decl,
+        other => panic!("expected a struct, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_struct_fields_defaults_and_visibility() {
+    let decl = single_struct("defstruct Point do public x: i32 = 0; y: i32 end");
+    assert_eq!(decl.name.name, "Point");
+    assert_eq!(decl.fields.len(), 2);
+
+    let x = &decl.fields[0];
+    assert_eq!(x.name.name, "x");
+    assert_eq!(x.visibility, Visibility::Public);
+    assert_eq!(x.ty.kind, TypeKind::Primitive(PrimitiveType::I32));
+    match &x.default {
+        Some(Expr {
+            kind: ExprKind::Literal(Literal::Int(text)),
+            ..
+        }) => assert_eq!(text, "0"),
+        other => panic!("expected an int default, got {:?}", other),
+    }
+
+    let y = &decl.fields[1];
+    assert_eq!(y.visibility, Visibility::Private);
+    assert!(y.default.is_none());
+}
+
+#[test]
+fn test_struct_fields_on_separate_lines() {
+    let decl = single_struct(
+        r#"defstruct Person do
+    public name: string
+    age: u8 = 18
+    address: Address
+end"#,
+    );
+    let names: Vec<&str> = decl.fields.iter().map(|f| f.name.name.as_str()).collect();
+    assert_eq!(names, vec!["name", "age", "address"]);
+    match &decl.fields[2].ty.kind {
+        TypeKind::Named(ident) => assert_eq!(ident.name, "Address"),
+        other => panic!("expected a named type, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_struct_literal() {
+    let items = parse_items(
+        r#"def origin() -> Point do
+    return Point{x: 1, y: 2}
+end"#,
+    );
+    let body = match &items[0].kind {
+        ItemKind::Function(decl) => &decl.body,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    let value = match &body.stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Return(Some(value)),
+            ..
+        }) => value,
+        other => panic!("expected a return, got {:?}", other),
+    };
+    match &value.kind {
+        ExprKind::StructLit { name, fields } => {
+            assert_eq!(name.name, "Point");
+            let names: Vec<&str> = fields.iter().map(|f| f.name.name.as_str()).collect();
+            assert_eq!(names, vec!["x", "y"]);
+        }
+        other => panic!("expected a struct literal, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_struct_missing_field_type() {
+    assert_eq!(
+        errors("defstruct Point do x end"),
+        ["expected `:`, found `end`"]
+    );
+}
+
+#[test]
+fn test_binary_precedence() {
+    let items = parse_items("def f() do\n    x: i32 = 1 + 2 * 3\nend");
+    let value = match &items[0].kind {
+        ItemKind::Function(decl) => match &decl.body.stmts[0].kind {
+            StmtKind::Let(binding) => binding.value.clone(),
+            other => panic!("expected a let, got {:?}", other),
+        },
+        other => panic!("expected a function, got {:?}", other),
+    };
+    match value.kind {
+        ExprKind::Binary { op, rhs, .. } => {
+            assert_eq!(op, BinaryOp::Add);
+            assert!(matches!(
+                rhs.kind,
+                ExprKind::Binary {
+                    op: BinaryOp::Mul,
+                    ..
+                }
+            ));
+        }
+        other => panic!("expected a binary expression, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_impl_methods_and_static_functions() {
+    let items = parse_items(
+        r#"defimpl Point do
+    def len(self) -> double do
+        return self.x * self.x + self.y * self.y
+    end
+    def scale(self, by: i32) -> Point do
+        return Point{x: self.x * by, y: self.y * by}
+    end
+    static def origin() -> Point do
+        return Point{x: 0, y: 0}
+    end
+end"#,
+    );
+    let block = match &items[0].kind {
+        ItemKind::Impl(block) => block,
+        other => panic!("expected an impl block, got {:?}", other),
+    };
+    assert_eq!(block.target.name, "Point");
+    let shape: Vec<(&str, bool, bool, usize)> = block
+        .methods
+        .iter()
+        .map(|m| {
+            (
+                m.function.name.name.as_str(),
+                m.is_static,
+                m.function.receiver.is_some(),
+                m.function.params.len(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        shape,
+        vec![
+            ("len", false, true, 0),
+            ("scale", false, true, 1),
+            ("origin", true, false, 0)
+        ]
+    );
+}
+
+#[test]
+fn test_method_receiver_rules() {
+    assert_eq!(
+        errors("defimpl Point do\n    def len() -> i32 do\n        return 1\n    end\nend"),
+        ["method `len` needs a `self` receiver, use `static def` for associated functions"]
+    );
+    assert_eq!(
+        errors("defimpl Point do\n    static def origin(self) do\n    end\nend"),
+        ["static functions cannot take a `self` receiver"]
+    );
+    assert_eq!(
+        errors("def len(self) do\nend"),
+        ["a `self` receiver is only allowed on methods inside `defimpl`"]
+    );
+}
+
+#[test]
+fn test_method_and_static_calls() {
+    let items = parse_items("def main() do\n    p.len()\n    Point.origin().x\nend");
+    let stmts = match &items[0].kind {
+        ItemKind::Function(decl) => &decl.body.stmts,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    match &stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::MethodCall {
+                receiver, method, ..
+            },
+            ..
+        }) => {
+            assert_eq!(method.name, "len");
+            assert!(matches!(&receiver.kind, ExprKind::Ident(ident) if ident.name == "p"));
+        }
+        other => panic!("expected a method call, got {:?}", other),
+    }
+    match &stmts[1].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Field { base, name, .. },
+            ..
+        }) => {
+            assert_eq!(name.name, "x");
+            assert!(matches!(
+                &base.kind,
+                ExprKind::MethodCall { method, .. } if method.name == "origin"
+            ));
+        }
+        other => panic!("expected a field access, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_lambda_params_and_types() {
+    let items = parse_items(
+        "def f() do\n    some_lambda = fn x, y do x + y end\n    typed = fn a: i32, b -> i64 do a end\nend",
+    );
+    let stmts = match &items[0].kind {
+        ItemKind::Function(decl) => &decl.body.stmts,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    let lambdas: Vec<&Lambda> = stmts
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Assign { value, .. },
+                ..
+            }) => match &value.kind {
+                ExprKind::Lambda(lambda) => lambda,
+                other => panic!("expected a lambda, got {:?}", other),
+            },
+            other => panic!("expected an assignment, got {:?}", other),
+        })
+        .collect();
+    let untyped = lambdas[0];
+    assert_eq!(untyped.params.len(), 2);
+    assert!(untyped.params.iter().all(|p| p.ty.is_none()));
+    assert!(untyped.return_type.is_none());
+    assert_eq!(untyped.body.stmts.len(), 1);
+
+    let typed = lambdas[1];
+    assert_eq!(
+        typed.params[0].ty.as_ref().map(|t| t.kind.clone()),
+        Some(TypeKind::Primitive(PrimitiveType::I32))
+    );
+    assert!(typed.params[1].ty.is_none());
+    assert_eq!(
+        typed.return_type.as_ref().map(|t| t.kind.clone()),
+        Some(TypeKind::Primitive(PrimitiveType::I64))
+    );
+}
+
+#[test]
+fn test_if_else_if_chain() {
+    let items = parse_items(
+        r#"def f(x: i32) do
+    y = if x == 1 do
+        print("one")
+        1
+    else if x == 2 do 2 else if x == 3 do 3
+    else
+        0
+    end
+end"#,
+    );
+    let stmts = match &items[0].kind {
+        ItemKind::Function(decl) => &decl.body.stmts,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    let if_expr = match &stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Assign { value, .. },
+            ..
+        }) => match &value.kind {
+            ExprKind::If(if_expr) => if_expr.clone(),
+            other => panic!("expected an if, got {:?}", other),
+        },
+        other => panic!("expected an assignment, got {:?}", other),
+    };
+    assert_eq!(if_expr.branches.len(), 3);
+    assert_eq!(if_expr.branches[0].block.stmts.len(), 2);
+    assert_eq!(if_expr.else_block.map(|b| b.stmts.len()), Some(1));
+}
+
+fn first_stmt_expr(source: &str) -> Expr {
+    let items = parse_items(source);
+    match &items[0].kind {
+        ItemKind::Function(decl) => match &decl.body.stmts[0].kind {
+            StmtKind::Expr(expr) => expr.clone(),
+            other => panic!("expected an expression statement, got {:?}", other),
+        },
+        other => panic!("expected a function, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_match_patterns_and_guards() {
+    let expr = first_stmt_expr(
+        r#"def f(v: Point) do
+    match v do
+        Point{x: 0, y} if y > 0 -> 1
+        Point{x, y: _} -> x
+        (a, -1) | (a, 2) -> a
+        null -> 0
+        "text" -> do
+            print("text")
+            2
+        end
+        _ -> 3
+    end
+end"#,
+    );
+    let arms = match expr.kind {
+        ExprKind::Match(match_expr) => match_expr.arms,
+        other => panic!("expected a match, got {:?}", other),
+    };
+    assert_eq!(arms.len(), 6);
+
+    match &arms[0].pattern.kind {
+        PatternKind::Struct { name, fields } => {
+            assert_eq!(name.name, "Point");
+            assert_eq!(
+                fields[0].pattern.kind,
+                PatternKind::Literal(Literal::Int("0".to_string()))
+            );
+            assert!(matches!(&fields[1].pattern.kind, PatternKind::Binding(i) if i.name == "y"));
+        }
+        other => panic!("expected a struct pattern, got {:?}", other),
+    }
+    assert!(arms[0].guard.is_some());
+    assert!(arms[1].guard.is_none());
+    let bound: Vec<&str> = arms[1]
+        .pattern
+        .bindings()
+        .iter()
+        .map(|i| i.name.as_str())
+        .collect();
+    assert_eq!(bound, vec!["x"]);
+
+    match &arms[2].pattern.kind {
+        PatternKind::Or(alternatives) => {
+            assert_eq!(alternatives.len(), 2);
+            match &alternatives[0].kind {
+                PatternKind::Tuple(elements) => assert_eq!(
+                    elements[1].kind,
+                    PatternKind::Literal(Literal::Int("-1".to_string()))
+                ),
+                other => panic!("expected a tuple pattern, got {:?}", other),
+            }
+        }
+        other => panic!("expected alternatives, got {:?}", other),
+    }
+    assert_eq!(arms[3].pattern.kind, PatternKind::Literal(Literal::Null));
+    assert!(matches!(&arms[4].body.kind, ExprKind::Block(block) if block.stmts.len() == 2));
+    assert_eq!(arms[5].pattern.kind, PatternKind::Wildcard);
+}
+
+#[test]
+fn test_match_arm_needs_arrow() {
+    assert_eq!(
+        errors("def f(v: i32) do\n    match v do\n        1 2\n    end\nend"),
+        ["expected `->`, found `2`"]
+    );
+}
+
+#[test]
+fn test_loops_labels_and_break_values() {
+    let expr = first_stmt_expr(
+        r#"def f(xs: Items) do
+    found = outer: for (k, v) in xs do
+        while v > 0 do
+            if v == 3 do break outer k end
+            v = v - 1
+            continue
+        end
+        break
+    end
+end"#,
+    );
+    let value = match expr.kind {
+        ExprKind::Assign { value, .. } => value,
+        other => panic!("expected an assignment, got {:?}", other),
+    };
+    let for_expr = match value.kind {
+        ExprKind::For(for_expr) => for_expr,
+        other => panic!("expected a for loop, got {:?}", other),
+    };
+    assert_eq!(for_expr.label.map(|l| l.name), Some("outer".to_string()));
+    assert!(matches!(for_expr.pattern.kind, PatternKind::Tuple(ref e) if e.len() == 2));
+    assert_eq!(for_expr.body.stmts.len(), 2);
+
+    let while_expr = match &for_expr.body.stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::While(while_expr),
+            ..
+        }) => while_expr.clone(),
+        other => panic!("expected a while loop, got {:?}", other),
+    };
+    assert!(while_expr.label.is_none());
+    let branch = match &while_expr.body.stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::If(if_expr),
+            ..
+        }) => if_expr.branches[0].block.clone(),
+        other => panic!("expected an if, got {:?}", other),
+    };
+    match &branch.stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Break { label, value },
+            ..
+        }) => {
+            assert_eq!(label.as_ref().map(|l| l.name.as_str()), Some("outer"));
+            assert!(
+                matches!(value.as_deref().map(|v| &v.kind), Some(ExprKind::Ident(i)) if i.name == "k")
+            );
+        }
+        other => panic!("expected a labeled break, got {:?}", other),
+    }
+    assert!(matches!(
+        &while_expr.body.stmts[2].kind,
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Continue { label: None },
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_break_names_only_enclosing_labels() {
+    let expr = first_stmt_expr("def f() do\n    while true do break outer end\nend");
+    let body = match expr.kind {
+        ExprKind::While(while_expr) => while_expr.body,
+        other => panic!("expected a while loop, got {:?}", other),
+    };
+    match &body.stmts[0].kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Break { label, value },
+            ..
+        }) => {
+            assert!(label.is_none());
+            assert!(value.is_some());
+        }
+        other => panic!("expected a break, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_recovery_reports_every_statement_error() {
+    let source = "def f() do\n    x = )\n    y: i32 = 1 1\n    z = 2\nend";
+    let result = parse_source(source);
+    let messages: Vec<&str> = result
+        .diagnostics
+        .iter()
+        .map(|d| d.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "expected an expression, found `)`",
+            "expected `;` or a new line, found `1`"
+        ]
+    );
+    let items = result.module.items;
+    assert_eq!(items.len(), 1);
+    match &items[0].kind {
+        ItemKind::Function(decl) => assert_eq!(decl.body.stmts.len(), 3),
+        other => panic!("expected a function, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_recovery_resumes_at_next_item() {
+    let source = "def f(x) do\n    x\nend\ndefstruct P do\n    x: i32\nend\ntype N = i32";
+    let result = parse_source(source);
+    assert_eq!(result.diagnostics.len(), 1);
+    assert_eq!(result.diagnostics[0].message, "expected `:`, found `)`");
+    let items = result.module.items;
+    assert_eq!(items.len(), 2);
+    assert!(matches!(items[0].kind, ItemKind::Struct(_)));
+    assert!(matches!(items[1].kind, ItemKind::TypeAlias(_)));
+}
+
+#[test]
+fn test_recovery_assumes_missing_end() {
+    let result = parse_source("def f() do\n    if true do\n        1\n    end\n\ndef g() do\nend");
+    assert_eq!(
+        result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect::<Vec<_>>(),
+        ["expected `end`, found `def`"]
+    );
+    assert_eq!(result.module.items.len(), 2);
+
+    let result = parse_source("defstruct P do\n    x: i32");
+    assert_eq!(
+        result.diagnostics[0].message,
+        "expected `end`, found end of file"
+    );
+    assert_eq!(result.module.items.len(), 1);
+}
+
+#[test]
+fn test_recovery_inserts_missing_nodes() {
+    let result = parse_source("def f() do\n    x: = 1 +\nend");
+    assert_eq!(result.diagnostics.len(), 2);
+    let decl = match &result.module.items[0].kind {
+        ItemKind::Function(decl) => decl,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    match &decl.body.stmts[0].kind {
+        StmtKind::Let(binding) => {
+            assert_eq!(binding.ty.as_ref().unwrap().kind, TypeKind::Missing);
+            match &binding.value.kind {
+                ExprKind::Binary { rhs, .. } => assert_eq!(rhs.kind, ExprKind::Missing),
+                other => panic!("expected a binary expression, got {:?}", other),
+            }
+        }
+        other => panic!("expected a let, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_generic_params_and_type_arguments() {
+    let module = parse_source(
+        "defstruct Box<T> do\n    value: T\nend\ntype Pair<A, B> = Map<A, List<B>>\ndef show<T: Show + Eq>(x: Box<T>) do\nend",
+    )
+    .into_result()
+    .unwrap();
+    let expected = r#"(Module
+  (Struct :name "Box"
+    (GenericParam :name "T")
+    (StructField :name "value" :public false :mutable false
+      (NamedType :name "T")))
+  (TypeAlias :name "Pair"
+    (GenericParam :name "A")
+    (GenericParam :name "B")
+    (GenericType :name "Map"
+      (NamedType :name "A")
+      (GenericType :name "List"
+        (NamedType :name "B"))))
+  (Function :name "show"
+    (GenericParam :name "T"
+      (NamedType :name "Show")
+      (NamedType :name "Eq"))
+    (Param :name "x" :mutable false
+      (GenericType :name "Box"
+        (NamedType :name "T")))
+    (Block)))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_angle_brackets_in_expressions_compare() {
+    let items = parse_items("def f() do\n    x: Box<i32> = g(a < b, c > d)\nend");
+    let body = match &items[0].kind {
+        ItemKind::Function(decl) => &decl.body,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    let args = match &body.stmts[0].kind {
+        StmtKind::Let(LetStmt {
+            value:
+                Expr {
+                    kind: ExprKind::Call { args, .. },
+                    ..
+                },
+            ..
+        }) => args,
+        other => panic!("expected a call, got {:?}", other),
+    };
+    let ops: Vec<BinaryOp> = args
+        .iter()
+        .map(|arg| match &arg.kind {
+            ExprKind::Binary { op, .. } => *op,
+            other => panic!("expected a comparison, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(ops, [BinaryOp::Lt, BinaryOp::Gt]);
+}
+
+#[test]
+fn test_empty_generic_lists_are_errors() {
+    assert_eq!(
+        errors("def f<>() do end"),
+        ["expected a type parameter, found `>`"]
+    );
+    assert_eq!(
+        errors("def f() do\n    x: Box<> = 1\nend"),
+        ["expected a type, found `>`"]
+    );
+}
+
+#[test]
+fn test_use_declarations() {
+    let items = parse_items("use geometry.shapes\nuse util.{id, max}; def f() do end");
+    let uses: Vec<(String, Option<Vec<String>>)> = items
+        .iter()
+        .filter_map(|item| match &item.kind {
+            ItemKind::Use(decl) => Some((
+                decl.module_name(),
+                decl.names
+                    .as_ref()
+                    .map(|names| names.iter().map(|n| n.name.clone()).collect()),
+            )),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        uses,
+        [
+            ("geometry.shapes".to_string(), None),
+            (
+                "util".to_string(),
+                Some(vec!["id".to_string(), "max".to_string()])
+            ),
+        ]
+    );
+    assert_eq!(items.len(), 3);
+    assert_eq!(
+        errors("use a.\ndef f() do end"),
+        ["expected a module name, found `def`"]
+    );
+}
+
+#[test]
+fn test_collection_literals_and_types() {
+    let module = parse_source(
+        "def f(m: {string => [i32]}) -> (i32, boolean) do\n    g([1, 2], {\"a\" => (1,)}, (), xs[0])\nend",
+    )
+    .into_result()
+    .unwrap();
+    let expected = r#"(Module
+  (Function :name "f"
+    (Param :name "m" :mutable false
+      (MapType
+        (PrimitiveType :type "string")
+        (ListType
+          (PrimitiveType :type "i32"))))
+    (TupleType
+      (PrimitiveType :type "i32")
+      (PrimitiveType :type "boolean"))
+    (Block
+      (ExprStmt
+        (Call
+          (Ident :name "g")
+          (List
+            (Literal :literal "int" :value "1")
+            (Literal :literal "int" :value "2"))
+          (Map
+            (MapEntry
+              (Literal :literal "string" :value "a")
+              (Tuple
+                (Literal :literal "int" :value "1"))))
+          (Tuple)
+          (Index
+            (Ident :name "xs")
+            (Literal :literal "int" :value "0")))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_brackets_on_a_new_line_start_a_statement() {
+    let items = parse_items("def f() do\n    g\n    [1]\n    (2)\nend");
+    let body = match &items[0].kind {
+        ItemKind::Function(decl) => &decl.body,
+        other => panic!("expected a function, got {:?}", other),
+    };
+    assert_eq!(body.stmts.len(), 3);
+    assert_eq!(
+        errors("def f() do\n    {1 2}\nend"),
+        ["expected `=>`, found `2`"]
+    );
+}
+
+#[test]
+fn test_nullable_types_safe_navigation_and_elvis() {
+    let module = parse_source(
+        "def f(p: Person?, g: ((i32) -> i32)?) -> [string?]? do\n    p?.name ?: q?.nick() ?: \"x\"\n    a == b ?: c + 1\nend",
+    )
+    .into_result()
+    .unwrap();
+    let expected = r#"(Module
+  (Function :name "f"
+    (Param :name "p" :mutable false
+      (NullableType
+        (NamedType :name "Person")))
+    (Param :name "g" :mutable false
+      (NullableType
+        (FunctionType
+          (FunctionTypeParam
+            (PrimitiveType :type "i32"))
+          (PrimitiveType :type "i32"))))
+    (NullableType
+      (ListType
+        (NullableType
+          (PrimitiveType :type "string"))))
+    (Block
+      (ExprStmt
+        (Binary :op "?:"
+          (Field :name "name" :safe true
+            (Ident :name "p"))
+          (Binary :op "?:"
+            (MethodCall :name "nick" :safe true
+              (Ident :name "q"))
+            (Literal :literal "string" :value "x"))))
+      (ExprStmt
+        (Binary :op "=="
+          (Ident :name "a")
+          (Binary :op "?:"
+            (Ident :name "b")
+            (Binary :op "+"
+              (Ident :name "c")
+              (Literal :literal "int" :value "1"))))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_mut_declarations_and_compound_assignment() {
+    let module = parse_source(
+        "defstruct C do\n    public mut n: i32\nend\ndef f(mut a: i32) do\n    mut x = 1\n    x += a\n    x -= 1\n    x *= 2\n    x /= 3\nend",
+    )
+    .into_result()
+    .unwrap();
+    let expected = r#"(Module
+  (Struct :name "C"
+    (StructField :name "n" :public true :mutable true
+      (PrimitiveType :type "i32")))
+  (Function :name "f"
+    (Param :name "a" :mutable true
+      (PrimitiveType :type "i32"))
+    (Block
+      (Let :name "x" :mutable true
+        (Literal :literal "int" :value "1"))
+      (ExprStmt
+        (Assign :op "+="
+          (Ident :name "x")
+          (Ident :name "a")))
+      (ExprStmt
+        (Assign :op "-="
+          (Ident :name "x")
+          (Literal :literal "int" :value "1")))
+      (ExprStmt
+        (Assign :op "*="
+          (Ident :name "x")
+          (Literal :literal "int" :value "2")))
+      (ExprStmt
+        (Assign :op "/="
+          (Ident :name "x")
+          (Literal :literal "int" :value "3"))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_safe_navigation_is_not_assignable() {
+    let result = parse_source("def f() do\n    p?.x = 1\nend");
+    let messages: Vec<&str> = result
+        .diagnostics
+        .iter()
+        .map(|diag| diag.message.as_str())
+        .collect();
+    assert_eq!(messages, vec!["invalid left-hand side of assignment"]);
+}
+
+#[test]
+fn test_pipelines() {
+    let module =
+        parse_source("def f() do\n    a + b |> f(c) |> g == d\n    xs\n    |> s.push()\nend")
+            .into_result()
+            .unwrap();
+    let expected = r#"(Module
+  (Function :name "f"
+    (Block
+      (ExprStmt
+        (Binary :op "=="
+          (Binary :op "|>"
+            (Binary :op "|>"
+              (Binary :op "+"
+                (Ident :name "a")
+                (Ident :name "b"))
+              (Call
+                (Ident :name "f")
+                (Ident :name "c")))
+            (Ident :name "g"))
+          (Ident :name "d")))
+      (ExprStmt
+        (Binary :op "|>"
+          (Ident :name "xs")
+          (MethodCall :name "push" :safe false
+            (Ident :name "s")))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_pipeline_needs_a_call() {
+    let result = parse_source("def f() do\n    x |> 1 + 2\nend");
+    let messages: Vec<&str> = result
+        .diagnostics
+        .iter()
+        .map(|diag| diag.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        vec!["the right side of `|>` must be a call or a function name"]
+    );
+}
+
+#[test]
+fn test_casts_bind_tighter_than_binary_operators() {
+    let module = parse_source("def f() do\n    a + -b as u8 * c as i64?\nend")
+        .into_result()
+        .unwrap();
+    let expected = r#"(Module
+  (Function :name "f"
+    (Block
+      (ExprStmt
+        (Binary :op "+"
+          (Ident :name "a")
+          (Binary :op "*"
+            (Cast
+              (Unary :op "-"
+                (Ident :name "b"))
+              (PrimitiveType :type "u8"))
+            (Cast
+              (Ident :name "c")
+              (NullableType
+                (PrimitiveType :type "i64")))))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_macro_definitions() {
+    let module =
+        parse_source("defmacro unless(cond, then) do\n    if !cond do\n        then\n    end\nend")
+            .into_result()
+            .unwrap();
+    let expected = r#"(Module
+  (Macro :name "unless"
+    (MacroParam :name "cond")
+    (MacroParam :name "then")
+    (Block
+      (ExprStmt
+        (If
+          (IfBranch
+            (Unary :op "!"
+              (Ident :name "cond"))
+            (Block
+              (ExprStmt
+                (Ident :name "then")))))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_recovery_skips_stray_closers_opening_a_line() {
+    for closer in [",", ")", "]", "}", "=>", "->", "else"] {
+        let source = format!("def f() do\n    x\n    {} y\n    z\nend\n", closer);
+        let result = parse_source(&source);
+        let messages: Vec<&str> = result
+            .diagnostics
+            .iter()
+            .map(|d| d.message.as_str())
+            .collect();
+        let expected = format!("expected an expression, found `{}`", closer);
+        assert_eq!(messages, [expected.as_str()], "{}", closer);
+        match &result.module.items[0].kind {
+            ItemKind::Function(decl) => assert_eq!(decl.body.stmts.len(), 2, "{}", closer),
+            other => panic!("expected a function, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use rot::parser::parser::parse_source;
+use rot::parser::serialize::{to_json, to_sexpr, SCHEMA_VERSION};
+
+#[test]
+fn test_sexpr_dump() {
+    let module = parse_source("def f(a: i32) do\n    s: str = \"hi\"\n    -a + 1\nend")
+        .into_result()
+        .unwrap();
+    let expected = r#"(Module
+  (Function :name "f"
+    (Param :name "a" :mutable false
+      (PrimitiveType :type "i32"))
+    (Block
+      (Let :name "s" :mutable false
+        (NamedType :name "str")
+        (Literal :literal "string" :value "hi"))
+      (ExprStmt
+        (Binary :op "+"
+          (Unary :op "-"
+            (Ident :name "a"))
+          (Literal :literal "int" :value "1"))))))
+"#;
+    assert_eq!(to_sexpr(&module), expected);
+}
+
+#[test]
+fn test_json_dump() {
+    let result = parse_source("def f() do\n    p.x\nend\n");
+    let expected = concat!(
+        r#"{"version":1,"module":{"kind":"Module","span":[0,22],"children":["#,
+        r#"{"kind":"Function","role":"item","span":[0,22],"name":"f","children":["#,
+        r#"{"kind":"Block","role":"body","span":[8,22],"children":["#,
+        r#"{"kind":"ExprStmt","role":"stmt","span":[15,18],"children":["#,
+        r#"{"kind":"Field","role":"expr","span":[15,18],"id":1,"name":"x","safe":false,"children":["#,
+        r#"{"kind":"Ident","role":"base","span":[15,16],"id":0,"name":"p","children":[]}"#,
+        r#"]}]}]}]}]},"diagnostics":[]}"#
+    );
+    assert_eq!(SCHEMA_VERSION, 1);
+    assert_eq!(to_json(&result.module, &result.diagnostics), expected);
+}
+
+#[test]
+fn test_json_includes_diagnostics() {
+    let result = parse_source("x");
+    assert_eq!(
+        to_json(&result.module, &result.diagnostics),
+        concat!(
+            r#"{"version":1,"module":{"kind":"Module","span":[0,1],"children":[]},"#,
+            r#""diagnostics":[{"severity":"error","message":"expected `def`, `defstruct`, `defimpl`, `defmacro`, `type` or `use`, found `x`","span":[0,1]}]}"#
+        )
+    );
+}
+
+#[test]
+fn test_json_escapes_strings() {
+    let result = parse_source("def f() do\n    print(\"a\\\"b\")\nend");
+    let json = to_json(&result.module, &result.diagnostics);
+    assert!(json.contains(r#""kind":"Literal","role":"arg","span":[21,27],"id":1,"literal":"string","value":"a\\\"b""#), "{}", json);
+    assert!(json.ends_with(r#""diagnostics":[]}"#));
+}
+
+#[test]
+fn test_parse_command_prints_only_json_on_lex_errors() {
+    let source = "def f() do\n    s = \"abc\nend\n";
+    let path = std::env::temp_dir().join(format!("rot-parse-{}.rot", std::process::id()));
+    std::fs::write(&path, source).unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_rot"))
+        .args(["parse", "--format", "json"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let result = parse_source(source);
+    assert_eq!(
+        result.diagnostics[0].message,
+        "found newline in possible string"
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        format!("{}\n", to_json(&result.module, &result.diagnostics))
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
@@ -0,0 +1,58 @@
+use rot::parser::ast::*;
+use rot::parser::parser::parse_source;
+use rot::parser::visit::{self, Visitor};
+
+#[derive(Default)]
+struct CallCounter {
+    calls: Vec<String>,
+    literals: usize,
+}
+
+impl Visitor for CallCounter {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Call { callee, .. } = &expr.kind {
+            if let ExprKind::Ident(name) = &callee.kind {
+                self.calls.push(name.name.clone());
+            }
+        }
+        visit::walk_expr(self, expr);
+    }
+
+    fn visit_literal(&mut self, _literal: &Literal) {
+        self.literals += 1;
+    }
+}
+
+#[test]
+fn test_visitor_reaches_nested_expressions() {
+    let module = parse_source(
+        "defstruct P do\n    x: i32 = seed(1)\nend\ndef f(v: i32) do\n    g = fn x do print(x) end\n    match v do\n        1 if check(v) -> log(2)\n        _ -> 0\n    end\n    while ok() do\n        for y in items() do step(y) end\n    end\nend",
+    )
+    .into_result()
+    .unwrap();
+    let mut counter = CallCounter::default();
+    counter.visit_module(&module);
+    assert_eq!(
+        counter.calls,
+        ["seed", "print", "check", "log", "ok", "items", "step"]
+    );
+    assert_eq!(counter.literals, 4);
+}
+
+#[test]
+fn test_overriding_without_walking_prunes_subtree() {
+    struct SkipLambdas(usize);
+    impl Visitor for SkipLambdas {
+        fn visit_lambda(&mut self, _lambda: &Lambda) {}
+        fn visit_ident(&mut self, _ident: &Ident) {
+            self.0 += 1;
+        }
+    }
+    let module = parse_source("def f() do\n    g = fn x do x + y end\nend")
+        .into_result()
+        .unwrap();
+    let mut visitor = SkipLambdas(0);
+    visitor.visit_module(&module);
+    // only `f` and `g`
+    assert_eq!(visitor.0, 2);
+}
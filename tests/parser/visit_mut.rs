@@ -0,0 +1,34 @@
+use rot::parser::ast::*;
+use rot::parser::parser::parse_source;
+use rot::parser::visit_mut::{self, VisitorMut};
+
+struct Rename;
+
+impl VisitorMut for Rename {
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let ExprKind::Ident(ident) = &mut expr.kind {
+            if ident.name == "old" {
+                ident.name = "new".to_string();
+            }
+        }
+        visit_mut::walk_expr_mut(self, expr);
+    }
+}
+
+#[test]
+fn test_visitor_mut_edits_in_place() {
+    let mut module = parse_source("def f(old: i32) do\n    old + fn do old end()\nend")
+        .into_result()
+        .unwrap();
+    Rename.visit_module_mut(&mut module);
+    let mut names = Vec::new();
+    struct Collect<'a>(&'a mut Vec<String>);
+    impl VisitorMut for Collect<'_> {
+        fn visit_ident_mut(&mut self, ident: &mut Ident) {
+            self.0.push(ident.name.clone());
+        }
+    }
+    Collect(&mut names).visit_module_mut(&mut module);
+    // the parameter declaration is not an expression and keeps its name
+    assert_eq!(names, ["f", "old", "new", "new"]);
+}
@@ -0,0 +1,92 @@
+use rot::parser::ast::*;
+use rot::parser::parser::parse_source;
+use rot::semantic_analyzer::captures::{compute_captures, CaptureMap};
+use rot::semantic_analyzer::resolve::resolve_names;
+
+fn lambda_ids(expr: &Expr, ids: &mut Vec<NodeId>) {
+    match &expr.kind {
+        ExprKind::Lambda(lambda) => {
+            ids.push(expr.id);
+            for stmt in &lambda.body.stmts {
+                stmt_lambda_ids(stmt, ids);
+            }
+        }
+        ExprKind::Assign { value, .. } => lambda_ids(value, ids),
+        ExprKind::Call { args, .. } => args.iter().for_each(|arg| lambda_ids(arg, ids)),
+        _ => {}
+    }
+}
+
+fn stmt_lambda_ids(stmt: &Stmt, ids: &mut Vec<NodeId>) {
+    match &stmt.kind {
+        StmtKind::Let(binding) => lambda_ids(&binding.value, ids),
+        StmtKind::Expr(expr) => lambda_ids(expr, ids),
+    }
+}
+
+// captured names per lambda, in source order
+fn captures_of(source: &str) -> Vec<Vec<String>> {
+    let module = parse_source(source).into_result().unwrap();
+    let resolution = resolve_names(&module);
+    let captures: CaptureMap = compute_captures(&module, &resolution);
+    let mut ids = Vec::new();
+    for item in &module.items {
+        if let ItemKind::Function(decl) = &item.kind {
+            decl.body
+                .stmts
+                .iter()
+                .for_each(|stmt| stmt_lambda_ids(stmt, &mut ids));
+        }
+    }
+    ids.iter()
+        .map(|id| captures[id].iter().map(|c| c.name.clone()).collect())
+        .collect()
+}
+
+#[test]
+fn test_lambda_without_captures() {
+    let captures = captures_of("def f() do\n    some_lambda = fn x, y do x + y end\nend");
+    assert_eq!(captures, vec![Vec::<String>::new()]);
+}
+
+#[test]
+fn test_lambda_captures_params_and_locals() {
+    let captures = captures_of(
+        r#"def f(base: i32) do
+    scale: i32 = 2
+    add = fn x: i32 -> i32 do
+        tmp = x * scale
+        tmp + base
+    end
+end"#,
+    );
+    assert_eq!(
+        captures,
+        vec![vec!["scale".to_string(), "base".to_string()]]
+    );
+}
+
+#[test]
+fn test_nested_lambda_captures_propagate() {
+    let captures = captures_of(
+        r#"def f(a: i32) do
+    outer = fn x do
+        b = x
+        fn y do a + b + y end
+    end
+end"#,
+    );
+    assert_eq!(
+        captures,
+        vec![
+            vec!["a".to_string()],
+            vec!["a".to_string(), "b".to_string()]
+        ]
+    );
+}
+
+#[test]
+fn test_globals_are_not_captured() {
+    let captures = captures_of("def f() do\n    g = fn x do println(x) end\nend");
+    assert_eq!(captures, vec![Vec::<String>::new()]);
+}
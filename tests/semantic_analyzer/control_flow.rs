@@ -0,0 +1,52 @@
+use rot::parser::parser::parse_source;
+use rot::semantic_analyzer::control_flow::check_control_flow;
+
+fn control_flow_errors(source: &str) -> Vec<String> {
+    let module = parse_source(source).into_result().unwrap();
+    check_control_flow(&module)
+        .into_iter()
+        .map(|diag| diag.message)
+        .collect()
+}
+
+#[test]
+fn test_break_and_continue_inside_loops() {
+    let errors = control_flow_errors(
+        r#"def f(xs: Items) do
+    outer: while true do
+        for x in xs do
+            if x == 1 do continue outer end
+            break outer
+        end
+        break
+    end
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_break_outside_loop() {
+    let errors = control_flow_errors(
+        r#"def f() do
+    break
+    if true do continue end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec!["`break` outside of a loop", "`continue` outside of a loop"]
+    );
+}
+
+#[test]
+fn test_break_does_not_cross_lambdas() {
+    let errors = control_flow_errors(
+        r#"def f() do
+    while true do
+        g = fn do break end
+    end
+end"#,
+    );
+    assert_eq!(errors, vec!["`break` outside of a loop"]);
+}
@@ -0,0 +1,5 @@
+mod captures;
+mod control_flow;
+mod mutability;
+mod resolve;
+mod type_checker;
@@ -0,0 +1,131 @@
+use rot::diagnostics::diagnostic::Diagnostic;
+use rot::parser::parser::parse_source;
+use rot::semantic_analyzer::mutability::check_mutability;
+use rot::semantic_analyzer::type_checker::check_module;
+
+fn mutability_errors(source: &str) -> Vec<Diagnostic> {
+    let module = parse_source(source).into_result().unwrap();
+    let types = check_module(&module);
+    check_mutability(&module, &types)
+}
+
+fn messages(errors: &[Diagnostic]) -> Vec<&str> {
+    errors.iter().map(|diag| diag.message.as_str()).collect()
+}
+
+#[test]
+fn test_mut_bindings_params_and_fields_can_be_assigned() {
+    let errors = mutability_errors(
+        r#"defstruct Counter do
+    mut count: i32
+end
+
+defimpl Counter do
+    def bump(self, mut by: i32) do
+        by *= 2
+        self.count += by
+    end
+end
+
+def f(c: Counter) do
+    mut x: u8 = 1
+    x += 1
+    mut total = 0
+    each = fn y do total = total + y end
+    c.count = 0
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", messages(&errors));
+}
+
+#[test]
+fn test_assignments_to_immutable_places() {
+    let source = r#"defstruct Point do
+    x: i32
+end
+
+def f(p: Point, n: i32) do
+    x: u8 = 1
+    x += 1
+    y = 2
+    y = 3
+    n -= 1
+    p.x = 4
+    for i in [1, 2] do
+        i = 0
+    end
+end"#;
+    let errors = mutability_errors(source);
+    assert_eq!(
+        messages(&errors),
+        vec![
+            "cannot assign twice to immutable binding `x`",
+            "cannot assign twice to immutable binding `y`",
+            "cannot assign to parameter `n`",
+            "cannot assign to field `x` of `Point`, it is not declared `mut`",
+            "cannot assign to immutable binding `i`",
+        ]
+    );
+    // every error points back at the declaration
+    let notes: Vec<(&str, usize)> = errors
+        .iter()
+        .map(|diag| (diag.notes[0].message.as_str(), diag.notes[0].span.start))
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            (
+                "`x` is declared here, write `mut x` to allow it",
+                source.find("x: u8").unwrap()
+            ),
+            (
+                "`y` is first assigned here, declare it with `mut y = ...` to allow it",
+                source.find("y = 2").unwrap()
+            ),
+            (
+                "`n` is declared here, write `mut n` to allow it",
+                source.find("n: i32").unwrap()
+            ),
+            ("`x` is declared here", source.find("x: i32").unwrap()),
+            ("`i` is bound here", source.find("i in").unwrap()),
+        ]
+    );
+}
+
+#[test]
+fn test_self_cannot_be_reassigned() {
+    let errors = mutability_errors(
+        r#"defstruct P do
+    x: i32
+end
+
+defimpl P do
+    def reset(self) do
+        self = P{x: 0}
+    end
+end"#,
+    );
+    assert_eq!(messages(&errors), vec!["cannot assign to `self`"]);
+}
+
+#[test]
+fn test_assigning_a_function_name_defines_a_local() {
+    let source = r#"def x() -> boolean do
+    true
+end
+
+def f() do
+    x = 1
+    x = 2
+    print = 5
+    n: i32 = x + print
+end"#;
+    let errors = mutability_errors(source);
+    assert_eq!(
+        messages(&errors),
+        vec!["cannot assign twice to immutable binding `x`"]
+    );
+    assert_eq!(errors[0].notes[0].span.start, source.find("x = 1").unwrap());
+    let module = parse_source(source).into_result().unwrap();
+    assert!(check_module(&module).diagnostics.is_empty());
+}
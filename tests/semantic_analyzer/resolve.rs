@@ -0,0 +1,211 @@
+use rot::parser::ast::*;
+use rot::parser::parser::parse_source;
+use rot::semantic_analyzer::resolve::{resolve_names, DefKind, Resolution};
+
+fn resolve(source: &str) -> (Module, Resolution) {
+    let module = parse_source(source).into_result().unwrap();
+    let resolution = resolve_names(&module);
+    (module, resolution)
+}
+
+fn resolve_errors(source: &str) -> Vec<String> {
+    resolve(source)
+        .1
+        .diagnostics
+        .into_iter()
+        .map(|diag| diag.message)
+        .collect()
+}
+
+// the arguments of the call in the last statement of function `f`
+fn call_args(module: &Module) -> &[Expr] {
+    let decl = module
+        .items
+        .iter()
+        .find_map(|item| match &item.kind {
+            ItemKind::Function(decl) if decl.name.name == "f" => Some(decl),
+            _ => None,
+        })
+        .unwrap();
+    match &decl.body.stmts.last().unwrap().kind {
+        StmtKind::Expr(Expr {
+            kind: ExprKind::Call { args, .. },
+            ..
+        }) => args,
+        stmt => panic!("expected a call, found {:?}", stmt),
+    }
+}
+
+#[test]
+fn test_identifiers_resolve_to_their_definitions() {
+    let source = r#"defstruct Point do
+    x: i32
+end
+def f(p: Point, n: i32) do
+    total = n
+    println(p, total, fn k do k end)
+end"#;
+    let (module, resolution) = resolve(source);
+    assert!(
+        resolution.diagnostics.is_empty(),
+        "{:?}",
+        resolution.diagnostics
+    );
+    let def = |expr: &Expr| resolution.value(expr.id).unwrap();
+    let args = call_args(&module);
+    assert_eq!(def(&args[0]).kind, DefKind::Param);
+    assert_eq!(def(&args[0]).span.start, source.find("p: Point").unwrap());
+    assert_eq!(def(&args[1]).kind, DefKind::Assigned);
+    assert_eq!(def(&args[1]).span.start, source.find("total =").unwrap());
+    let ExprKind::Lambda(lambda) = &args[2].kind else {
+        panic!("expected a lambda");
+    };
+    let StmtKind::Expr(k) = &lambda.body.stmts[0].kind else {
+        panic!("expected an expression");
+    };
+    assert_eq!(def(k).kind, DefKind::LambdaParam);
+
+    let point = source.find("Point,").unwrap();
+    let ty = resolution
+        .types
+        .iter()
+        .find(|(span, _)| span.start == point);
+    let (_, ty) = ty.expect("`Point` is resolved");
+    assert_eq!(resolution.definition(*ty).kind, DefKind::Struct);
+}
+
+#[test]
+fn test_undefined_names_and_types() {
+    let errors = resolve_errors(
+        r#"def f(p: Pointt) -> i32 do
+    q = Point { x: 1 }
+    y + missing(q)
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot find type `Pointt` in this scope",
+            "cannot find type `Point` in this scope",
+            "cannot find `y` in this scope",
+            "cannot find `missing` in this scope",
+        ]
+    );
+}
+
+#[test]
+fn test_locals_are_scoped_to_their_block() {
+    let errors = resolve_errors(
+        r#"def f(flag: boolean) do
+    if flag do
+        inner: i32 = 1
+    end
+    inner
+    for x in [1, 2] do
+        x
+    end
+    x
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot find `inner` in this scope",
+            "cannot find `x` in this scope"
+        ]
+    );
+}
+
+#[test]
+fn test_assignments_never_rebind_items() {
+    let source = r#"def x() do end
+def f() do
+    x = 1
+    print = 5
+    println(x, print)
+end"#;
+    let (module, resolution) = resolve(source);
+    assert!(
+        resolution.diagnostics.is_empty(),
+        "{:?}",
+        resolution.diagnostics
+    );
+    let args = call_args(&module);
+    let x = resolution.value(args[0].id).unwrap();
+    assert_eq!(x.kind, DefKind::Assigned);
+    assert_eq!(x.span.start, source.find("x = 1").unwrap());
+    let print = resolution.value(args[1].id).unwrap();
+    assert_eq!(print.kind, DefKind::Assigned);
+    assert_eq!(print.span.start, source.find("print = 5").unwrap());
+}
+
+#[test]
+fn test_duplicate_definitions() {
+    let (_, resolution) = resolve(
+        r#"defstruct P do
+    x: i32
+    x: i32
+end
+defimpl P do
+    def m(self) do end
+    def m(self) do end
+end
+def f(a: i32, a: i32) do
+    b: i32 = 1
+    b: i32 = 2
+    match (a, b) do
+        (c, c) -> c
+    end
+end
+def f() do end"#,
+    );
+    let messages: Vec<&str> = resolution
+        .diagnostics
+        .iter()
+        .map(|diag| diag.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "the name `f` is defined multiple times",
+            "the name `x` is defined multiple times",
+            "the name `m` is defined multiple times",
+            "the name `a` is defined multiple times",
+            "the name `b` is defined multiple times",
+            "the name `c` is defined multiple times",
+        ]
+    );
+    let note = &resolution.diagnostics[4].notes[0];
+    assert_eq!(note.message, "previous definition of `b` here");
+}
+
+#[test]
+fn test_shadowing_within_a_function_is_rejected() {
+    let errors = resolve_errors(
+        r#"def f(n: i32) do
+    total: i32 = 0
+    if n > 0 do
+        n: i32 = 1
+        total = n
+    end
+    for total in [1] do
+    end
+    g = fn n do
+        total: i32 = n
+    end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "`n` shadows a binding of an enclosing scope",
+            "`total` shadows a binding of an enclosing scope",
+        ]
+    );
+}
+
+#[test]
+fn test_compound_assignments_read_their_target() {
+    let errors = resolve_errors("def f() do\n    y += 1\n    y = 2\nend");
+    assert_eq!(errors, vec!["cannot find `y` in this scope"]);
+}
@@ -0,0 +1,696 @@
+use rot::parser::parser::parse_source;
+use rot::semantic_analyzer::type_checker::{check_module, MethodTarget};
+
+fn type_errors(source: &str) -> Vec<String> {
+    let module = parse_source(source).into_result().unwrap();
+    check_module(&module)
+        .diagnostics
+        .into_iter()
+        .map(|diag| diag.message)
+        .collect()
+}
+
+#[test]
+fn test_if_expression_branches_agree() {
+    let errors = type_errors(
+        r#"def f(c: boolean) do
+    y = if c do 1 else 2 end
+    z: u8 = if c do 1 else if !c do 2 else 3 end
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_if_expression_branch_mismatch() {
+    let errors = type_errors(
+        r#"def f(c: boolean) do
+    y = if c do 1 else "two" end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec!["`if` branches have incompatible types, expected `i32`, found `string`"]
+    );
+}
+
+#[test]
+fn test_if_statement_branches_may_differ() {
+    let errors = type_errors(
+        r#"def f(c: boolean) do
+    if c do
+        1
+    else
+        "two"
+    end
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_lambda_bodies_ending_in_if_statements() {
+    let errors = type_errors(
+        r#"def f() do
+    g = fn x do if x do print("p") end end
+    g(true)
+    h = fn x: boolean -> i32 do if x do 1 end end
+end"#,
+    );
+    assert_eq!(errors, vec!["`if` used as a value needs an `else` branch"]);
+}
+
+#[test]
+fn test_if_value_needs_else_and_boolean_condition() {
+    let errors = type_errors(
+        r#"def f(x: i32) do
+    y = if x do 1 end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected `boolean`, found `i32`",
+            "`if` used as a value needs an `else` branch"
+        ]
+    );
+}
+
+#[test]
+fn test_match_arms_and_patterns() {
+    let errors = type_errors(
+        r#"defstruct Point do
+    x: i32
+    y: i32
+end
+
+def f(p: Point, n: i32) do
+    a = match p do
+        Point{x: 0, y} if y -> y
+        Point{z} -> 1
+        _ -> "other"
+    end
+    b = match n do
+        1 | x -> 0
+        "one" -> 1
+        _ -> 2
+    end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected `boolean`, found `i32`",
+            "struct `Point` has no field `z`",
+            "`match` arms have incompatible types, expected `i32`, found `string`",
+            "`x` is not bound in every alternative",
+            "expected `i32`, found `string`",
+        ]
+    );
+}
+
+#[test]
+fn test_loop_break_values() {
+    let errors = type_errors(
+        r#"def f(n: i32, xs: [i32]) do
+    x: i32 = 0
+    y = while true do
+        if n > 1 do break x end
+        break "done"
+    end
+    z: i32? = for i in xs do
+        if i > n do break i end
+    end
+    for i in xs do
+        break
+    end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec!["`break` values have incompatible types, expected `i32`, found `string`"]
+    );
+}
+
+#[test]
+fn test_loops_may_end_without_a_break_value() {
+    let errors = type_errors(
+        r#"def f(n: i32) do
+    x: i32 = while false do break 5 end
+    for i in n do
+        break
+    end
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec!["expected `i32`, found `i32?`", "`i32` is not iterable"]
+    );
+}
+
+#[test]
+fn test_collection_literals_and_indexing() {
+    let errors = type_errors(
+        r#"def f(xs: [u8], m: {string => (i32, boolean)}) -> (u8, boolean) do
+    ys: [u8] = [1, 2, xs[0]]
+    m["a"] = (1, false)
+    for (k, v) in m do
+        v[1]
+    end
+    pair = m["b"]
+    (ys[0], pair[1])
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_collection_type_errors() {
+    let errors = type_errors(
+        r#"def f(m: {string => i32}, t: (i32, string)) do
+    xs = [1, "two"]
+    m[1]
+    t[2]
+    xs[true]
+    3[0]
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "list elements have incompatible types, expected `i32`, found `string`",
+            "expected `string`, found `i32`",
+            "index 2 is out of range for `(i32, string)`",
+            "list indices must be integers, found `boolean`",
+            "cannot index into a value of type `i32`",
+        ]
+    );
+}
+
+#[test]
+fn test_nullable_values_must_be_checked_before_use() {
+    let errors = type_errors(
+        r#"defstruct Person do
+    name: string
+end
+
+defimpl Person do
+    def greet(self) do
+    end
+end
+
+def f(p: Person?, xs: [i32]?, g: ((i32) -> i32)?) do
+    p.name
+    p.greet()
+    xs[0]
+    g(1)
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "cannot access field `name` on nullable `Person?`, check it against `null` first or use `?.`",
+            "cannot call method `greet` on nullable `Person?`, check it against `null` first or use `?.`",
+            "cannot index into nullable `[i32]?`, check it against `null` first",
+            "cannot call nullable `((i32) -> i32)?`, check it against `null` first",
+        ]
+    );
+}
+
+#[test]
+fn test_null_checks_safe_navigation_and_elvis() {
+    let errors = type_errors(
+        r#"defstruct Person do
+    name: string
+    nickname: string?
+end
+
+defimpl Person do
+    def greet(self) do
+    end
+end
+
+def f(p: Person?) -> string do
+    if p != null && p.name != "" do
+        p.name
+        p.nickname ?: p.name
+    end
+    if null != p do
+        p.greet()
+    end
+    p?.greet()
+    nick: string? = null
+    n: string = p?.nickname ?: "nobody"
+    p?.name ?: "nobody"
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_null_checks_narrow_else_branches_and_the_rest_of_the_block() {
+    let errors = type_errors(
+        r#"defstruct P do
+    x: i32
+end
+
+def f(p: P?, q: P?, s: P?) -> i32 do
+    if p == null do
+        return 0
+    end
+    a = p.x
+    if q == null || q.x > 3 do
+        a = 1
+    else if q.x < 0 do
+        a = q.x
+    else
+        a = q.x
+    end
+    if !(s != null) do
+        return 1
+    end
+    s.x
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_assignments_undo_null_checks() {
+    let errors = type_errors(
+        r#"defstruct P do
+    x: i32
+end
+
+def f(mut p: P?, c: boolean) do
+    if p != null do
+        p = null
+        p.x
+    end
+    p = P { x: 1 }
+    p.x
+    while c do
+        p.x
+        p = null
+    end
+    if p == null do
+        p.x
+    end
+end"#,
+    );
+    let message =
+        "cannot access field `x` on nullable `P?`, check it against `null` first or use `?.`";
+    assert_eq!(errors, vec![message, message, message]);
+}
+
+#[test]
+fn test_elvis_fallback_must_match() {
+    let errors = type_errors(
+        r#"def f(x: i32?) do
+    y = x ?: "zero"
+    z = x ?: null
+    z + 1
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "`?:` operands have incompatible types, expected `i32`, found `string`",
+            "cannot apply `+` to nullable `i32?`, check it against `null` first",
+        ]
+    );
+}
+
+#[test]
+fn test_methods_resolve_to_impls_then_free_functions() {
+    let module = parse_source(
+        r#"defstruct Stack do
+    items: [i32]
+end
+
+defimpl Stack do
+    def len(self) -> usize do
+        len(self.items)
+    end
+
+    static def empty() -> Stack do
+        Stack{items: []}
+    end
+end
+
+def len(xs: [i32]) -> usize do
+    0
+end
+
+def top(s: Stack) -> i32 do
+    s.items[0]
+end
+
+def f(xs: [i32]) -> usize do
+    s = Stack.empty()
+    t: i32 = s.top()
+    s.len() + xs.len()
+end"#,
+    )
+    .into_result()
+    .unwrap();
+    let results = check_module(&module);
+    assert!(results.diagnostics.is_empty(), "{:?}", results.diagnostics);
+    let mut targets: Vec<String> = results
+        .methods
+        .values()
+        .map(|target| match target {
+            MethodTarget::Method { ty, name } => format!("{}.{}", ty, name),
+            MethodTarget::Function(name) => name.clone(),
+        })
+        .collect();
+    targets.sort();
+    assert_eq!(targets, vec!["Stack.empty", "Stack.len", "len", "top"]);
+}
+
+#[test]
+fn test_unresolved_methods_and_fields() {
+    let errors = type_errors(
+        r#"defstruct P do
+    x: i32
+end
+
+defimpl P do
+    def get(self) -> i32 do
+        self.x
+    end
+end
+
+def len(xs: [i32]) -> usize do
+    0
+end
+
+def f(p: P, names: [string]) do
+    p.y
+    p.size()
+    names.len()
+    P.get()
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "struct `P` has no field `y`",
+            "no method `size` found for `P`",
+            "no method `len` found for `[string]`",
+            "no static method `get` found for `P`",
+        ]
+    );
+}
+
+#[test]
+fn test_pipeline_stages_are_checked_as_calls() {
+    let errors = type_errors(
+        r#"def twice(x: i32) -> i32 do
+    x * 2
+end
+
+def show(x: i32, width: usize) -> string do
+    ""
+end
+
+def f() -> string do
+    1 |> twice() |> twice |> show(4)
+end"#,
+    );
+    assert!(errors.is_empty(), "{:?}", errors);
+}
+
+#[test]
+fn test_pipeline_errors_name_the_stage() {
+    let errors = type_errors(
+        r#"def twice(x: i32) -> i32 do
+    x * 2
+end
+
+def show(x: i32) -> string do
+    ""
+end
+
+def add(x: i32, y: i32) -> i32 do
+    x + y
+end
+
+def f() do
+    1 |> show() |> twice()
+    2 |> twice(3)
+    3 |> add("x")
+    twice("four")
+    twice()
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "pipeline stage `twice` expects `i32`, found `string`",
+            "pipeline stage `twice` takes 1 argument but 2 were given, counting the piped value",
+            "pipeline stage `add` expects `i32` for argument 2, found `string`",
+            "expected `i32`, found `string`",
+            "`twice` takes 1 argument but 0 were given",
+        ]
+    );
+}
+
+#[test]
+fn test_operator_operands() {
+    let errors = type_errors(
+        r#"def f(a: i32, b: u8, u: u32, s: string, c: boolean) do
+    x = a + 1
+    y = 2 * b
+    t = s + "!"
+    ok = a < 3 && s >= "a" || !c
+    a + u
+    s - s
+    c < c
+    -s
+    a || c
+    a == s
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected `i32`, found `u32`",
+            "`-` cannot be applied to `string`",
+            "`<` cannot be applied to `boolean`",
+            "`-` cannot be applied to `string`",
+            "expected `boolean`, found `i32`",
+            "cannot compare `i32` with `string`",
+        ]
+    );
+}
+
+#[test]
+fn test_returns_match_the_declared_type() {
+    let errors = type_errors(
+        r#"def f(c: boolean) -> i32 do
+    if c do
+        return "early"
+    end
+    return
+end
+def g() -> string do
+    1
+end
+def h() -> i32 do
+    x: i32 = 1
+end
+def k() do
+    return 1
+end
+def ok(c: boolean) -> i32 do
+    if c do return 1 end
+    inc = fn n: i32 -> i32 do n + 1 end
+    inc(2)
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected `i32`, found `string`",
+            "expected `i32`, found `()`",
+            "expected `string`, found `i32`",
+            "expected `i32`, found `()`",
+            "expected `()`, found `i32`",
+        ]
+    );
+}
+
+#[test]
+fn test_assignments_match_declarations() {
+    let errors = type_errors(
+        r#"defstruct P do
+    public mut x: i32
+    label: string = 3
+end
+def f(mut p: P, mut n: u8) do
+    m: u8 = "one"
+    n = 300
+    n = "two"
+    p.x = true
+    q = P { x: "no", label: "a" }
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected `string`, found `i32`",
+            "expected `u8`, found `string`",
+            "expected `u8`, found `string`",
+            "expected `i32`, found `boolean`",
+            "expected `i32`, found `string`",
+            "literal `300` does not fit in `u8`, whose range is `0..=255`",
+        ]
+    );
+}
+
+#[test]
+fn test_compound_assignments() {
+    let errors = type_errors(
+        r#"def next() -> usize do
+    0
+end
+def f(mut xs: [i32], mut n: u8, mut s: string, mut w: i64) do
+    xs[next()] += 1
+    xs[next()] += "one"
+    n += 300
+    s += "!"
+    s -= "!"
+    w += n
+    n += w
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "expected `i32`, found `string`",
+            "`-` cannot be applied to `string`",
+            "expected `u8`, found `i64`",
+            "literal `300` does not fit in `u8`, whose range is `0..=255`",
+        ]
+    );
+}
+
+#[test]
+fn test_literals_take_their_type_from_use() {
+    let errors = type_errors(
+        r#"def f() do
+    x = 1
+    n: u8 = x
+    y = 2.5
+    z: float = y
+    w = 3
+    s: string = w
+    xs = []
+    ys: [i64] = xs
+end"#,
+    );
+    assert_eq!(errors, vec!["expected `string`, found `i32`"]);
+}
+
+#[test]
+fn test_lambda_parameters_are_inferred() {
+    let errors = type_errors(
+        r#"def apply(f: (i64) -> i64, x: i64) -> i64 do
+    f(x)
+end
+def g() do
+    add = fn x, y do x + y end
+    n: i64 = add(1, 2)
+    m: i64 = apply(fn x do x * 2 end, 3)
+    call = fn h do h(1) end
+    bad = add(1, true)
+end"#,
+    );
+    assert_eq!(errors, vec!["expected `i64`, found `boolean`"]);
+}
+
+#[test]
+fn test_ambiguous_types_need_annotations() {
+    let errors = type_errors(
+        r#"def f() do
+    some_lambda = fn x, y do x + y end
+    xs = []
+    m = {}
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "type annotations needed for `x`",
+            "type annotations needed for this empty list",
+            "type annotations needed for this empty map",
+        ]
+    );
+}
+
+#[test]
+fn test_integer_literals_fit_their_type() {
+    let errors = type_errors(
+        r#"def f() do
+    a: u8 = 255
+    b: u8 = 256
+    c: i8 = -128
+    d: i8 = -129
+    e: u16 = -1
+    x = 70000
+    g: u16 = x
+    h = 3000000000
+end"#,
+    );
+    assert_eq!(
+        errors,
+        vec![
+            "literal `256` does not fit in `u8`, whose range is `0..=255`",
+            "literal `-129` does not fit in `i8`, whose range is `-128..=127`",
+            "literal `-1` does not fit in `u16`, whose range is `0..=65535`",
+            "literal `70000` does not fit in `u16`, whose range is `0..=65535`",
+            "literal `3000000000` does not fit in `i32`, whose range is `-2147483648..=2147483647`",
+        ]
+    );
+}
+
+#[test]
+fn test_numbers_widen_but_only_narrow_with_as() {
+    let module = parse_source(
+        r#"def f(small: u8, big: i64, x: float, n: i32) do
+    a: i64 = small
+    b: u8 = big
+    c: double = x
+    d: double = n
+    e: u8 = big as u8
+    g: i32 = x as i32 + small
+    h = 300 as u8
+    s = "1" as i32
+end"#,
+    )
+    .into_result()
+    .unwrap();
+    let diagnostics = check_module(&module).diagnostics;
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        vec![
+            "expected `u8`, found `i64`",
+            "expected `double`, found `i32`",
+            "cannot convert `string` to `i32` with `as`, it only converts between numbers",
+            "literal `300` does not fit in `u8`, whose range is `0..=255`",
+        ]
+    );
+    let notes: Vec<&str> = diagnostics[..2]
+        .iter()
+        .map(|d| d.notes[0].message.as_str())
+        .collect();
+    assert_eq!(
+        notes,
+        vec![
+            "`u8` holds `0..=255`, convert with `as u8` if the value fits",
+            "convert with `as double`",
+        ]
+    );
+}